@@ -0,0 +1,81 @@
+// src/affects.rs - Active spell effects from GMCP char.affects, counted down locally
+use crate::telnet_client::Affect;
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// The `[affects]` config section.
+///
+/// `wear_off` is a regex matched against each output line; its `name` group
+/// (or first group) names an affect to drop, for servers that only announce
+/// expiry in text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AffectsConfig {
+    pub wear_off: Option<String>,
+}
+
+struct ActiveAffect {
+    name: String,
+    /// `None` for affects without a known duration.
+    expires: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct AffectTracker {
+    active: Vec<ActiveAffect>,
+    wear_off: Option<Regex>,
+}
+
+impl AffectTracker {
+    pub fn from_config(cfg: &AffectsConfig) -> Self {
+        let wear_off = cfg.wear_off.as_deref().and_then(|pattern| match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                warn!("Invalid affects wear_off pattern '{}': {}", pattern, e);
+                None
+            }
+        });
+        Self { active: Vec::new(), wear_off }
+    }
+
+    /// Replaces the list with a fresh char.affects update.
+    pub fn set(&mut self, affects: Vec<Affect>) {
+        let now = Instant::now();
+        self.active = affects
+            .into_iter()
+            .map(|a| ActiveAffect {
+                name: a.name,
+                expires: a.duration.filter(|&secs| secs >= 0).map(|secs| now + Duration::from_secs(secs as u64)),
+            })
+            .collect();
+    }
+
+    /// Drops the affect named by a wear-off message, if `line` is one.
+    pub fn on_line(&mut self, line: &str) {
+        let Some(caps) = self.wear_off.as_ref().and_then(|re| re.captures(line)) else {
+            return;
+        };
+        let Some(name) = caps.name("name").or_else(|| caps.get(1)) else {
+            return;
+        };
+        let name = name.as_str().trim();
+        self.active.retain(|a| !a.name.eq_ignore_ascii_case(name));
+    }
+
+    /// The affects still running with their time left (`None` = no known
+    /// end). Ones whose countdown reached zero are dropped.
+    pub fn remaining(&mut self) -> Vec<(&str, Option<Duration>)> {
+        let now = Instant::now();
+        self.active.retain(|a| a.expires.is_none_or(|end| end > now));
+        self.active
+            .iter()
+            .map(|a| (a.name.as_str(), a.expires.map(|end| end - now)))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+}
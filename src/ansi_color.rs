@@ -1,9 +1,9 @@
 // src/ansi_color.rs
 
-use ratatui::style::{Color, Style};
-use ratatui::text::Span;
+use crate::styled::{Rgb, StyledLine, TextStyle};
 use std::collections::HashMap;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 
 /// Build a complete color mapping.
 ///
@@ -13,49 +13,48 @@ use lazy_static::lazy_static;
 ///   - 256‑color sequences (using keys like "38;5;X")
 ///
 /// Note: For background 256‑colors, we will later convert the code from "48;5;X" to "38;5;X".
-pub fn generate_xterm_color_map() -> HashMap<&'static str, Color> {
+pub fn generate_xterm_color_map() -> HashMap<&'static str, Rgb> {
     let mut color_map = HashMap::new();
 
     // Standard foreground colors (using egui values)
-    color_map.insert("0;30", Color::Rgb(0, 0, 0));           // Black (dim)
-    color_map.insert("0;31", Color::Rgb(128, 0, 0));         // Dark Red (dim)
-    color_map.insert("0;32", Color::Rgb(0, 128, 0));         // Dark Green (dim)
-    color_map.insert("0;33", Color::Rgb(128, 128, 0));       // Dark Yellow (dim)
-    color_map.insert("0;34", Color::Rgb(0, 0, 128));         // Dark Blue (dim)
-    color_map.insert("0;35", Color::Rgb(128, 0, 128));       // Dark Magenta (dim)
-    color_map.insert("0;36", Color::Rgb(0, 128, 128));       // Dark Cyan (dim)
-    color_map.insert("0;37", Color::Rgb(192, 192, 192));     // Light Gray (dim)
-    color_map.insert("1;30", Color::Rgb(128, 128, 128));     // Dark Gray (bright)
-    color_map.insert("1;31", Color::Rgb(255, 0, 0));         // Red (bright)
-    color_map.insert("1;32", Color::Rgb(0, 255, 0));         // Green (bright)
-    color_map.insert("1;33", Color::Rgb(255, 255, 0));       // Yellow (bright)
-    color_map.insert("1;34", Color::Rgb(0, 0, 255));         // Blue (bright)
-    color_map.insert("1;35", Color::Rgb(255, 0, 255));       // Magenta (bright)
-    color_map.insert("1;36", Color::Rgb(0, 255, 255));       // Cyan (bright)
-    color_map.insert("1;37", Color::Rgb(255, 255, 255));     // White (bright)
+    color_map.insert("0;30", Rgb(0, 0, 0));           // Black (dim)
+    color_map.insert("0;31", Rgb(128, 0, 0));         // Dark Red (dim)
+    color_map.insert("0;32", Rgb(0, 128, 0));         // Dark Green (dim)
+    color_map.insert("0;33", Rgb(128, 128, 0));       // Dark Yellow (dim)
+    color_map.insert("0;34", Rgb(0, 0, 128));         // Dark Blue (dim)
+    color_map.insert("0;35", Rgb(128, 0, 128));       // Dark Magenta (dim)
+    color_map.insert("0;36", Rgb(0, 128, 128));       // Dark Cyan (dim)
+    color_map.insert("0;37", Rgb(192, 192, 192));     // Light Gray (dim)
+    color_map.insert("1;30", Rgb(128, 128, 128));     // Dark Gray (bright)
+    color_map.insert("1;31", Rgb(255, 0, 0));         // Red (bright)
+    color_map.insert("1;32", Rgb(0, 255, 0));         // Green (bright)
+    color_map.insert("1;33", Rgb(255, 255, 0));       // Yellow (bright)
+    color_map.insert("1;34", Rgb(0, 0, 255));         // Blue (bright)
+    color_map.insert("1;35", Rgb(255, 0, 255));       // Magenta (bright)
+    color_map.insert("1;36", Rgb(0, 255, 255));       // Cyan (bright)
+    color_map.insert("1;37", Rgb(255, 255, 255));     // White (bright)
 
     // Build mapping for xterm 256 colors (for foreground)
     for i in 0..=255 {
         let key = format!("38;5;{}", i);
         let color = if i < 16 {
             match i {
-                0 => Color::Rgb(0, 0, 0),
-                1 => Color::Rgb(128, 0, 0),
-                2 => Color::Rgb(0, 128, 0),
-                3 => Color::Rgb(128, 128, 0),
-                4 => Color::Rgb(0, 0, 128),
-                5 => Color::Rgb(128, 0, 128),
-                6 => Color::Rgb(0, 128, 128),
-                7 => Color::Rgb(192, 192, 192),
-                8 => Color::Rgb(128, 128, 128),
-                9 => Color::Rgb(255, 0, 0),
-                10 => Color::Rgb(0, 255, 0),
-                11 => Color::Rgb(255, 255, 0),
-                12 => Color::Rgb(0, 0, 255),
-                13 => Color::Rgb(255, 0, 255),
-                14 => Color::Rgb(0, 255, 255),
-                15 => Color::Rgb(255, 255, 255),
-                _ => Color::Reset,
+                0 => Rgb(0, 0, 0),
+                1 => Rgb(128, 0, 0),
+                2 => Rgb(0, 128, 0),
+                3 => Rgb(128, 128, 0),
+                4 => Rgb(0, 0, 128),
+                5 => Rgb(128, 0, 128),
+                6 => Rgb(0, 128, 128),
+                7 => Rgb(192, 192, 192),
+                8 => Rgb(128, 128, 128),
+                9 => Rgb(255, 0, 0),
+                10 => Rgb(0, 255, 0),
+                11 => Rgb(255, 255, 0),
+                12 => Rgb(0, 0, 255),
+                13 => Rgb(255, 0, 255),
+                14 => Rgb(0, 255, 255),
+                _ => Rgb(255, 255, 255),
             }
         } else if i < 232 {
             // 6×6×6 color cube.
@@ -63,11 +62,11 @@ pub fn generate_xterm_color_map() -> HashMap<&'static str, Color> {
             let r = (index / 36) * 51;
             let g = ((index % 36) / 6) * 51;
             let b = (index % 6) * 51;
-            Color::Rgb(r as u8, g as u8, b as u8)
+            Rgb(r as u8, g as u8, b as u8)
         } else {
             // Grayscale ramp.
             let gray = 8 + (i - 232) * 10;
-            Color::Rgb(gray as u8, gray as u8, gray as u8)
+            Rgb(gray as u8, gray as u8, gray as u8)
         };
         let leaked_key = Box::leak(key.into_boxed_str());
         color_map.insert(leaked_key, color);
@@ -76,7 +75,208 @@ pub fn generate_xterm_color_map() -> HashMap<&'static str, Color> {
 }
 
 lazy_static! {
-    pub static ref COLOR_MAP: HashMap<&'static str, Color> = generate_xterm_color_map();
+    pub static ref COLOR_MAP: HashMap<&'static str, Rgb> = generate_xterm_color_map();
+}
+
+/// Single-letter `$` color markers used in MUD text (`$R`, `$g`, ...):
+/// uppercase is bright, lowercase dim, `$D` dark gray. `$n` and `$0` reset to
+/// the default color (`None`).
+pub static MARKER_COLORS: [(char, Option<Rgb>); 17] = [
+    ('r', Some(Rgb(128, 0, 0))),
+    ('R', Some(Rgb(255, 0, 0))),
+    ('g', Some(Rgb(0, 128, 0))),
+    ('G', Some(Rgb(0, 255, 0))),
+    ('y', Some(Rgb(128, 128, 0))),
+    ('Y', Some(Rgb(255, 255, 0))),
+    ('b', Some(Rgb(0, 0, 128))),
+    ('B', Some(Rgb(0, 0, 255))),
+    ('m', Some(Rgb(128, 0, 128))),
+    ('M', Some(Rgb(255, 0, 255))),
+    ('c', Some(Rgb(0, 128, 128))),
+    ('C', Some(Rgb(0, 255, 255))),
+    ('w', Some(Rgb(192, 192, 192))),
+    ('W', Some(Rgb(255, 255, 255))),
+    ('D', Some(Rgb(128, 128, 128))),
+    ('n', None),
+    ('0', None),
+];
+
+/// Looks up a single-letter marker: `None` if `letter` is not a marker,
+/// `Some(None)` for a reset marker.
+pub fn marker_color(letter: char) -> Option<Option<Rgb>> {
+    MARKER_COLORS.iter().find(|(c, _)| *c == letter).map(|(_, color)| *color)
+}
+
+/// Looks up an xterm 256-color index in `COLOR_MAP`.
+pub fn xterm_color(index: u8) -> Option<Rgb> {
+    COLOR_MAP.get(format!("38;5;{}", index).as_str()).copied()
+}
+
+/// Converts an inline marker (like "$x196" or "$G") into a color using the
+/// marker table. This is the one implementation used for both chat text and
+/// the gauges. `None` means the default color: a reset marker, or one
+/// that names no known color.
+pub fn convert_color_marker(marker: &str) -> Option<Rgb> {
+    if let Some(num_str) = marker.strip_prefix("$x") {
+        return xterm_color(num_str.parse().ok()?);
+    }
+    let mut letters = marker.strip_prefix('$')?.chars();
+    match (letters.next(), letters.next()) {
+        (Some(letter), None) => marker_color(letter).flatten(),
+        _ => None,
+    }
+}
+
+/// Removes well-formed inline color markers from `text`: `$` and a marker
+/// letter, or `$x` and an xterm color index (0-255). A marker followed by a
+/// further digit (`$05`, `$x2560`) is not one, and everything else, line
+/// breaks included, is kept as it is.
+///
+/// ```
+/// use mud_tui::ansi_color::strip_color_markers;
+///
+/// assert_eq!(strip_color_markers("$RRed$0 and $x196bright$n"), "Red and bright");
+/// assert_eq!(strip_color_markers("It costs $05, $x or $x999."), "It costs $05, $x or $x999.");
+/// assert_eq!(strip_color_markers("one$G\ntwo"), "one\ntwo");
+/// ```
+pub fn strip_color_markers(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        match marker_len(after) {
+            Some(len) => rest = &after[len..],
+            None => {
+                out.push('$');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the marker that `after` (the text after a `$`) starts with.
+fn marker_len(after: &str) -> Option<usize> {
+    let len = match after.strip_prefix('x') {
+        Some(digits) => {
+            let count = digits.bytes().take_while(u8::is_ascii_digit).count();
+            digits[..count].parse::<u8>().ok()?;
+            1 + count
+        }
+        None => {
+            let letter = after.chars().next()?;
+            marker_color(letter)?;
+            letter.len_utf8()
+        }
+    };
+    if after[len..].starts_with(|c: char| c.is_ascii_digit()) {
+        None
+    } else {
+        Some(len)
+    }
+}
+
+/// Character set of the text a server sends (`connection.encoding`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    /// ISO-8859-1: every byte is the code point of the same value.
+    #[serde(rename = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    /// The IBM PC character set, with its box-drawing and block characters.
+    #[serde(rename = "cp437", alias = "ibm437")]
+    Cp437,
+}
+
+/// Unicode for CP437 bytes 0x80-0xFF; the lower half is ASCII.
+static CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Decodes server bytes to text. Invalid UTF-8 becomes U+FFFD; the
+/// single-byte encodings map every byte.
+///
+/// ```
+/// use mud_tui::ansi_color::{decode, Encoding};
+///
+/// assert_eq!(decode(&[0xC9, 0xCD, 0xCD, 0xBB], Encoding::Cp437), "╔══╗");
+/// assert_eq!(decode(&[0xBA, b' ', b'o', b'k', b' ', 0xBA], Encoding::Cp437), "║ ok ║");
+/// assert_eq!(decode(&[0xC8, 0xCD, 0xBC], Encoding::Cp437), "╚═╝");
+/// assert_eq!(decode(b"caf\xe9", Encoding::Latin1), "café");
+/// assert_eq!(decode("café".as_bytes(), Encoding::Utf8), "café");
+/// ```
+pub fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        Encoding::Cp437 => bytes
+            .iter()
+            .map(|&b| if b < 0x80 { b as char } else { CP437_HIGH[(b - 0x80) as usize] })
+            .collect(),
+    }
+}
+
+/// Holds back a UTF-8 character cut off at the end of a read until the next
+/// read completes it, so it is not decoded as two U+FFFD.
+///
+/// ```
+/// use mud_tui::ansi_color::{decode, Encoding, Utf8Carry};
+///
+/// // "€" is E2 82 AC; the read boundary falls after its first byte.
+/// let mut carry = Utf8Carry::default();
+/// let first = carry.feed(b"Price: 5\xE2");
+/// assert_eq!(decode(&first, Encoding::Utf8), "Price: 5");
+/// let second = carry.feed(b"\x82\xAC each\n");
+/// assert_eq!(decode(&second, Encoding::Utf8), "€ each\n");
+///
+/// // Invalid bytes are passed on (and decode to U+FFFD), not held forever.
+/// assert_eq!(carry.feed(b"bad \x82"), b"bad \x82");
+/// ```
+#[derive(Debug, Default)]
+pub struct Utf8Carry {
+    pending: Vec<u8>,
+}
+
+impl Utf8Carry {
+    /// `data` after the bytes held back by the last call, less a trailing
+    /// incomplete character, which is held back for the next call.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(data);
+        let keep = incomplete_tail(&bytes);
+        self.pending = bytes.split_off(bytes.len() - keep);
+        bytes
+    }
+}
+
+/// The length of the incomplete UTF-8 sequence ending `bytes`, or 0.
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    // A sequence is at most 4 bytes, so its lead byte is within the last 3.
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue; // Continuation byte
+        }
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 0,
+        };
+        return if needed > back { back } else { 0 };
+    }
+    0
 }
 
 /// Strip MXP tags from the input string.
@@ -87,28 +287,77 @@ pub fn strip_mxp_tags(input: &str) -> String {
 }
 
 /// Parse ANSI escape sequences from raw bytes (converted to a UTF‑8 string)
-/// into lines of styled spans. This parser preserves Unicode and supports both
+/// into styled lines. This parser preserves Unicode and supports both
 /// foreground and background colors. When an SGR sequence is encountered:
 /// - If the parameter string is bare (e.g. "35"), we prepend "0;" so that it is dim.
 /// - If a background 256‑color sequence ("48;5;X") is encountered, we convert it
 ///   to a foreground lookup key ("38;5;X") for color lookup.
-pub fn parse_ansi_codes(buffer: Vec<u8>) -> Vec<Vec<Span<'static>>> {
-    // Convert raw bytes to a UTF‑8 string (lossy conversion preserves Unicode)
-    let raw_input = String::from_utf8_lossy(&buffer);
+///
+/// Carriage returns and BEL (0x07) bytes are dropped from the text. OSC
+/// sequences (`ESC ]` ... BEL or `ESC \`) are removed too; an OSC 8 hyperlink
+/// keeps its text, with the URL in the segments' `link`. DCS sequences such
+/// as sixel images (`ESC P` ... `ESC \`), and the similar SOS, PM and APC
+/// strings, are skipped whole; drawing them would overwrite the TUI's layout.
+/// A background still set at the end of a line is recorded in its `fill_bg`.
+///
+/// ```
+/// use mud_tui::parse_ansi_codes;
+///
+/// let lines = parse_ansi_codes(b"You are \x1b[31mpaged\x07 by Bob.\r\n".to_vec());
+/// assert_eq!(lines.len(), 1);
+/// assert_eq!(lines[0].text(), "You are paged by Bob.");
+/// assert!(lines[0].segments.iter().all(|s| !s.text.contains('\x07')));
+///
+/// let link = b"\x1b[32mSee \x1b]8;;https://example.com\x1b\\the wiki\x1b]8;;\x1b\\ now\x1b[0m\n";
+/// let lines = parse_ansi_codes(link.to_vec());
+/// assert_eq!(lines[0].text(), "See the wiki now");
+/// let linked: Vec<_> = lines[0].segments.iter().filter(|s| s.link.is_some()).collect();
+/// assert_eq!(linked.len(), 1);
+/// assert_eq!(linked[0].text, "the wiki");
+/// assert_eq!(linked[0].link.as_deref(), Some("https://example.com"));
+/// assert!(lines[0].segments.iter().all(|s| s.style.fg.is_some()));
+///
+/// // A bare `ESC [m` and `ESC [;m` reset like `ESC [0m`.
+/// for reset in ["\x1b[m", "\x1b[;m"] {
+///     let text = format!("\x1b[31m\x1b[44mred{}plain\n", reset);
+///     let lines = parse_ansi_codes(text.into_bytes());
+///     assert!(lines[0].segments[0].style.bg.is_some());
+///     let plain = lines[0].segments.iter().find(|s| s.text == "plain").unwrap();
+///     assert_eq!((plain.style.fg, plain.style.bg), (None, None));
+/// }
+///
+/// // A sixel image (a DCS string) leaves nothing behind, not even its newlines.
+/// let sixel = b"Map: \x1bP0;1;0q\"1;1;4;4#0;2;0;0;0#0!4~-\n#0!4~\x1b\\ done\n";
+/// let lines = parse_ansi_codes(sixel.to_vec());
+/// assert_eq!(lines.len(), 1);
+/// assert_eq!(lines[0].text(), "Map:  done");
+/// ```
+pub fn parse_ansi_codes(buffer: Vec<u8>) -> Vec<StyledLine> {
+    parse_ansi_codes_as(&buffer, Encoding::Utf8)
+}
+
+/// `parse_ansi_codes` for servers that send text in another `encoding`.
+pub fn parse_ansi_codes_as(buffer: &[u8], encoding: Encoding) -> Vec<StyledLine> {
+    parse_ansi_codes_with_bell(buffer, encoding).0
+}
+
+/// `parse_ansi_codes_as`, also telling whether the text rang the bell: a BEL
+/// in the text itself, not one that ends an OSC sequence or sits in a DCS string.
+pub(crate) fn parse_ansi_codes_with_bell(buffer: &[u8], encoding: Encoding) -> (Vec<StyledLine>, bool) {
+    let raw_input = decode(buffer, encoding);
     // First, strip MXP tags from the input.
     let input = strip_mxp_tags(&raw_input);
     let mut results = Vec::new();
-    let mut current_line = Vec::new();
+    let mut current_line = StyledLine::default();
     let mut current_text = String::new();
-    // Default state: white foreground, no background.
-    let mut current_fg: Color = Color::White;
-    let mut current_bg: Option<Color> = None;
-    let mut current_style = Style::default().fg(current_fg);
-    if let Some(bg) = current_bg {
-        current_style = current_style.bg(bg);
-    }
+    // Default state: default foreground, no background.
+    let mut current_fg: Option<Rgb> = None;
+    let mut current_bg: Option<Rgb> = None;
+    // Target of the OSC 8 hyperlink the text is currently inside.
+    let mut current_link: Option<String> = None;
+    let mut bell = false;
 
-    enum State { Normal, Escaped, Parsing(String) }
+    enum State { Normal, Escaped, Parsing(String), Osc(String), OscEscaped(String), Dcs, DcsEscaped }
     let mut state = State::Normal;
     for ch in input.chars() {
         match state {
@@ -116,20 +365,19 @@ pub fn parse_ansi_codes(buffer: Vec<u8>) -> Vec<Vec<Span<'static>>> {
                 if ch == '\x1B' {
                     state = State::Escaped;
                     if !current_text.is_empty() {
-                        current_style = Style::default().fg(current_fg);
-                        if let Some(bg) = current_bg { current_style = current_style.bg(bg); }
-                        current_line.push(Span::styled(current_text.clone(), current_style));
-                        current_text.clear();
+                        let style = TextStyle { fg: current_fg, bg: current_bg, ..TextStyle::default() };
+                        current_line.push_link(std::mem::take(&mut current_text), style, current_link.clone());
                     }
                 } else if ch == '\n' {
                     if !current_text.is_empty() {
-                        current_style = Style::default().fg(current_fg);
-                        if let Some(bg) = current_bg { current_style = current_style.bg(bg); }
-                        current_line.push(Span::styled(current_text.clone(), current_style));
-                        current_text.clear();
+                        let style = TextStyle { fg: current_fg, bg: current_bg, ..TextStyle::default() };
+                        current_line.push_link(std::mem::take(&mut current_text), style, current_link.clone());
                     }
-                    results.push(current_line);
-                    current_line = Vec::new();
+                    current_line.fill_bg = current_bg;
+                    results.push(std::mem::take(&mut current_line));
+                } else if ch == '\x07' {
+                    // BEL is reported separately by the client, never drawn.
+                    bell = true;
                 } else if ch != '\r' {
                     current_text.push(ch);
                 }
@@ -137,6 +385,10 @@ pub fn parse_ansi_codes(buffer: Vec<u8>) -> Vec<Vec<Span<'static>>> {
             State::Escaped => {
                 if ch == '[' {
                     state = State::Parsing(String::new());
+                } else if ch == ']' {
+                    state = State::Osc(String::new());
+                } else if matches!(ch, 'P' | 'X' | '^' | '_') {
+                    state = State::Dcs;
                 } else {
                     state = State::Normal;
                     current_text.push(ch);
@@ -145,69 +397,155 @@ pub fn parse_ansi_codes(buffer: Vec<u8>) -> Vec<Vec<Span<'static>>> {
             State::Parsing(ref mut code_str) => {
                 if ch == 'm' {
                     // Finished reading an SGR sequence.
-                    let code = code_str.clone();
-                    // log::debug("Parsed SGR code: {}", code);
-                    if code == "0" {
-                        current_fg = Color::White;
-                        current_bg = None;
-                    } else if code.starts_with("38;5;") {
-                        // 256-color foreground.
-                        if let Some(&color) = COLOR_MAP.get(code.as_str()) {
-                            current_fg = color;
-                        } else {
-                            // log::debug("Foreground key not found: {}", code);
-                        }
-                    } else if code.starts_with("48;5;") {
-                        // 256-color background.
-                        let fg_key = code.replacen("48;5;", "38;5;", 1);
-                        if let Some(&color) = COLOR_MAP.get(fg_key.as_str()) {
-                            current_bg = Some(color);
-                        } else {
-                            // log::debug("Background key not found: {}", code);
-                        }
-                    } else if ["40","41","42","43","44","45","46","47"].contains(&code.as_str()) {
-                        // Standard background codes.
-                        let bg_color = match code {
-                            ref s if *s == "40" => Color::Rgb(0, 0, 0),
-                            ref s if *s == "41" => Color::Rgb(128, 0, 0),
-                            ref s if *s == "42" => Color::Rgb(0, 128, 0),
-                            ref s if *s == "43" => Color::Rgb(128, 128, 0),
-                            ref s if *s == "44" => Color::Rgb(0, 0, 128),
-                            ref s if *s == "45" => Color::Rgb(128, 0, 128),
-                            ref s if *s == "46" => Color::Rgb(0, 128, 128),
-                            ref s if *s == "47" => Color::Rgb(192, 192, 192),
-                            _ => Color::Reset,
-                        };
-                        current_bg = Some(bg_color);
-                    } else if ["90","91","92","93","94","95","96","97"].contains(&code.as_str()) {
-                        // Explicit bright foreground.
-                        let key = format!("1;{}", code);
-                        if let Some(&color) = COLOR_MAP.get(key.as_str()) {
-                            current_fg = color;
-                        }
-                    } else {
-                        // For standard foreground codes: if bare (no semicolon), prepend "0;".
-                        let key = if code.contains(";") { code } else { format!("0;{}", code) };
-                        if let Some(&color) = COLOR_MAP.get(key.as_str()) {
-                            current_fg = color;
-                        } else {
-                            // log::debug("SGR code not found in COLOR_MAP: {}", key);
-                        }
-                    }
+                    apply_sgr(code_str, &mut current_fg, &mut current_bg);
                     state = State::Normal;
                 } else {
                     code_str.push(ch);
                 }
             }
+            // An OSC sequence ends with BEL or the string terminator `ESC \`.
+            State::Osc(ref mut osc) => {
+                if ch == '\x07' {
+                    apply_osc(osc, &mut current_link);
+                    state = State::Normal;
+                } else if ch == '\x1B' {
+                    state = State::OscEscaped(std::mem::take(osc));
+                } else {
+                    osc.push(ch);
+                }
+            }
+            // DCS, SOS, PM and APC strings run to the string terminator `ESC \`.
+            State::Dcs => {
+                if ch == '\x1B' {
+                    state = State::DcsEscaped;
+                }
+            }
+            State::DcsEscaped => {
+                state = match ch {
+                    '\\' => State::Normal,
+                    '\x1B' => State::DcsEscaped,
+                    _ => State::Dcs,
+                };
+            }
+            State::OscEscaped(ref osc) => {
+                apply_osc(osc, &mut current_link);
+                // Anything but `\` after the ESC starts the next sequence.
+                state = match ch {
+                    '\\' => State::Normal,
+                    '[' => State::Parsing(String::new()),
+                    ']' => State::Osc(String::new()),
+                    _ => {
+                        current_text.push(ch);
+                        State::Normal
+                    }
+                };
+            }
         }
     }
     if !current_text.is_empty() {
-        current_style = Style::default().fg(current_fg);
-        if let Some(bg) = current_bg { current_style = current_style.bg(bg); }
-        current_line.push(Span::styled(current_text, current_style));
+        let style = TextStyle { fg: current_fg, bg: current_bg, ..TextStyle::default() };
+        current_line.push_link(current_text, style, current_link);
     }
     if !current_line.is_empty() {
+        current_line.fill_bg = current_bg;
         results.push(current_line);
     }
-    results
+    (results, bell)
+}
+
+/// Applies a finished OSC sequence. Only OSC 8 (`8;params;URL`) matters: a
+/// URL opens a hyperlink, an empty one closes it. Others (window titles etc.)
+/// are dropped.
+fn apply_osc(osc: &str, link: &mut Option<String>) {
+    if let Some(rest) = osc.strip_prefix("8;") {
+        let url = rest.split_once(';').map_or("", |(_, url)| url);
+        *link = (!url.is_empty()).then(|| url.to_string());
+    }
+}
+
+/// Applies one SGR parameter string (the part between `ESC [` and `m`) to the
+/// current colors. Unknown codes leave them unchanged.
+///
+/// Empty parameters count as `0`, so a bare `ESC [m` or `ESC [;m` is a full
+/// reset, and a leading `0` (`ESC [;31m`, `ESC [0;31m`) resets before the
+/// color is applied.
+pub(crate) fn apply_sgr(code: &str, fg: &mut Option<Rgb>, bg: &mut Option<Rgb>) {
+    let normalized = code
+        .split(';')
+        .map(|param| if param.is_empty() { "0" } else { param })
+        .collect::<Vec<_>>()
+        .join(";");
+    let code = normalized.as_str();
+    if code.split(';').all(|param| param == "0") {
+        *fg = None;
+        *bg = None;
+        return;
+    }
+    if code.starts_with("0;") {
+        *fg = None;
+        *bg = None;
+    }
+    if code.starts_with("38;5;") {
+        // 256-color foreground.
+        if let Some(&color) = COLOR_MAP.get(code) {
+            *fg = Some(color);
+        }
+    } else if code.starts_with("48;5;") {
+        // 256-color background.
+        let fg_key = code.replacen("48;5;", "38;5;", 1);
+        if let Some(&color) = COLOR_MAP.get(fg_key.as_str()) {
+            *bg = Some(color);
+        }
+    } else if ["40","41","42","43","44","45","46","47"].contains(&code) {
+        // Standard background codes.
+        let bg_color = match code {
+            "40" => Rgb(0, 0, 0),
+            "41" => Rgb(128, 0, 0),
+            "42" => Rgb(0, 128, 0),
+            "43" => Rgb(128, 128, 0),
+            "44" => Rgb(0, 0, 128),
+            "45" => Rgb(128, 0, 128),
+            "46" => Rgb(0, 128, 128),
+            _ => Rgb(192, 192, 192),
+        };
+        *bg = Some(bg_color);
+    } else if ["90","91","92","93","94","95","96","97"].contains(&code) {
+        // Explicit bright foreground.
+        let key = format!("1;{}", code);
+        if let Some(&color) = COLOR_MAP.get(key.as_str()) {
+            *fg = Some(color);
+        }
+    } else {
+        // For standard foreground codes: if bare (no semicolon), prepend "0;".
+        let key = if code.contains(';') { code.to_string() } else { format!("0;{}", code) };
+        if let Some(&color) = COLOR_MAP.get(key.as_str()) {
+            *fg = Some(color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bell_in_text_rings() {
+        let (lines, bell) = parse_ansi_codes_with_bell(b"You are paged\x07 by Bob.\n", Encoding::Utf8);
+        assert!(bell);
+        assert_eq!(lines[0].text(), "You are paged by Bob.");
+    }
+
+    #[test]
+    fn bel_ending_an_osc_link_does_not_ring() {
+        let link = b"See \x1b]8;;https://example.com\x07the wiki\x1b]8;;\x07 now\n";
+        let (lines, bell) = parse_ansi_codes_with_bell(link, Encoding::Utf8);
+        assert!(!bell);
+        assert_eq!(lines[0].text(), "See the wiki now");
+    }
+
+    #[test]
+    fn bel_inside_a_dcs_string_does_not_ring() {
+        let (_, bell) = parse_ansi_codes_with_bell(b"Map: \x1bPq\x07#0!4~\x1b\\ done\n", Encoding::Utf8);
+        assert!(!bell);
+    }
 }
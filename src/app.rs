@@ -0,0 +1,1505 @@
+// src/app.rs - Client state and the handling of messages from the connection
+use crate::affects::AffectTracker;
+use crate::autologin::{AutoLogin, LoginSend};
+use crate::away::Away;
+use crate::clipboard::Clipboard;
+use crate::commands;
+use crate::config::{parse_color, BellMode, Config, GroupPanel, HistoryDedup, LongLines, UiConfig, CONFIG_FILE};
+use crate::gauges::{GaugeLabel, TnlProgress};
+use crate::gmcp_store::GMCPStore;
+use crate::inspector::GmcpInspector;
+use crate::inventory::Inventory;
+use crate::macros::Macros;
+use crate::mapper::Mapper;
+use crate::prompt::{PromptParser, PromptVitals};
+use crate::scripting::{self, ScriptEngine};
+use crate::session_log::SessionLog;
+use crate::styled::StyledLine;
+use crate::skills::SkillTracker;
+use crate::status_socket::StatusPublisher;
+use crate::substitutions::Substitutions;
+use crate::tick::TickTracker;
+use crate::telnet_client::{CharStatus, DisconnectReason, GroupInfo, RoomInfo, TelnetClient, TelnetMessage};
+use crate::timers::Timers;
+use crate::triggers::{TriggerAction, Triggers};
+use crate::ui::{chat_lines, exit_list, max_scroll, plain_text, ring_bell, to_spans};
+use log::{error, info};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Server connected to at startup; `/connect` switches servers at runtime.
+pub(crate) const DEFAULT_HOST: &str = "darkwiz.org";
+pub(crate) const DEFAULT_PORT: &str = "6969";
+
+/// A change to the open sessions, asked for by `/session` or a session key.
+/// The UI loop owns the sessions and carries it out before the next draw.
+pub(crate) enum SessionRequest {
+    /// Open a session for this host and port and show it.
+    Open(String, String),
+    /// Show the session at this index.
+    Switch(usize),
+    Next,
+    Previous,
+    /// Close the shown session.
+    Close,
+    /// List the sessions in the output.
+    List,
+}
+
+/// How long the output pane border stays highlighted after a server bell.
+pub(crate) const BELL_FLASH: Duration = Duration::from_millis(300);
+
+/// How long the status bar's tell notice stays highlighted after a tell.
+pub(crate) const TELL_FLASH: Duration = Duration::from_secs(3);
+
+/// Lines of tells kept for the F7 list.
+const MAX_TELLS: usize = 20;
+
+/// Commands kept in the input history; the oldest go first.
+const MAX_HISTORY: usize = 500;
+
+/// How long a first Ctrl+C waits for the second one that confirms quitting.
+const QUIT_CONFIRM_WINDOW: Duration = Duration::from_secs(3);
+
+/// Holds personal gauge data
+#[derive(Clone, Debug)]
+pub struct Vitals {
+    pub hp: i32,
+    pub mana: i32,
+    pub movement: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MaxStats {
+    pub maxhp: i32,
+    pub maxmana: i32,
+    pub maxmove: i32,
+}
+
+/// The scrollable panes; keyboard scrolling acts on the focused one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Pane {
+    Main,
+    Chat,
+}
+
+/// Where a line of the output pane came from, so that whatever reacts to
+/// server output can leave the client's own lines alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LineSource {
+    /// Sent by the MUD.
+    Server,
+    /// A command the client sent, echoed.
+    LocalEcho,
+    /// A notice from the client itself: command replies, GMCP debug lines,
+    /// connection status, banners.
+    System,
+}
+
+/// One line of the output pane.
+pub(crate) struct OutputLine {
+    pub(crate) spans: Vec<Span<'static>>,
+    pub(crate) source: LineSource,
+}
+
+pub(crate) struct AppState {
+    pub(crate) mud_output: VecDeque<OutputLine>,
+    pub(crate) chat_output: VecDeque<Vec<Span<'static>>>,
+    pub(crate) input: String,
+    pub(crate) cursor: usize,                      // Byte offset of the cursor within `input`
+    pub(crate) input_scroll: usize,                // First display column shown in the input box
+    pub(crate) scroll_offset: u16,
+    pub(crate) last_scroll: Instant,               // When the output pane was last scrolled by hand
+    pub(crate) chat_scroll_offset: u16,
+    pub(crate) main_view_height: u16,              // Visible rows in the output pane, from the last draw
+    pub(crate) main_view_width: u16,               // Visible columns in the output pane, from the last draw
+    pub(crate) term_size: (u16, u16),              // Terminal columns and rows, kept up to date on resize
+    pub(crate) naws_size: Option<(u16, u16)>,      // Output pane size last reported to the server
+    pub(crate) chat_view_height: u16,              // Visible rows in the chat pane, from the last draw
+    pub(crate) pane_areas: [(Pane, Rect); 2],      // Where the output and chat panes were last drawn
+    pub(crate) focus: Pane,                        // Pane that PageUp/PageDown and Ctrl+Home/End scroll
+    pub(crate) command_history: Vec<String>,
+    pub(crate) history_index: Option<usize>,
+    pub(crate) saved_input: Option<String>,        // Partly typed input stashed while browsing history
+    pub(crate) common_commands: Vec<String>,
+    pub(crate) config: Config,
+    pub(crate) movement_mode: bool,                // Mapped keys send movement commands instead of typing
+    pub(crate) gauge_label: GaugeLabel,            // Numbers and/or percent shown after each gauge
+    pub(crate) group_panel: GroupPanel,            // How the group panel is drawn (/group)
+    pub(crate) mouse_capture: bool,                // The mouse should be captured (/mouse)
+    pub(crate) gmcp_debug: bool,                   // Echo a readable line for every GMCP update
+    pub(crate) unread_chat: usize,                 // Chat lines received while the chat pane was scrolled back
+    pub(crate) tells: VecDeque<Vec<Span<'static>>>, // Recent lines on the chat.important channels
+    pub(crate) unread_tells: usize,                // Tells not yet seen in the F7 list
+    pub(crate) tell_flash: Option<Instant>,        // When the last tell arrived
+    pub(crate) show_tells: bool,                   // The F7 recent-tells list is open
+    pub(crate) autologin: Option<AutoLogin>,
+    pub(crate) password_mode: bool,                // Server turned off local echo; hide typed input
+    pub(crate) timers: Timers,
+    pub(crate) triggers: Triggers,
+    pub(crate) substitutions: Substitutions,      // Output rewrites from [[substitutions]] and /sub
+    pub(crate) macros: Macros,
+    pub(crate) prompt: Option<PromptParser>,       // Reads vitals from text prompts (no GMCP)
+    pub(crate) scripts: Option<Arc<ScriptEngine>>,
+    pub(crate) clipboard: Clipboard,
+    pub(crate) mud_lines_added: usize,             // Total lines ever added to mud_output
+    pub(crate) room_mark: Option<usize>,           // Value of mud_lines_added at the last room.info
+    pub(crate) quit_pending: Option<Instant>,      // When Ctrl+C was pressed once, awaiting confirmation
+    pub(crate) bell_flash: Option<Instant>,        // When the last server bell flashed the output pane
+    pub(crate) bells: usize,                       // Server bells since the last command (BellMode::Count)
+    pub(crate) away: Option<Away>,                 // Set by /afk: tells get an automatic reply
+    pub(crate) char_name: Option<String>,          // Our character, from GMCP char.login
+    pub(crate) blank_run: usize,                   // Blank server lines received in a row
+    pub(crate) idle_warned: bool,                  // The idle warning was shown for the current idle stretch
+    pub(crate) ping_at: Option<Instant>,           // When the last Core.Ping was sent
+    pub(crate) ping_pending: bool,                 // That ping has not been answered yet
+    pub(crate) latency: Option<Duration>,          // Round trip of the last answered ping
+
+    // Personal GMCP info:
+    pub(crate) gmcp_vitals: Option<Vitals>,
+    pub(crate) gmcp_maxstats: Option<MaxStats>,
+    pub(crate) group_info: Option<GroupInfo>,     // group GMCP info (which includes enemy info)
+    pub(crate) current_room: Option<RoomInfo>,    // Where the character is standing (room.info)
+    pub(crate) zone: Option<String>,              // Zone of the last room.info, for the "Entering" banner
+    pub(crate) mapper: Mapper,
+    pub(crate) inventory: Inventory,              // Items by location from char.items.*
+    pub(crate) affects: AffectTracker,            // Active affects from char.affects
+    pub(crate) skills: SkillTracker,              // Skill cooldowns from char.skills
+    pub(crate) tick: Option<TickTracker>,         // Countdown to the next game tick, when [tick] is set up
+    pub(crate) show_skills: bool,                 // The skills panel is shown (toggled with /skills)
+    pub(crate) session_log: Option<SessionLog>,   // Transcript of the server's output
+    pub(crate) inspector: Option<GmcpInspector>,  // The F5 GMCP overlay, while open
+    pub(crate) char_status: Option<CharStatus>,   // Level, tnl and combat state from char.status
+    pub(crate) tnl_progress: Option<TnlProgress>, // Progress through the level, for the XP gauge
+    pub(crate) connected: bool,
+    pub(crate) gmcp_store: Arc<Mutex<GMCPStore>>,
+    pub(crate) last_server: Option<(String, String)>, // Host and port used by a bare /connect
+    pub(crate) session_request: Option<SessionRequest>, // For the UI loop, which owns the sessions
+    pub(crate) status_socket: Option<StatusPublisher>, // Snapshots for --status-socket readers
+    pub(crate) user_disconnect: bool,             // The next Disconnect was asked for; don't reconnect
+    pub(crate) reconnect: Option<CancellationToken>, // Stops the pending automatic reconnect
+    pub(crate) saved_config: String,               // `current_config` as TOML when loaded or last saved
+}
+
+impl AppState {
+    pub(crate) fn new(config: Config, gmcp_store: Arc<Mutex<GMCPStore>>) -> Self {
+        let mapper = if config.mapper.enabled {
+            Mapper::load(Path::new(&config.mapper.file))
+        } else {
+            Mapper::default()
+        };
+        let mut st = Self {
+            mud_output: VecDeque::new(),
+            chat_output: VecDeque::new(),
+            input: String::new(),
+            cursor: 0,
+            input_scroll: 0,
+            scroll_offset: 0,
+            last_scroll: Instant::now(),
+            chat_scroll_offset: 0,
+            main_view_height: 0,
+            main_view_width: 0,
+            term_size: crossterm::terminal::size().unwrap_or((80, 24)),
+            naws_size: None,
+            chat_view_height: 0,
+            pane_areas: [(Pane::Main, Rect::default()), (Pane::Chat, Rect::default())],
+            focus: Pane::Main,
+            command_history: Vec::new(),
+            history_index: None,
+            saved_input: None,
+            common_commands: vec![
+                "look".to_string(),
+                "inventory".to_string(),
+                "say".to_string(),
+                "quit".to_string(),
+                "help".to_string(),
+            ],
+            movement_mode: false,
+            gauge_label: config.ui.gauge_label,
+            group_panel: config.ui.group_panel,
+            mouse_capture: config.ui.mouse_capture,
+            gmcp_debug: config.ui.gmcp_debug,
+            unread_chat: 0,
+            tells: VecDeque::new(),
+            unread_tells: 0,
+            tell_flash: None,
+            show_tells: false,
+            autologin: AutoLogin::new(&config.login),
+            password_mode: false,
+            timers: Timers::default(),
+            triggers: Triggers::from_config(&config.triggers),
+            substitutions: Substitutions::from_config(&config.substitutions),
+            macros: Macros::from_config(&config.macros),
+            prompt: PromptParser::from_config(&config.prompt),
+            affects: AffectTracker::from_config(&config.affects),
+            skills: SkillTracker::default(),
+            tick: TickTracker::from_config(&config.tick),
+            show_skills: true,
+            session_log: SessionLog::open(&config.session_log),
+            inspector: None,
+            scripts: None,
+            clipboard: Clipboard::default(),
+            mud_lines_added: 0,
+            room_mark: None,
+            quit_pending: None,
+            bell_flash: None,
+            bells: 0,
+            away: None,
+            char_name: None,
+            blank_run: 0,
+            idle_warned: false,
+            ping_at: None,
+            ping_pending: false,
+            latency: None,
+            config,
+            gmcp_vitals: None,
+            gmcp_maxstats: None,
+            group_info: None,
+            current_room: None,
+            zone: None,
+            mapper,
+            inventory: Inventory::default(),
+            char_status: None,
+            tnl_progress: None,
+            connected: false,
+            gmcp_store,
+            last_server: None,
+            session_request: None,
+            status_socket: None,
+            user_disconnect: false,
+            reconnect: None,
+            saved_config: String::new(),
+        };
+        st.saved_config = st.current_config().to_toml().unwrap_or_default();
+        st
+    }
+
+    /// Sets up per-connection state for a fresh connection: forgets what the
+    /// previous server told us, re-arms auto-login and starts the configured timers.
+    pub(crate) fn start_session(&mut self, client: &TelnetClient) {
+        self.connected = true;
+        self.password_mode = false;
+        self.autologin = AutoLogin::new(&self.config.login);
+        self.gmcp_vitals = None;
+        self.gmcp_maxstats = None;
+        self.group_info = None;
+        self.current_room = None;
+        self.zone = None;
+        self.room_mark = None;
+        self.char_status = None;
+        self.tnl_progress = None;
+        self.char_name = None;
+        self.inventory.clear();
+        self.affects.clear();
+        self.skills.clear();
+        if let Some(tick) = self.tick.as_mut() {
+            tick.start(Instant::now());
+        }
+        self.ping_at = None;
+        self.ping_pending = false;
+        self.latency = None;
+        let echo = self.config.ui.echo_timers;
+        for timer in self.config.timers.clone() {
+            self.timers.add(&timer.name, timer.seconds, &timer.command, client, echo);
+        }
+    }
+
+    /// Adds a line the server sent.
+    pub(crate) fn add_mud_output(&mut self, line: Vec<Span<'static>>) {
+        self.push_output(OutputLine { spans: line, source: LineSource::Server });
+    }
+
+    /// Adds a styled line from the client itself.
+    pub(crate) fn add_system_line(&mut self, line: Vec<Span<'static>>) {
+        self.push_output(OutputLine { spans: line, source: LineSource::System });
+    }
+
+    /// Echoes a command the client sent, with `ui.echo_prefix` in `ui.echo_color`.
+    pub(crate) fn add_local_echo(&mut self, cmd: &str) {
+        let ui = &self.config.ui;
+        let color = ui.echo_color.as_deref().and_then(parse_color).unwrap_or(Color::Yellow);
+        let span = Span::styled(format!("{}{}", ui.echo_prefix, cmd), Style::default().fg(color));
+        self.push_output(OutputLine { spans: vec![span], source: LineSource::LocalEcho });
+    }
+
+    fn push_output(&mut self, line: OutputLine) {
+        if self.mud_output.len() > 2000 {
+            self.mud_output.pop_front();
+        }
+        self.mud_output.push_back(line);
+        self.mud_lines_added += 1;
+    }
+
+    /// Number of output lines received since the last room.info, if one has arrived.
+    pub(crate) fn lines_since_room(&self) -> Option<usize> {
+        self.room_mark.map(|mark| self.mud_lines_added - mark)
+    }
+
+    /// Copies the plain text of the last `count` output lines to the clipboard,
+    /// leaving out all but the server's lines unless `with_echoes`.
+    pub(crate) fn copy_last_lines(&mut self, count: usize, with_echoes: bool) {
+        let lines = if with_echoes {
+            self.recent_output(count)
+        } else {
+            let skip = self.mud_output.len().saturating_sub(count);
+            let lines = self.mud_output.iter().skip(skip).filter(|line| line.source == LineSource::Server);
+            lines.map(|line| plain_text(&line.spans)).collect()
+        };
+        self.copy_lines(&lines);
+    }
+
+    /// Copies `lines` to the clipboard, one per line, and reports how many.
+    pub(crate) fn copy_lines(&mut self, lines: &[String]) {
+        match self.clipboard.copy(&lines.join("\n")) {
+            Ok(()) => self.add_system_output(format!("Copied {} line(s) to the clipboard", lines.len())),
+            Err(e) => self.add_system_output(e),
+        }
+    }
+
+    /// The plain text of the last `count` output lines (fewer if there are
+    /// not that many), oldest first, echoed commands included.
+    pub(crate) fn recent_output(&self, count: usize) -> Vec<String> {
+        let skip = self.mud_output.len().saturating_sub(count);
+        self.mud_output.iter().skip(skip).map(|line| plain_text(&line.spans)).collect()
+    }
+
+    /// The plain text of the last `count` chat pane lines, oldest first.
+    pub(crate) fn recent_chat(&self, count: usize) -> Vec<String> {
+        let skip = self.chat_output.len().saturating_sub(count);
+        self.chat_output.iter().skip(skip).map(|line| plain_text(line)).collect()
+    }
+
+    /// Handles Ctrl+C; returns true when the client should quit. With
+    /// `ui.confirm_quit` the first press only arms the quit and a second press
+    /// within a few seconds confirms it.
+    pub(crate) fn request_quit(&mut self) -> bool {
+        if !self.config.ui.confirm_quit {
+            return true;
+        }
+        if self.quit_pending.is_some_and(|armed| armed.elapsed() < QUIT_CONFIRM_WINDOW) {
+            return true;
+        }
+        self.quit_pending = Some(Instant::now());
+        self.add_system_output("Press Ctrl+C again to quit");
+        false
+    }
+
+    /// Marks the coming Disconnect (if connected) as the user's doing and
+    /// stops any automatic reconnect in progress.
+    pub(crate) fn closing_connection(&mut self) {
+        self.user_disconnect = self.connected;
+        if let Some(reconnect) = self.reconnect.take() {
+            reconnect.cancel();
+        }
+    }
+
+    /// Reports the output pane's size to the server (NAWS) when it has changed
+    /// since the last report, e.g. after the terminal was resized.
+    pub(crate) fn report_window_size(&mut self, client: &TelnetClient) {
+        let size = (self.main_view_width, self.main_view_height);
+        if size.0 == 0 || self.naws_size == Some(size) {
+            return;
+        }
+        self.naws_size = Some(size);
+        let client = client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.set_window_size(size.0, size.1).await {
+                error!("Failed to report window size: {}", e);
+            }
+        });
+    }
+
+    /// Warns once per idle stretch when no command has been sent for
+    /// `idle.warn_after_mins`, sending the configured keepalive if any.
+    /// Tells whether it warned.
+    pub(crate) fn check_idle(&mut self, client: &TelnetClient) -> bool {
+        let mins = self.config.idle.warn_after_mins;
+        if mins == 0 || !self.connected {
+            return false;
+        }
+        let idle = client.idle_for();
+        if idle < Duration::from_secs(mins * 60) {
+            self.idle_warned = false;
+            return false;
+        }
+        if self.idle_warned {
+            return false;
+        }
+        self.idle_warned = true;
+        match self.config.idle.keepalive.clone() {
+            Some(cmd) => {
+                self.add_system_output(format!("Idle for {}m — sending '{}' to stay connected", idle.as_secs() / 60, cmd));
+                spawn_send(client, cmd);
+            }
+            None => self.add_system_output(format!("Idle for {}m — server may disconnect soon", idle.as_secs() / 60)),
+        }
+        true
+    }
+
+    /// The configuration with the settings changed during the session (F4
+    /// gauge labels, `/gmcp debug`, `/macro`) folded in.
+    pub(crate) fn current_config(&self) -> Config {
+        let mut config = self.config.clone();
+        config.ui.gauge_label = self.gauge_label;
+        config.ui.group_panel = self.group_panel;
+        config.ui.mouse_capture = self.mouse_capture;
+        config.ui.gmcp_debug = self.gmcp_debug;
+        config.macros = self.macros.to_config();
+        config.substitutions = self.substitutions.to_config();
+        for trigger in &mut config.triggers {
+            if let Some(enabled) = self.triggers.is_enabled(trigger.name()) {
+                trigger.enabled = enabled;
+            }
+        }
+        config
+    }
+
+    /// Writes `current_config` to the config file. `only_if_changed` skips
+    /// the write when nothing changed since it was loaded or last saved.
+    /// Settings this session left alone keep what the file has now, which
+    /// another session may have saved.
+    pub(crate) fn save_config(&mut self, only_if_changed: bool) -> Result<bool, String> {
+        self.save_config_to(Path::new(CONFIG_FILE), only_if_changed)
+    }
+
+    /// `save_config` to `path`. A file that cannot be read or parsed (say, a
+    /// typo made while the client runs) is left alone rather than merged with
+    /// or replaced by this session's settings.
+    fn save_config_to(&mut self, path: &Path, only_if_changed: bool) -> Result<bool, String> {
+        let config = self.current_config();
+        let text = config.to_toml()?;
+        if only_if_changed && text == self.saved_config {
+            return Ok(false);
+        }
+        let merged = match Config::read(path).map_err(|e| format!("Settings not saved: {}", e))? {
+            Some(on_disk) => config.merge_changes(&self.saved_config, &on_disk)?,
+            None => config.clone(),
+        };
+        merged.save(path)?;
+        self.config = config;
+        self.saved_config = text;
+        Ok(true)
+    }
+
+    /// Counts a server line towards the current run of blank lines and tells
+    /// whether it goes beyond `ui.max_blank_lines`, i.e. should not be shown.
+    pub(crate) fn squeeze_blank(&mut self, text: &str) -> bool {
+        if !text.trim().is_empty() {
+            self.blank_run = 0;
+            return false;
+        }
+        self.blank_run += 1;
+        self.config.ui.max_blank_lines.is_some_and(|max| self.blank_run > max)
+    }
+
+    /// Sends a GMCP Core.Ping every `connection.ping_interval_secs` to measure
+    /// latency. A ping still unanswered when the next is due clears the
+    /// reading, so servers that never reply show "n/a".
+    pub(crate) fn check_ping(&mut self, client: &TelnetClient) {
+        let secs = self.config.connection.ping_interval_secs;
+        if secs == 0 || !self.connected {
+            return;
+        }
+        if self.ping_at.is_some_and(|at| at.elapsed() < Duration::from_secs(secs)) {
+            return;
+        }
+        if self.ping_pending {
+            self.latency = None;
+        }
+        self.ping_at = Some(Instant::now());
+        self.ping_pending = true;
+        let client = client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.send_gmcp_subneg("Core.Ping").await {
+                error!("Failed to send Core.Ping: {}", e);
+            }
+        });
+    }
+
+    /// Adds a line describing a GMCP update, but only when GMCP debugging is on.
+    pub(crate) fn add_gmcp_debug(&mut self, line: Span<'static>) {
+        if self.gmcp_debug {
+            self.add_system_line(vec![line]);
+        }
+    }
+
+    /// Records the zone of a room.info update and, when it differs from the
+    /// previous room's, announces it with an "Entering" banner. The first room
+    /// of a session only sets the zone.
+    pub(crate) fn enter_zone(&mut self, zone: &str) {
+        if zone.is_empty() || self.zone.as_deref() == Some(zone) {
+            return;
+        }
+        if self.zone.replace(zone.to_string()).is_some() {
+            let style = Style::default().fg(Color::Black).bg(Color::LightYellow).add_modifier(Modifier::BOLD);
+            self.add_system_line(vec![Span::styled(format!(" Entering: {} ", zone), style)]);
+        }
+    }
+
+    /// The pane drawn at a terminal cell, if any.
+    pub(crate) fn pane_at(&self, column: u16, row: u16) -> Option<Pane> {
+        self.pane_areas.iter().find_map(|&(pane, area)| {
+            let inside = (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row);
+            inside.then_some(pane)
+        })
+    }
+
+    /// Adds a client-generated informational line to the MUD output.
+    pub(crate) fn add_system_output(&mut self, text: impl Into<String>) {
+        self.add_system_line(vec![Span::styled(text.into(), Style::default().fg(Color::LightCyan))]);
+    }
+
+    /// Keeps the lines of a message on an important channel for the F7 list
+    /// and flags it in the status bar.
+    pub(crate) fn add_tell(&mut self, lines: &[Vec<Span<'static>>]) {
+        for line in lines {
+            if self.tells.len() >= MAX_TELLS {
+                self.tells.pop_front();
+            }
+            self.tells.push_back(line.clone());
+        }
+        self.tell_flash = Some(Instant::now());
+        if !self.show_tells {
+            self.unread_tells += 1;
+        }
+    }
+
+    /// Opens or closes the recent-tells list; opening it marks them read.
+    pub(crate) fn toggle_tells(&mut self) {
+        self.show_tells = !self.show_tells;
+        if self.show_tells {
+            self.unread_tells = 0;
+        }
+    }
+
+    pub(crate) fn add_chat_output(&mut self, line: Vec<Span<'static>>) {
+        if self.chat_output.len() > 1000 {
+            self.chat_output.pop_front();
+        }
+        self.chat_output.push_back(line);
+    }
+
+    // Scroll offsets count rows back from the newest line, so scrolling "up"
+    // (toward older output) increases the offset and "down" decreases it.
+    pub(crate) fn scroll_up_main(&mut self, lines: u16) {
+        let max = max_scroll(self.mud_output.len(), self.main_view_height);
+        self.scroll_offset = self.scroll_offset.saturating_add(lines).min(max);
+        self.last_scroll = Instant::now();
+    }
+    pub(crate) fn scroll_down_main(&mut self, lines: u16) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        self.last_scroll = Instant::now();
+    }
+    /// Scrolls the output pane back to the oldest buffered line.
+    pub(crate) fn scroll_main_to_oldest(&mut self) {
+        self.scroll_offset = max_scroll(self.mud_output.len(), self.main_view_height);
+        self.last_scroll = Instant::now();
+    }
+    /// Returns the output pane to the newest line once it has been left
+    /// scrolled back, untouched, for `ui.scroll_return_secs`. Tells whether it did.
+    pub(crate) fn check_scroll_return(&mut self) -> bool {
+        let secs = self.config.ui.scroll_return_secs;
+        let due = secs > 0 && self.scroll_offset > 0 && self.last_scroll.elapsed() >= Duration::from_secs(secs);
+        if due {
+            self.scroll_offset = 0;
+        }
+        due
+    }
+    /// Returns the output pane to the newest line.
+    pub(crate) fn scroll_main_to_newest(&mut self) {
+        self.scroll_offset = 0;
+    }
+    pub(crate) fn scroll_up_chat(&mut self, lines: u16) {
+        let max = max_scroll(self.chat_output.len(), self.chat_view_height);
+        self.chat_scroll_offset = self.chat_scroll_offset.saturating_add(lines).min(max);
+    }
+    pub(crate) fn scroll_down_chat(&mut self, lines: u16) {
+        self.chat_scroll_offset = self.chat_scroll_offset.saturating_sub(lines);
+        if self.chat_scroll_offset == 0 {
+            self.unread_chat = 0;
+        }
+    }
+
+    /// Moves keyboard scrolling to the other pane.
+    pub(crate) fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Pane::Main => Pane::Chat,
+            Pane::Chat => Pane::Main,
+        };
+    }
+    pub(crate) fn scroll_up_focused(&mut self, lines: u16) {
+        match self.focus {
+            Pane::Main => self.scroll_up_main(lines),
+            Pane::Chat => self.scroll_up_chat(lines),
+        }
+    }
+    pub(crate) fn scroll_down_focused(&mut self, lines: u16) {
+        match self.focus {
+            Pane::Main => self.scroll_down_main(lines),
+            Pane::Chat => self.scroll_down_chat(lines),
+        }
+    }
+    pub(crate) fn scroll_focused_to_oldest(&mut self) {
+        match self.focus {
+            Pane::Main => self.scroll_main_to_oldest(),
+            Pane::Chat => self.scroll_up_chat(u16::MAX),
+        }
+    }
+    pub(crate) fn scroll_focused_to_newest(&mut self) {
+        match self.focus {
+            Pane::Main => self.scroll_main_to_newest(),
+            Pane::Chat => self.scroll_down_chat(u16::MAX),
+        }
+    }
+
+    pub(crate) fn add_to_history(&mut self, cmd: String) {
+        if !cmd.trim().is_empty() {
+            match self.config.input.history_dedup {
+                HistoryDedup::Consecutive => {
+                    if self.command_history.last() != Some(&cmd) {
+                        self.command_history.push(cmd);
+                    }
+                }
+                HistoryDedup::All => {
+                    self.command_history.retain(|c| *c != cmd);
+                    self.command_history.push(cmd);
+                }
+            }
+            let excess = self.command_history.len().saturating_sub(MAX_HISTORY);
+            self.command_history.drain(..excess);
+        }
+        self.history_index = None;
+        self.saved_input = None;
+    }
+
+    pub(crate) fn history_up(&mut self) {
+        if self.command_history.is_empty() {
+            return;
+        }
+        match self.history_index {
+            None => {
+                self.saved_input = Some(self.input.clone());
+                self.history_index = Some(self.command_history.len().saturating_sub(1));
+            }
+            Some(0) => {}
+            Some(i) => self.history_index = Some(i.saturating_sub(1)),
+        }
+        if let Some(i) = self.history_index {
+            self.set_input(self.command_history[i].clone());
+        }
+    }
+
+    pub(crate) fn history_down(&mut self) {
+        if self.command_history.is_empty() {
+            return;
+        }
+        match self.history_index {
+            None => {}
+            Some(i) if i >= self.command_history.len() - 1 => {
+                self.history_index = None;
+                let saved = self.saved_input.take().unwrap_or_default();
+                self.set_input(saved);
+            }
+            Some(i) => {
+                self.history_index = Some(i + 1);
+                if let Some(j) = self.history_index {
+                    self.set_input(self.command_history[j].clone());
+                }
+            }
+        }
+    }
+
+    /// Completes the word before the cursor, leaving the rest of the input
+    /// alone. Candidates are the common commands, `input.completion_words`
+    /// and words from the history (most recent first), in that order.
+    pub(crate) fn autocomplete(&mut self) {
+        let delimiters = &self.config.input.word_delimiters;
+        let before = &self.input[..self.cursor];
+        let start = before
+            .char_indices()
+            .rfind(|(_, c)| delimiters.contains(*c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &before[start..];
+        if word.is_empty() {
+            return;
+        }
+        let history_words = self
+            .command_history
+            .iter()
+            .rev()
+            .flat_map(|cmd| cmd.split(|c| delimiters.contains(c)));
+        let completion = self
+            .common_commands
+            .iter()
+            .chain(&self.config.input.completion_words)
+            .map(String::as_str)
+            .chain(history_words)
+            .find(|candidate| candidate.len() > word.len() && candidate.starts_with(word))
+            .map(str::to_string);
+        if let Some(completion) = completion {
+            self.input.replace_range(start..self.cursor, &completion);
+            self.cursor = start + completion.len();
+        }
+    }
+
+    /// Replaces the input line and puts the cursor at its end.
+    pub(crate) fn set_input(&mut self, text: String) {
+        self.cursor = text.len();
+        self.input = text;
+    }
+
+    /// Takes the whole input line, leaving it empty.
+    pub(crate) fn take_input(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.input)
+    }
+
+    pub(crate) fn insert_char(&mut self, c: char) {
+        self.input.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        self.input.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Deletes the character before the cursor.
+    pub(crate) fn backspace(&mut self) {
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.input.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the character under the cursor.
+    pub(crate) fn delete(&mut self) {
+        if self.cursor < self.input.len() {
+            self.input.remove(self.cursor);
+        }
+    }
+
+    pub(crate) fn cursor_left(&mut self) {
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub(crate) fn cursor_right(&mut self) {
+        if let Some(c) = self.input[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+}
+
+/// Feeds vitals read from a text prompt through the same path as GMCP's
+/// char.vitals / char.maxstats. Values the prompt doesn't show keep their
+/// last known value.
+fn apply_prompt(st: &mut AppState, client: &TelnetClient, p: PromptVitals) {
+    if p.maxhp.is_some() || p.maxmana.is_some() || p.maxmove.is_some() {
+        let old = st.gmcp_maxstats.clone();
+        let (maxhp, maxmana, maxmove) = (
+            p.maxhp.or(old.as_ref().map(|m| m.maxhp)).unwrap_or(0),
+            p.maxmana.or(old.as_ref().map(|m| m.maxmana)).unwrap_or(0),
+            p.maxmove.or(old.as_ref().map(|m| m.maxmove)).unwrap_or(0),
+        );
+        handle_message(st, client, TelnetMessage::CharMaxStats(maxhp, maxmana, maxmove));
+    }
+    if p.hp.is_some() || p.mana.is_some() || p.movement.is_some() {
+        let old = st.gmcp_vitals.clone();
+        let (hp, mana, movement) = (
+            p.hp.or(old.as_ref().map(|v| v.hp)).unwrap_or(0),
+            p.mana.or(old.as_ref().map(|v| v.mana)).unwrap_or(0),
+            p.movement.or(old.as_ref().map(|v| v.movement)).unwrap_or(0),
+        );
+        handle_message(st, client, TelnetMessage::CharVitals(hp, mana, movement));
+    }
+}
+
+/// A server line cut or split to `ui.max_line_length` characters, or `None`
+/// when it is kept as it is.
+fn fit_line(ui: &UiConfig, line: &StyledLine) -> Option<Vec<StyledLine>> {
+    match ui.long_lines {
+        _ if ui.max_line_length == 0 => None,
+        LongLines::Off => None,
+        LongLines::Truncate => line.truncated(ui.max_line_length, "…").map(|cut| vec![cut]),
+        LongLines::Wrap => Some(line.wrapped(ui.max_line_length)).filter(|pieces| pieces.len() > 1),
+    }
+}
+
+/// Applies one message from the telnet client (or scripts/timers) to the UI state.
+pub(crate) fn handle_message(st: &mut AppState, client: &TelnetClient, msg: TelnetMessage) {
+    match msg {
+        TelnetMessage::MUDOutput(line) => {
+            // Substitutions change what is shown and logged; prompts, auto-login
+            // and triggers still see the line as the server sent it, with color
+            // markers stripped the same way as for the log.
+            let original = line.plain_text();
+            let line = st.substitutions.apply(line);
+            let spans = to_spans(&line, Color::White);
+            let text = plain_text(&spans);
+            if let Some(log) = st.session_log.as_mut() {
+                log.write_line(&text);
+            }
+            // The log keeps overlong lines whole; the output buffer gets them
+            // cut or split per ui.long_lines.
+            let mut output = match fit_line(&st.config.ui, &line) {
+                Some(pieces) => pieces.iter().map(|piece| to_spans(piece, Color::White)).collect(),
+                None => vec![spans],
+            };
+            if let Some(bg) = line.fill_bg.filter(|_| st.config.ui.fill_background) {
+                // Widened to the pane when drawn; see ui::fill_background.
+                for spans in &mut output {
+                    spans.push(Span::styled(String::new(), Style::default().bg(bg.into())));
+                }
+            }
+            let squeezed = st.squeeze_blank(&text);
+            let prompt = st.prompt.as_ref().and_then(|p| Some((p.parse(&original)?, p.gag)));
+            match prompt {
+                Some((vitals, gag)) => {
+                    apply_prompt(st, client, vitals);
+                    if !gag {
+                        for spans in output {
+                            st.add_mud_output(spans);
+                        }
+                    }
+                }
+                // Squeezed blank lines still reach the session log above.
+                None if squeezed => {}
+                None => {
+                    for spans in output {
+                        st.add_mud_output(spans);
+                    }
+                }
+            }
+            if let Some(send) = st.autologin.as_mut().and_then(|a| a.on_line(&original)) {
+                send_login(st, client, send);
+            }
+            st.affects.on_line(&original);
+            if let Some(tick) = st.tick.as_mut() {
+                tick.on_line(&original, Instant::now());
+            }
+            run_triggers(st, client, &original);
+        }
+        TelnetMessage::ChatMessage { channel, player, message } => {
+            let lines = chat_lines(&st.config.chat, &channel, &player, &message);
+            // Tells and the like also go to the chat pane as usual.
+            let important = st.config.chat.is_important(&channel);
+            if important {
+                st.add_tell(&lines);
+            }
+            for line in lines {
+                st.add_chat_output(line);
+                if st.chat_scroll_offset > 0 {
+                    st.unread_chat += 1;
+                }
+            }
+            if important || (st.chat_scroll_offset > 0 && st.config.ui.chat_bell) {
+                ring_bell();
+            }
+            auto_reply(st, client, &channel, &player);
+        }
+        TelnetMessage::Connected(addr) => {
+            st.add_system_output(format!("Connected to {}", addr));
+            // Ends a reconnect in progress; the next loss counts from 1 again.
+            if let Some(reconnect) = st.reconnect.take() {
+                reconnect.cancel();
+            }
+            st.start_session(client);
+        }
+        TelnetMessage::ConnectFailed { addr, error } => {
+            let text = format!("Could not connect to {}: {}", addr, error);
+            st.add_system_line(vec![Span::styled(text, Style::default().fg(Color::Red))]);
+            // While reconnecting, the next attempt is on its way already.
+            if st.reconnect.is_none() {
+                st.add_system_output("Type /reconnect to try again");
+            }
+        }
+        TelnetMessage::Disconnect(reason) => {
+            st.connected = false;
+            st.timers.stop_all();
+            // The user knows why when they closed it themselves.
+            let text = match reason {
+                DisconnectReason::Client => "Disconnected".to_string(),
+                ref reason => format!("Disconnected: {}", reason),
+            };
+            st.add_system_line(vec![Span::styled(text, Style::default().fg(Color::Red))]);
+            if !std::mem::take(&mut st.user_disconnect) && reason.should_reconnect() {
+                start_reconnect(st, client);
+            }
+        }
+        TelnetMessage::CharVitals(hp, mana, movement) => {
+            let line = Span::styled(
+                format!("GMCP: Char.Vitals => HP: {}, Mana: {}, Movement: {}", hp, mana, movement),
+                Style::default().fg(Color::Cyan),
+            );
+            st.add_gmcp_debug(line);
+            st.gmcp_vitals = Some(Vitals { hp, mana, movement });
+        }
+        TelnetMessage::CharMaxStats(maxhp, maxmana, maxmove) => {
+            let line = Span::styled(
+                format!("GMCP: Char.MaxStats => maxHP: {}, maxMana: {}, maxMove: {}", maxhp, maxmana, maxmove),
+                Style::default().fg(Color::Cyan),
+            );
+            st.add_gmcp_debug(line);
+            st.gmcp_maxstats = Some(MaxStats { maxhp, maxmana, maxmove });
+        }
+        TelnetMessage::CharLogin(name) => {
+            st.char_name = Some(name.clone());
+            let line = Span::styled(
+                format!("GMCP: Char.Login => name={}", name),
+                Style::default().fg(Color::Green),
+            );
+            st.add_gmcp_debug(line);
+        }
+        TelnetMessage::RoomInfo(room) => {
+            let line = Span::styled(
+                format!("GMCP: Room.Info => name={}, zone={}, exits={}", room.name, room.zone, exit_list(&room.exits)),
+                Style::default().fg(Color::Magenta),
+            );
+            st.add_gmcp_debug(line);
+            if st.config.mapper.enabled {
+                st.mapper.enter(&room);
+            }
+            st.enter_zone(&room.zone);
+            st.current_room = Some(room);
+            st.room_mark = Some(st.mud_lines_added);
+        }
+        TelnetMessage::CharStatus(status) => {
+            let line = Span::styled(
+                format!(
+                    "GMCP: Char.Status => level {}, tnl {}, state {}, enemy {} ({}%)",
+                    status.level, status.tnl, status.state, status.enemy, status.enemypct
+                ),
+                Style::default().fg(Color::Cyan),
+            );
+            st.add_gmcp_debug(line);
+            match st.tnl_progress.as_mut() {
+                Some(progress) => progress.update(status.level, status.tnl),
+                None => st.tnl_progress = Some(TnlProgress::new(status.level, status.tnl)),
+            }
+            st.char_status = Some(status);
+        }
+        TelnetMessage::ServerEcho(on) => {
+            st.password_mode = on;
+            if on {
+                if let Some(send) = st.autologin.as_mut().and_then(|a| a.on_echo_off()) {
+                    send_login(st, client, send);
+                }
+            }
+        }
+        TelnetMessage::Info(text) => st.add_system_output(text),
+        TelnetMessage::Bell => match st.config.ui.server_bell {
+            BellMode::Ring => ring_bell(),
+            BellMode::Flash => st.bell_flash = Some(Instant::now()),
+            BellMode::Count => st.bells += 1,
+            BellMode::Off => {}
+        },
+        TelnetMessage::LocalEcho(cmd) => st.add_local_echo(&cmd),
+        TelnetMessage::ItemList { location, items } => st.inventory.set(location, items),
+        TelnetMessage::ItemAdd { location, item } => st.inventory.add(location, item),
+        TelnetMessage::ItemRemove { location, id } => st.inventory.remove(&location, &id),
+        TelnetMessage::ItemUpdate { location, item } => st.inventory.update(location, item),
+        TelnetMessage::Affects(affects) => st.affects.set(affects),
+        TelnetMessage::Skills(skills) => st.skills.set(skills),
+        TelnetMessage::SkillUpdate(skills) => st.skills.update(skills),
+        TelnetMessage::Pong => {
+            if std::mem::take(&mut st.ping_pending) {
+                st.latency = st.ping_at.map(|at| at.elapsed());
+            }
+        }
+        TelnetMessage::GroupInfo(group) => {
+            let line = Span::styled(
+                format!("GMCP: Group => groupname: {}", group.groupname),
+                Style::default().fg(Color::Blue),
+            );
+            st.add_gmcp_debug(line);
+            st.group_info = Some(group);
+        }
+    }
+}
+
+/// Records a typed line in the history and runs it as a client command or sends it to the MUD.
+pub(crate) fn submit_command(st: &mut AppState, client: &TelnetClient, cmd: String) {
+    st.bells = 0;
+    if st.config.ui.snap_on_send {
+        st.scroll_offset = 0;
+    }
+    st.add_to_history(cmd.clone());
+    if let Some(text) = commands::unescape(st, &cmd) {
+        let text = text.to_string();
+        send_and_echo(st, client, text);
+    } else if !commands::handle_command(st, client, &cmd) {
+        send_and_echo(st, client, cmd);
+    }
+}
+
+/// Echoes a command into the MUD output pane and sends it to the server in the background.
+/// These are commands the user issued directly, so they skip the throttle queue.
+pub(crate) fn send_and_echo(st: &mut AppState, client: &TelnetClient, cmd: String) {
+    st.mapper.note_command(&cmd);
+    st.add_local_echo(&cmd);
+    spawn_send(client, cmd);
+}
+
+/// Echoes and sends the commands bound to a macro key. They go through the
+/// throttle queue (when one is configured) from a single task, so they reach
+/// the server in order.
+pub(crate) fn send_macro(st: &mut AppState, client: &TelnetClient, commands: Vec<String>) {
+    st.bells = 0;
+    for cmd in &commands {
+        st.mapper.note_command(cmd);
+        st.add_local_echo(cmd);
+    }
+    let client = client.clone();
+    tokio::spawn(async move {
+        for cmd in commands {
+            if let Err(e) = client.send_command(&cmd).await {
+                error!("Failed to send macro command: {}", e);
+                break;
+            }
+        }
+    });
+}
+
+/// Sends a command whose text must stay out of the output pane (passwords).
+pub(crate) fn send_hidden(st: &mut AppState, client: &TelnetClient, cmd: String) {
+    st.add_local_echo("********");
+    spawn_send(client, cmd);
+}
+
+/// Sends the next auto-login command, masking it if it carries the password.
+pub(crate) fn send_login(st: &mut AppState, client: &TelnetClient, send: LoginSend) {
+    if send.secret {
+        send_hidden(st, client, send.command);
+    } else {
+        send_and_echo(st, client, send.command);
+    }
+}
+
+/// Answers a tell while away (`/afk`), once per player per `away.repeat_mins`.
+/// Our own messages (the server's echo of what we said) are never answered.
+fn auto_reply(st: &mut AppState, client: &TelnetClient, channel: &str, player: &str) {
+    let own = st.char_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(player))
+        || player.eq_ignore_ascii_case("you");
+    if own {
+        return;
+    }
+    let cfg = &st.config.away;
+    let Some(reply) = st.away.as_mut().and_then(|away| away.reply_to(cfg, channel, player)) else {
+        return;
+    };
+    info!("Away: auto-replying to {} with '{}'", player, reply);
+    st.add_system_output(format!("[AFK] Replied to {}", player));
+    let client = client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.send_command(&reply).await {
+            error!("Failed to send away reply: {}", e);
+        }
+    });
+}
+
+/// Fires every trigger matching a line of server output.
+pub(crate) fn run_triggers(st: &AppState, client: &TelnetClient, text: &str) {
+    for hit in st.triggers.matching(text) {
+        match hit.action {
+            TriggerAction::Send(cmd) => {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = client.send_command_echoed(&cmd).await {
+                        error!("Trigger send failed: {}", e);
+                    }
+                });
+            }
+            TriggerAction::Script { script, function } => {
+                let Some(engine) = st.scripts.clone() else {
+                    continue;
+                };
+                let line = text.to_string();
+                scripting::spawn_script(engine, client.clone(), move |e| {
+                    e.call(&script, &function, line, hit.captures)
+                });
+            }
+        }
+    }
+}
+
+/// After a lost connection, retries the last server every
+/// `connection.reconnect_delay_secs` until it answers or
+/// `connection.max_reconnect_attempts` (0 = no limit) run out. A fresh loss
+/// or `/reconnect` starts counting again.
+fn start_reconnect(st: &mut AppState, client: &TelnetClient) {
+    if !st.config.connection.reconnect {
+        st.add_system_output("Connection lost — type /reconnect to retry");
+        return;
+    }
+    spawn_reconnect(st, client, false);
+}
+
+/// `/reconnect`: drops any connection and retries the last server at once,
+/// then like an automatic reconnect with a fresh attempt count. With automatic
+/// reconnecting off, tries just once.
+pub(crate) fn reconnect_now(st: &mut AppState, client: &TelnetClient) {
+    if st.last_server.is_none() {
+        return st.add_system_output("Usage: /connect <host> <port>");
+    }
+    st.closing_connection();
+    spawn_reconnect(st, client, true);
+}
+
+fn spawn_reconnect(st: &mut AppState, client: &TelnetClient, now: bool) {
+    let Some((host, port)) = st.last_server.clone() else {
+        st.add_system_output("Connection lost — type /reconnect to retry");
+        return;
+    };
+    let opts = &st.config.connection;
+    let max = if opts.reconnect { opts.max_reconnect_attempts } else { 1 };
+    let delay = Duration::from_secs(opts.reconnect_delay_secs);
+    let cancel = CancellationToken::new();
+    if let Some(old) = st.reconnect.replace(cancel.clone()) {
+        old.cancel();
+    }
+    let client = client.clone();
+    let gmcp_store = st.gmcp_store.clone();
+    tokio::spawn(async move {
+        if now {
+            client.disconnect().await;
+        }
+        let mut attempt = 0;
+        while max == 0 || attempt < max {
+            attempt += 1;
+            let limit = if max == 0 { String::new() } else { format!("/{}", max) };
+            let wait = if now && attempt == 1 { Duration::ZERO } else { delay };
+            client
+                .info(format!("Reconnecting to {}:{} in {}s (attempt {}{})", host, port, wait.as_secs(), attempt, limit))
+                .await;
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = cancel.cancelled() => return,
+            }
+            gmcp_store.lock().await.clear();
+            // A failure is reported by connect itself.
+            if client.connect(&host, &port, gmcp_store.clone()).await.is_ok() {
+                return;
+            }
+        }
+        client.info("Connection lost — type /reconnect to retry").await;
+    });
+}
+
+pub(crate) fn spawn_send(client: &TelnetClient, cmd: String) {
+    let client = client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.send_command_now(&cmd).await {
+            error!("Failed to send command: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A state that reads and writes no files.
+    pub(crate) fn state() -> AppState {
+        let mut config = Config::default();
+        config.mapper.enabled = false;
+        AppState::new(config, Arc::new(Mutex::new(GMCPStore::new())))
+    }
+
+    /// A client that is never connected.
+    pub(crate) fn client() -> TelnetClient {
+        TelnetClient::new(tokio::sync::mpsc::channel(1).0)
+    }
+
+    #[test]
+    fn recent_output_keeps_the_newest_lines_of_every_source() {
+        let mut st = state();
+        st.add_mud_output(vec![Span::raw("You are hungry.")]);
+        st.add_local_echo("eat bread");
+        st.add_system_output("Timer fired");
+        st.add_mud_output(vec![Span::raw("You eat the bread.")]);
+
+        assert!(st.recent_output(0).is_empty());
+        assert_eq!(st.recent_output(2), ["Timer fired", "You eat the bread."]);
+        let all = ["You are hungry.", "> eat bread", "Timer fired", "You eat the bread."];
+        assert_eq!(st.recent_output(4), all);
+        assert_eq!(st.recent_output(100), all);
+    }
+
+    #[test]
+    fn recent_chat_keeps_the_newest_lines() {
+        let mut st = state();
+        assert!(st.recent_chat(5).is_empty());
+        for line in ["one", "two", "three"] {
+            st.add_chat_output(vec![Span::raw(line)]);
+        }
+        assert_eq!(st.recent_chat(1), ["three"]);
+        assert_eq!(st.recent_chat(3), ["one", "two", "three"]);
+        assert_eq!(st.recent_chat(10), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn output_lines_are_tagged_with_their_source() {
+        let mut st = state();
+        let client = client();
+        let mut line = StyledLine::default();
+        line.push("A goblin arrives.", Default::default());
+        handle_message(&mut st, &client, TelnetMessage::MUDOutput(line));
+        st.add_local_echo("kill goblin");
+        st.add_system_output("Logging started");
+        st.add_system_line(vec![Span::raw("Entering: Midgaard")]);
+        let sources: Vec<LineSource> = st.mud_output.iter().map(|line| line.source).collect();
+        assert_eq!(sources, [LineSource::Server, LineSource::LocalEcho, LineSource::System, LineSource::System]);
+    }
+
+    #[test]
+    fn history_browsing_restores_the_partly_typed_input() {
+        let mut st = state();
+        st.add_to_history("look".to_string());
+        st.add_to_history("score".to_string());
+        st.set_input("say hel".to_string());
+
+        st.history_up();
+        assert_eq!(st.input, "score");
+        st.history_up();
+        assert_eq!(st.input, "look");
+        st.history_up();
+        assert_eq!(st.input, "look");
+        st.history_down();
+        assert_eq!(st.input, "score");
+        st.history_down();
+        assert_eq!(st.input, "say hel");
+        assert_eq!(st.history_index, None);
+        assert_eq!(st.saved_input, None);
+        st.history_down();
+        assert_eq!(st.input, "say hel");
+    }
+
+    #[test]
+    fn history_keys_do_nothing_without_history() {
+        let mut st = state();
+        st.set_input("north".to_string());
+        st.history_up();
+        st.history_down();
+        assert_eq!(st.input, "north");
+        assert_eq!(st.saved_input, None);
+    }
+
+    #[test]
+    fn consecutive_dedup_skips_only_repeats_of_the_last_command() {
+        let mut st = state();
+        for cmd in ["look", "look", "north", "look", "  "] {
+            st.add_to_history(cmd.to_string());
+        }
+        assert_eq!(st.command_history, ["look", "north", "look"]);
+    }
+
+    #[test]
+    fn full_dedup_moves_a_repeated_command_to_the_end() {
+        let mut st = state();
+        st.config.input.history_dedup = HistoryDedup::All;
+        for cmd in ["look", "north", "score", "look", "look"] {
+            st.add_to_history(cmd.to_string());
+        }
+        assert_eq!(st.command_history, ["north", "score", "look"]);
+    }
+
+    #[test]
+    fn history_keeps_only_the_newest_commands() {
+        let mut st = state();
+        for n in 0..MAX_HISTORY + 5 {
+            st.add_to_history(format!("say {}", n));
+        }
+        assert_eq!(st.command_history.len(), MAX_HISTORY);
+        assert_eq!(st.command_history[0], "say 5");
+        assert_eq!(st.command_history.last().unwrap(), &format!("say {}", MAX_HISTORY + 4));
+    }
+
+    #[test]
+    fn squeeze_blank_hides_blank_lines_past_the_limit_until_text_arrives() {
+        let mut st = state();
+        st.config.ui.max_blank_lines = Some(1);
+        let hidden: Vec<bool> = ["", "  ", "", "Text", "", "\t"].iter().map(|line| st.squeeze_blank(line)).collect();
+        assert_eq!(hidden, [false, true, true, false, false, true]);
+    }
+
+    #[test]
+    fn squeeze_blank_keeps_every_line_without_a_limit() {
+        let mut st = state();
+        st.config.ui.max_blank_lines = None;
+        assert!((0..10).all(|_| !st.squeeze_blank("")));
+    }
+
+    #[test]
+    fn zone_banner_shows_only_when_the_zone_changes() {
+        let mut st = state();
+        st.enter_zone("Midgaard");
+        st.enter_zone("Midgaard");
+        st.enter_zone("");
+        assert!(st.recent_output(10).is_empty());
+        assert_eq!(st.zone.as_deref(), Some("Midgaard"));
+
+        st.enter_zone("Haon-Dor");
+        st.enter_zone("Haon-Dor");
+        assert_eq!(st.recent_output(10), [" Entering: Haon-Dor "]);
+    }
+
+    #[test]
+    fn autocomplete_completes_the_word_before_the_cursor() {
+        let mut st = state();
+        st.config.input.completion_words = vec!["goblin".to_string()];
+        st.set_input("kill gob north".to_string());
+        st.cursor = "kill gob".len();
+        st.autocomplete();
+        assert_eq!(st.input, "kill goblin north");
+        assert_eq!(st.cursor, "kill goblin".len());
+
+        st.add_to_history("tell gandalf greetings".to_string());
+        st.set_input("say gre".to_string());
+        st.autocomplete();
+        assert_eq!(st.input, "say greetings");
+    }
+
+    #[test]
+    fn autocomplete_splits_words_at_custom_delimiters() {
+        let mut st = state();
+        st.config.input.word_delimiters = " ;".to_string();
+        st.add_to_history("get sword;wield sword".to_string());
+        st.set_input("n;wie".to_string());
+        st.autocomplete();
+        assert_eq!(st.input, "n;wield");
+
+        st.set_input("n;lo".to_string());
+        st.autocomplete();
+        assert_eq!(st.input, "n;look");
+    }
+
+    #[test]
+    fn pane_at_hits_only_the_drawn_panes() {
+        let mut st = state();
+        assert_eq!(st.pane_at(0, 0), None);
+        st.pane_areas = [(Pane::Main, Rect::new(0, 1, 60, 20)), (Pane::Chat, Rect::new(60, 15, 20, 9))];
+        assert_eq!(st.pane_at(0, 1), Some(Pane::Main));
+        assert_eq!(st.pane_at(59, 20), Some(Pane::Main));
+        assert_eq!(st.pane_at(60, 15), Some(Pane::Chat));
+        assert_eq!(st.pane_at(79, 23), Some(Pane::Chat));
+        // The tab line, the status and input rows and the map beside the chat.
+        assert_eq!(st.pane_at(10, 0), None);
+        assert_eq!(st.pane_at(10, 21), None);
+        assert_eq!(st.pane_at(70, 5), None);
+    }
+
+    #[test]
+    fn save_leaves_a_malformed_config_file_alone() {
+        let path = std::env::temp_dir().join(format!("mud_tui_malformed_{}.toml", std::process::id()));
+        let typo = "[ui]\nmouse_capture = tru\n";
+        std::fs::write(&path, typo).unwrap();
+        let mut st = state();
+        st.gmcp_debug = !st.gmcp_debug;
+
+        let result = st.save_config_to(&path, true);
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(result.unwrap_err().starts_with("Settings not saved: Malformed config"));
+        assert_eq!(on_disk, typo);
+    }
+
+    #[test]
+    fn save_merges_with_the_config_file() {
+        let path = std::env::temp_dir().join(format!("mud_tui_merge_{}.toml", std::process::id()));
+        let mut st = state();
+        let mut theirs = st.current_config();
+        theirs.input.command_prefix = "#".to_string();
+        theirs.save(&path).unwrap();
+        st.gmcp_debug = !st.gmcp_debug;
+
+        let result = st.save_config_to(&path, true);
+        let saved = Config::read(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(result, Ok(true));
+        let saved = saved.unwrap().unwrap();
+        assert_eq!(saved.input.command_prefix, "#");
+        assert_eq!(saved.ui.gmcp_debug, st.gmcp_debug);
+    }
+
+    /// Appends what the server reads from the client to `seen` until `until`
+    /// shows up or `wait` has passed.
+    async fn server_reads(server: &mut tokio::net::TcpStream, seen: &mut String, until: &str, wait: Duration) {
+        use tokio::io::AsyncReadExt;
+        let mut buf = [0u8; 256];
+        let deadline = tokio::time::Instant::now() + wait;
+        while !seen.contains(until) {
+            match tokio::time::timeout_at(deadline, server.read(&mut buf)).await {
+                Ok(Ok(n)) if n > 0 => seen.push_str(&String::from_utf8_lossy(&buf[..n])),
+                _ => break,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn triggers_fire_on_server_lines_but_not_on_echoed_commands() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(64);
+        let client = TelnetClient::new(tx);
+        client.connect("127.0.0.1", &port, Arc::new(Mutex::new(GMCPStore::new()))).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let mut st = state();
+        st.triggers = Triggers::from_config(&[crate::triggers::TriggerConfig {
+            pattern: "kill goblin".to_string(),
+            send: Some("flee".to_string()),
+            script: None,
+            function: None,
+            name: None,
+            group: None,
+            priority: 0,
+            enabled: true,
+        }]);
+
+        let mut seen = String::new();
+        submit_command(&mut st, &client, "kill goblin".to_string());
+        st.add_local_echo("kill goblin");
+        server_reads(&mut server, &mut seen, "kill goblin", Duration::from_secs(5)).await;
+        server_reads(&mut server, &mut seen, "flee", Duration::from_millis(200)).await;
+        assert!(seen.contains("kill goblin"));
+        assert!(!seen.contains("flee"), "a trigger fired on an echo: {:?}", seen);
+
+        let mut line = StyledLine::default();
+        line.push("The goblin shouts: kill goblin? Never!", Default::default());
+        handle_message(&mut st, &client, TelnetMessage::MUDOutput(line));
+        server_reads(&mut server, &mut seen, "flee", Duration::from_secs(5)).await;
+        assert!(seen.contains("flee"));
+        client.disconnect().await;
+    }
+
+    /// The lengths, in characters, of the output lines a 5000-character
+    /// server line becomes under `long_lines` and `max_line_length`.
+    fn long_line_lengths(long_lines: LongLines, max_line_length: usize) -> Vec<usize> {
+        let mut st = state();
+        st.config.ui.long_lines = long_lines;
+        st.config.ui.max_line_length = max_line_length;
+        let mut line = StyledLine::default();
+        line.push("0123456789".repeat(500), Default::default());
+        handle_message(&mut st, &client(), TelnetMessage::MUDOutput(line));
+        st.recent_output(10).iter().map(|text| text.chars().count()).collect()
+    }
+
+    #[test]
+    fn long_lines_are_kept_truncated_or_wrapped() {
+        assert_eq!(long_line_lengths(LongLines::Off, 2000), [5000]);
+        assert_eq!(long_line_lengths(LongLines::Truncate, 2000), [2000]);
+        assert_eq!(long_line_lengths(LongLines::Wrap, 2000), [2000, 2000, 1000]);
+        // No limit at all.
+        assert_eq!(long_line_lengths(LongLines::Truncate, 0), [5000]);
+        assert_eq!(long_line_lengths(LongLines::Wrap, 0), [5000]);
+    }
+}
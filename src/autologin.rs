@@ -0,0 +1,94 @@
+// src/autologin.rs - Sends stored credentials when login prompts appear
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Auto-login settings from the `[login]` config section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoginConfig {
+    pub enabled: bool,
+    pub username: String,
+    pub password: String,
+    /// Steps run in order; each fires once when its prompt is seen.
+    pub steps: Vec<LoginStep>,
+}
+
+/// One send-on-match pair of a login sequence.
+///
+/// `pattern` is matched case-insensitively against each server line. An empty
+/// pattern fires when the server turns off local echo instead, which is how
+/// most MUDs announce a password prompt. `$username` and `$password` in
+/// `send` are replaced with the configured credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginStep {
+    #[serde(default)]
+    pub pattern: String,
+    pub send: String,
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            username: String::new(),
+            password: String::new(),
+            steps: vec![
+                LoginStep { pattern: "name".to_string(), send: "$username".to_string() },
+                LoginStep { pattern: String::new(), send: "$password".to_string() },
+            ],
+        }
+    }
+}
+
+/// A command produced by the login sequence.
+pub struct LoginSend {
+    pub command: String,
+    /// True when the command contains the password and must never be echoed or logged.
+    pub secret: bool,
+}
+
+/// Tracks progress through the configured login steps for one connection.
+pub struct AutoLogin {
+    config: LoginConfig,
+    next: usize,
+}
+
+impl AutoLogin {
+    /// Returns `None` when auto-login is disabled or has no steps.
+    pub fn new(config: &LoginConfig) -> Option<Self> {
+        if !config.enabled || config.steps.is_empty() {
+            return None;
+        }
+        Some(Self { config: config.clone(), next: 0 })
+    }
+
+    /// Checks a line of server output against the next pattern step.
+    pub fn on_line(&mut self, text: &str) -> Option<LoginSend> {
+        let step = self.config.steps.get(self.next)?;
+        if step.pattern.is_empty() || !text.to_lowercase().contains(&step.pattern.to_lowercase()) {
+            return None;
+        }
+        self.advance()
+    }
+
+    /// Fires the next step if it is waiting for the server to disable echo.
+    pub fn on_echo_off(&mut self) -> Option<LoginSend> {
+        let step = self.config.steps.get(self.next)?;
+        if !step.pattern.is_empty() {
+            return None;
+        }
+        self.advance()
+    }
+
+    fn advance(&mut self) -> Option<LoginSend> {
+        let step = &self.config.steps[self.next];
+        self.next += 1;
+        let secret = step.send.contains("$password");
+        let command = step
+            .send
+            .replace("$username", &self.config.username)
+            .replace("$password", &self.config.password);
+        info!("Auto-login: sending step {} of {}", self.next, self.config.steps.len());
+        Some(LoginSend { command, secret })
+    }
+}
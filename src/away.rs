@@ -0,0 +1,71 @@
+// src/away.rs - Automatic replies to tells while away (/afk)
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The `[away]` config section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AwayConfig {
+    /// Message used when `/afk` is given none.
+    pub message: String,
+    /// Command that answers a tell; `$player` and `$message` are filled in.
+    pub reply: String,
+    /// Minutes before the same player is answered again.
+    pub repeat_mins: u64,
+    /// Channels that get answered; case-insensitive.
+    pub channels: Vec<String>,
+}
+
+impl Default for AwayConfig {
+    fn default() -> Self {
+        Self {
+            message: "I'm away from the keyboard, back soon.".to_string(),
+            reply: "tell $player $message".to_string(),
+            repeat_mins: 10,
+            channels: vec!["tell".to_string()],
+        }
+    }
+}
+
+/// Away mode, from `/afk` until `/back`.
+pub struct Away {
+    message: String,
+    since: Instant,
+    /// When each player (lowercased) was last answered.
+    replied: HashMap<String, Instant>,
+}
+
+impl Away {
+    pub fn new(message: String) -> Self {
+        Self { message, since: Instant::now(), replied: HashMap::new() }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn since(&self) -> Instant {
+        self.since
+    }
+
+    /// Players answered so far.
+    pub fn replied(&self) -> usize {
+        self.replied.len()
+    }
+
+    /// The command answering `player`'s message on `channel`, or `None` when
+    /// the channel is not answered or the player was answered recently.
+    pub fn reply_to(&mut self, cfg: &AwayConfig, channel: &str, player: &str) -> Option<String> {
+        if player.trim().is_empty() || !cfg.channels.iter().any(|c| c.eq_ignore_ascii_case(channel)) {
+            return None;
+        }
+        let key = player.to_lowercase();
+        let repeat = Duration::from_secs(cfg.repeat_mins * 60);
+        if self.replied.get(&key).is_some_and(|at| at.elapsed() < repeat) {
+            return None;
+        }
+        self.replied.insert(key, Instant::now());
+        Some(cfg.reply.replace("$player", player).replace("$message", &self.message))
+    }
+}
@@ -0,0 +1,32 @@
+// src/clipboard.rs - Copying output text to the system clipboard
+use log::warn;
+
+/// Lazily opened handle to the system clipboard.
+///
+/// The handle is kept open after the first copy: on X11 the copied text is
+/// only available while its owner is alive.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Puts `text` on the system clipboard.
+    ///
+    /// Fails (with a logged warning) when no clipboard is available, e.g. in a
+    /// headless session or over SSH without X forwarding.
+    pub fn copy(&mut self, text: &str) -> Result<(), String> {
+        if self.inner.is_none() {
+            let clipboard = arboard::Clipboard::new().map_err(|e| {
+                warn!("No clipboard available: {}", e);
+                format!("No clipboard available: {}", e)
+            })?;
+            self.inner = Some(clipboard);
+        }
+        let clipboard = self.inner.as_mut().expect("clipboard opened above");
+        clipboard.set_text(text).map_err(|e| {
+            warn!("Failed to copy to clipboard: {}", e);
+            format!("Failed to copy to clipboard: {}", e)
+        })
+    }
+}
@@ -0,0 +1,740 @@
+// src/commands.rs - Client-side slash commands
+use crate::macros::{self, MacroKey};
+use crate::scripting;
+use crate::session_log;
+use crate::telnet_client::TelnetClient;
+use crate::app::{reconnect_now, AppState, SessionRequest};
+use log::error;
+use crate::away::Away;
+use crate::config::{GroupPanel, NewlineKey, CONFIG_FILE};
+use ratatui::style::{Color, Style};
+use ratatui::text::Span;
+
+/// A client command: its names, a one-line usage and summary for `/help`,
+/// longer help for `/help <name>`, and the handler that runs it.
+struct Command {
+    names: &'static [&'static str],
+    usage: &'static str,
+    summary: &'static str,
+    details: &'static str,
+    run: fn(&mut AppState, &TelnetClient, &[&str]),
+}
+
+/// Every client command, in the order `/help` lists them.
+static COMMANDS: &[Command] = &[
+    Command {
+        names: &["help"],
+        usage: "/help [command]",
+        summary: "List client commands and keys, or explain one command.",
+        details: "Without an argument, lists every client command and the key bindings \
+                  (including macros). With a command name (with or without the /), shows its full help.",
+        run: |st, _, args| help_command(st, args),
+    },
+    Command {
+        names: &["connect"],
+        usage: "/connect [<host> <port>]",
+        summary: "Connect to another server (or reconnect to the last one).",
+        details: "Closes the current connection first. Without arguments connects to the last server used.",
+        run: connect_command,
+    },
+    Command {
+        names: &["reconnect"],
+        usage: "/reconnect",
+        summary: "Reconnect to the last server (restarts the attempt count).",
+        details: "Closes the current connection and tries the last server at once, then every \
+                  connection.reconnect_delay_secs up to connection.max_reconnect_attempts times.",
+        run: |st, client, _| reconnect_now(st, client),
+    },
+    Command {
+        names: &["session"],
+        usage: "/session [new <host> <port>|<n>|close]",
+        summary: "Open another MUD session in a tab, switch to one, or close this one.",
+        details: "Each session has its own connection, output, chat, gauges and scripts; only the one shown is \
+                  drawn, and a tab with new output is marked with *. Alt+1..9 and Alt+Left/Right switch tabs. \
+                  Without an argument, lists the sessions.",
+        run: |st, _, args| {
+            st.session_request = Some(match args {
+                [] | ["list"] => SessionRequest::List,
+                ["new", host, port] => SessionRequest::Open(host.to_string(), port.to_string()),
+                ["close"] => SessionRequest::Close,
+                [n] => match n.parse::<usize>() {
+                    Ok(n) if n > 0 => SessionRequest::Switch(n - 1),
+                    _ => return st.add_system_output("Usage: /session [new <host> <port>|<n>|close]"),
+                },
+                _ => return st.add_system_output("Usage: /session [new <host> <port>|<n>|close]"),
+            });
+        },
+    },
+    Command {
+        names: &["disconnect"],
+        usage: "/disconnect",
+        summary: "Close the current connection.",
+        details: "Automatic reconnecting is not attempted after a /disconnect.",
+        run: |st, client, _| disconnect_command(st, client),
+    },
+    Command {
+        names: &["raw"],
+        usage: "/raw <text>",
+        summary: "Send text without a line ending.",
+        details: "For MUD prompts that read single keystrokes, e.g. /raw y. The text is sent at once, without \
+                  connection.line_ending, and is not echoed.",
+        run: raw_command,
+    },
+    Command {
+        names: &["inv", "inventory"],
+        usage: "/inv [room]",
+        summary: "List carried items or items in the room, from GMCP char.items.",
+        details: "Worn items are marked. If no item list has arrived yet, the server is asked for one.",
+        run: inventory_command,
+    },
+    Command {
+        names: &["macro", "macros"],
+        usage: "/macro [<key> [<cmd>[;<cmd>...]]]",
+        summary: "List, show or bind macro keys.",
+        details: "/macro lists the macros; /macro <key> shows one; /macro <key> <cmd>;<cmd> binds the key \
+                  (e.g. /macro F6 quaff heal;kick); /macro remove <key> removes it. Keys: F1-F24, a character \
+                  or insert/delete/home/end/pageup/pagedown/space, with ctrl+/alt+/shift+.",
+        run: |st, _, args| macro_command(st, args),
+    },
+    Command {
+        names: &["skills"],
+        usage: "/skills",
+        summary: "Show or hide the skills panel.",
+        details: "The panel lists the skills from GMCP char.skills with their cooldowns, counted down between \
+                  updates. It only appears once the server has sent skills.",
+        run: |st, _, _| {
+            st.show_skills = !st.show_skills;
+            let state = if !st.show_skills {
+                "hidden"
+            } else if st.skills.is_empty() {
+                "shown once the server sends skills"
+            } else {
+                "shown"
+            };
+            st.add_system_output(format!("Skills panel {}", state));
+        },
+    },
+    Command {
+        names: &["group"],
+        usage: "/group [full|compact|off]",
+        summary: "Choose how the group panel is drawn.",
+        details: "full lists every member with HP, mana and moves; compact sums the group up on one line \
+                  with each member's HP; off hides it. Without an argument, switches between full and compact. \
+                  Members who are not in the room are dimmed and shown in parentheses.",
+        run: |st, _, args| group_command(st, args),
+    },
+    Command {
+        names: &["trigger", "triggers"],
+        usage: "/trigger list|enable|disable [<name|group>]",
+        summary: "List triggers, or switch one or a group on or off.",
+        details: "/trigger list shows every trigger in the order they run (highest priority first) with its \
+                  group and whether it is disabled. /trigger enable|disable <name> switches one trigger; when \
+                  no trigger has that name, every trigger in the group of that name is switched.",
+        run: |st, _, args| trigger_command(st, args),
+    },
+    Command {
+        names: &["sub", "subs"],
+        usage: "/sub <pattern> <replacement>",
+        summary: "Rewrite output matching a regex.",
+        details: "/sub <pattern> <replacement> adds a substitution (or changes its replacement); $1 or ${name} \
+                  in the replacement insert capture groups. The pattern is one word, so use \\s for spaces. \
+                  /sub list lists them; /sub remove <number|pattern> removes one. Triggers still see the \
+                  original text.",
+        run: |st, _, args| sub_command(st, args),
+    },
+    Command {
+        names: &["timer", "timers"],
+        usage: "/timer <name> <seconds> <command>",
+        summary: "Send a command on an interval.",
+        details: "/timer <name> <seconds> <command> adds (or replaces) a timer; /timer list lists them; \
+                  /timer pause|resume|remove <name> controls one.",
+        run: timer_command,
+    },
+    Command {
+        names: &["run"],
+        usage: "/run <script>",
+        summary: "Re-read and run scripts/<script>.rhai.",
+        details: "The script runs in the background; its output appears in the output pane.",
+        run: run_command,
+    },
+    Command {
+        names: &["copy"],
+        usage: "/copy [chat] [lines]",
+        summary: "Copy output (or chat) to the clipboard.",
+        details: "Without a count, copies what the server sent since the last room.info (usually the room \
+                  description), without your commands. With a count, copies that many of the last lines. \
+                  /copy chat copies the last 20 chat lines, or the given number.",
+        run: |st, _, args| copy_command(st, args),
+    },
+    Command {
+        names: &["replay"],
+        usage: "/replay [lines]",
+        summary: "Show the last lines of the session log again, dimmed.",
+        details: "Shows the last 50 lines by default. Needs [session_log] enabled.",
+        run: |st, _, args| replay_command(st, args),
+    },
+    Command {
+        names: &["afk", "away"],
+        usage: "/afk [message]",
+        summary: "Answer tells automatically until /back.",
+        details: "Each player who sends a tell gets one reply (again after away.repeat_mins), sent with the \
+                  away.reply command. Without a message, away.message is used.",
+        run: |st, _, args| afk_command(st, args),
+    },
+    Command {
+        names: &["back"],
+        usage: "/back",
+        summary: "Leave away mode.",
+        details: "Stops the automatic replies started by /afk and says how many players were answered.",
+        run: |st, _, _| back_command(st),
+    },
+    Command {
+        names: &["mouse"],
+        usage: "/mouse [on|off]",
+        summary: "Capture the mouse for wheel scrolling, or leave it to the terminal.",
+        details: "With capture off, click-and-drag selects text the terminal's own way; the wheel no longer \
+                  scrolls the panes (PageUp/PageDown still do). Without an argument, switches it.",
+        run: |st, _, args| {
+            st.mouse_capture = match args {
+                [] => !st.mouse_capture,
+                ["on"] => true,
+                ["off"] => false,
+                _ => return st.add_system_output("Usage: /mouse [on|off]"),
+            };
+            let state = if st.mouse_capture { "captured (wheel scrolls)" } else { "released (terminal selection)" };
+            st.add_system_output(format!("Mouse {}", state));
+        },
+    },
+    Command {
+        names: &["saveconfig"],
+        usage: "/saveconfig",
+        summary: "Write the current settings to mud_tui.toml.",
+        details: "Saves the loaded configuration together with what changed this session (F4 gauge labels, \
+                  /gmcp debug, /macro). This also happens on a clean exit when something changed. The file \
+                  is rewritten as a whole, so comments in it are lost.",
+        run: |st, _, _| match st.save_config(false) {
+            Ok(_) => st.add_system_output(format!("Settings saved to {}", CONFIG_FILE)),
+            Err(e) => st.add_system_output(e),
+        },
+    },
+    Command {
+        names: &["gmcp"],
+        usage: "/gmcp debug on|off",
+        summary: "Show or hide a line for every GMCP update.",
+        details: "F5 opens the GMCP inspector for a browsable view of everything received.",
+        run: |st, _, args| gmcp_command(st, args),
+    },
+];
+
+/// Built-in keys, as listed by `/help`.
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("Enter", "Send the input line"),
+    ("Up/Down", "Browse command history"),
+    ("Tab", "Complete the word before the cursor"),
+    ("Shift+Tab", "Switch focus between output and chat"),
+    ("PageUp/PageDown", "Scroll the focused pane"),
+    ("Ctrl+Home/End", "Jump to the oldest/newest line"),
+    ("Ctrl+Y", "Copy the last output lines"),
+    ("F1/F2", "Scroll the chat"),
+    ("F3", "Toggle movement mode"),
+    ("F4", "Cycle gauge labels"),
+    ("F5", "Open the GMCP inspector"),
+    ("F7", "Show recent tells"),
+    ("Alt+1..9, Alt+Left/Right", "Switch sessions"),
+    ("Esc / Ctrl+C", "Quit"),
+];
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|cmd| cmd.names.contains(&name))
+}
+
+/// Input starting with the command prefix twice (`//who`) is meant for the
+/// MUD: returns it with one prefix removed (`/who`), or `None` for any other input.
+pub fn unescape<'a>(st: &AppState, input: &'a str) -> Option<&'a str> {
+    let prefix = st.config.input.command_prefix();
+    let rest = input.trim_start().strip_prefix(prefix)?;
+    rest.starts_with(prefix).then_some(rest)
+}
+
+/// Handles input that starts with the command prefix (`input.command_prefix`,
+/// "/" by default) as a client command.
+/// Returns `true` when the input was consumed and must not be sent to the MUD.
+pub fn handle_command(st: &mut AppState, client: &TelnetClient, input: &str) -> bool {
+    let prefix = st.config.input.command_prefix().to_string();
+    let Some(rest) = input.trim().strip_prefix(prefix.as_str()) else {
+        return false;
+    };
+    let mut words = rest.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+
+    match find_command(name) {
+        Some(cmd) => (cmd.run)(st, client, &args),
+        None => st.add_system_output(format!("Unknown command: {0}{1} (try {0}help)", prefix, name)),
+    }
+    true
+}
+
+/// `/help [command]` lists the commands and keys, or shows one command's help.
+fn help_command(st: &mut AppState, args: &[&str]) {
+    // Usages are written with "/"; show them with the prefix actually in use.
+    let prefix = st.config.input.command_prefix().to_string();
+    let usage = |cmd: &Command| cmd.usage.replacen('/', &prefix, 1);
+    match args {
+        [] => {
+            st.add_system_output("Client commands:");
+            for cmd in COMMANDS {
+                st.add_system_output(format!("  {:<34} {}", usage(cmd), cmd.summary));
+            }
+            st.add_system_output("Keys:");
+            for (key, action) in KEY_BINDINGS {
+                st.add_system_output(format!("  {:<34} {}", key, action));
+            }
+            let newline = match st.config.input.newline_key {
+                NewlineKey::ShiftEnter => Some("Shift+Enter"),
+                NewlineKey::CtrlEnter => Some("Ctrl+Enter"),
+                NewlineKey::AltEnter => Some("Alt+Enter"),
+                NewlineKey::Off => None,
+            };
+            if let Some(key) = newline {
+                st.add_system_output(format!("  {:<34} {}", key, "Insert a line break"));
+            }
+            for line in st.macros.describe() {
+                st.add_system_output(format!("  Macro {}", line));
+            }
+            st.add_system_output(format!(
+                "{0}help <command> explains a command; {0}{0}text sends {0}text to the MUD.",
+                prefix
+            ));
+        }
+        [name] => match find_command(name.trim_start_matches(prefix.as_str())) {
+            Some(cmd) => {
+                st.add_system_output(usage(cmd));
+                if cmd.names.len() > 1 {
+                    let aliases: Vec<String> = cmd.names.iter().map(|n| format!("{}{}", prefix, n)).collect();
+                    st.add_system_output(format!("  Also: {}", aliases.join(", ")));
+                }
+                st.add_system_output(format!("  {}", cmd.details));
+            }
+            None => st.add_system_output(format!("Unknown command: {}{}", prefix, name.trim_start_matches(prefix.as_str()))),
+        },
+        _ => st.add_system_output(format!("Usage: {}help [command]", prefix)),
+    }
+}
+
+/// `/afk [message]` turns on automatic replies to tells.
+fn afk_command(st: &mut AppState, args: &[&str]) {
+    let message = match args {
+        [] => st.config.away.message.clone(),
+        words => words.join(" "),
+    };
+    st.add_system_output(format!("Away: tells are answered with '{}'. /back to stop.", message));
+    st.away = Some(Away::new(message));
+}
+
+/// `/back` ends away mode.
+fn back_command(st: &mut AppState) {
+    match st.away.take() {
+        Some(away) => {
+            let mins = away.since().elapsed().as_secs() / 60;
+            st.add_system_output(format!(
+                "Welcome back. Away {}m; answered {} player(s) with '{}'.",
+                mins,
+                away.replied(),
+                away.message()
+            ));
+        }
+        None => st.add_system_output("You were not away"),
+    }
+}
+
+/// `/gmcp debug on|off` toggles the readable GMCP update lines in the output pane.
+fn gmcp_command(st: &mut AppState, args: &[&str]) {
+    match args {
+        ["debug", "on"] => {
+            st.gmcp_debug = true;
+            st.add_system_output("GMCP debug output enabled");
+        }
+        ["debug", "off"] => {
+            st.gmcp_debug = false;
+            st.add_system_output("GMCP debug output disabled");
+        }
+        _ => st.add_system_output("Usage: /gmcp debug on|off"),
+    }
+}
+
+/// `/timer [list]`, `/timer <name> <seconds> <command>`, `/timer pause|resume|remove <name>`.
+fn timer_command(st: &mut AppState, client: &TelnetClient, args: &[&str]) {
+    match args {
+        [] | ["list"] => {
+            let lines = st.timers.describe();
+            if lines.is_empty() {
+                st.add_system_output("No timers defined");
+            }
+            for line in lines {
+                st.add_system_output(line);
+            }
+        }
+        ["pause", name] | ["resume", name] => {
+            let paused = args[0] == "pause";
+            if st.timers.set_paused(name, paused) {
+                st.add_system_output(format!("Timer '{}' {}", name, if paused { "paused" } else { "resumed" }));
+            } else {
+                st.add_system_output(format!("No timer named '{}'", name));
+            }
+        }
+        ["remove", name] => {
+            if st.timers.remove(name) {
+                st.add_system_output(format!("Timer '{}' removed", name));
+            } else {
+                st.add_system_output(format!("No timer named '{}'", name));
+            }
+        }
+        [name, seconds, command @ ..] if !command.is_empty() => match seconds.parse::<u64>() {
+            Ok(secs) if secs > 0 => {
+                let command = command.join(" ");
+                let echo = st.config.ui.echo_timers;
+                st.timers.add(name, secs, &command, client, echo);
+                st.add_system_output(format!("Timer '{}' sends '{}' every {}s", name, command, secs));
+            }
+            _ => st.add_system_output("Timer interval must be a positive number of seconds"),
+        },
+        _ => st.add_system_output("Usage: /timer <name> <seconds> <command> | /timer list|pause|resume|remove <name>"),
+    }
+}
+
+/// `/run <script>` re-reads and runs a script from the scripts directory.
+fn run_command(st: &mut AppState, client: &TelnetClient, args: &[&str]) {
+    let [name] = args else {
+        st.add_system_output("Usage: /run <script>");
+        return;
+    };
+    let Some(engine) = st.scripts.clone() else {
+        st.add_system_output("Scripting is disabled");
+        return;
+    };
+    let name = name.to_string();
+    st.add_system_output(format!("Running script {}", name));
+    scripting::spawn_script(engine, client.clone(), move |e| e.run_file(&name));
+}
+
+/// `/copy [lines]` copies output to the clipboard. Without a count it copies
+/// what the server sent since the last room.info (usually the room
+/// description), without the commands typed in between.
+fn copy_command(st: &mut AppState, args: &[&str]) {
+    match args {
+        [] => match st.lines_since_room() {
+            Some(count) if count > 0 => st.copy_last_lines(count, false),
+            _ => st.add_system_output("No room output to copy yet; use /copy <lines>"),
+        },
+        ["chat"] => {
+            let lines = st.recent_chat(20);
+            st.copy_lines(&lines);
+        }
+        ["chat", count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => {
+                let lines = st.recent_chat(count);
+                st.copy_lines(&lines);
+            }
+            _ => st.add_system_output("Usage: /copy chat [lines]"),
+        },
+        [count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => st.copy_last_lines(count, true),
+            _ => st.add_system_output("Usage: /copy [chat] [lines]"),
+        },
+        _ => st.add_system_output("Usage: /copy [chat] [lines]"),
+    }
+}
+
+/// `/replay [lines]` shows the last lines of the session log again (default
+/// 50), dimmed and prefixed so they can't be mistaken for live output.
+fn replay_command(st: &mut AppState, args: &[&str]) {
+    let count = match args {
+        [] => 50,
+        [count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => return st.add_system_output("Usage: /replay [lines]"),
+        },
+        _ => return st.add_system_output("Usage: /replay [lines]"),
+    };
+    let Some(path) = st.session_log.as_ref().map(|log| log.path().to_path_buf()) else {
+        return st.add_system_output("Session logging is off (enable it in [session_log])");
+    };
+    let lines = match session_log::tail(&path, count) {
+        Ok(lines) if lines.is_empty() => return st.add_system_output("The session log is empty"),
+        Ok(lines) => lines,
+        Err(e) => return st.add_system_output(e),
+    };
+    st.add_system_output(format!("--- Replaying {} line(s) from {} ---", lines.len(), path.display()));
+    for line in lines {
+        st.add_system_line(vec![
+            Span::styled("│ ", Style::default().fg(Color::Gray)),
+            Span::styled(line, Style::default().fg(Color::DarkGray)),
+        ]);
+    }
+    st.add_system_output("--- End of replay ---");
+}
+
+/// `/connect [<host> <port>]` connects to a server, by default the last one used.
+/// Any current connection is closed first.
+fn connect_command(st: &mut AppState, client: &TelnetClient, args: &[&str]) {
+    let (host, port) = match args {
+        [host, port] => (host.to_string(), port.to_string()),
+        [] => match st.last_server.clone() {
+            Some(server) => server,
+            None => return st.add_system_output("Usage: /connect <host> <port>"),
+        },
+        _ => return st.add_system_output("Usage: /connect [<host> <port>]"),
+    };
+    st.last_server = Some((host.clone(), port.clone()));
+    st.closing_connection();
+    st.add_system_output(format!("Connecting to {}:{}...", host, port));
+
+    let client = client.clone();
+    let gmcp_store = st.gmcp_store.clone();
+    tokio::spawn(async move {
+        client.disconnect().await;
+        gmcp_store.lock().await.clear();
+        // A failure is reported by connect itself.
+        let _ = client.connect(&host, &port, gmcp_store).await;
+    });
+}
+
+/// `/inv [room]` lists the carried (worn items marked) or room items known from
+/// GMCP char.items. Asks the server for the list if none has arrived yet.
+fn inventory_command(st: &mut AppState, client: &TelnetClient, args: &[&str]) {
+    let (location, title) = match args {
+        [] => ("inv", "Inventory"),
+        ["room"] => ("room", "In the room"),
+        _ => return st.add_system_output("Usage: /inv [room]"),
+    };
+    if st.inventory.is_empty() {
+        st.add_system_output("No item list received yet; asking the server");
+        let client = client.clone();
+        tokio::spawn(async move {
+            for request in ["Char.Items.Inv", "Char.Items.Room"] {
+                let _ = client.send_gmcp_subneg(request).await;
+            }
+        });
+        return;
+    }
+    let lines: Vec<String> = st
+        .inventory
+        .items(location)
+        .iter()
+        .map(|item| {
+            let worn = if item.is_worn() { " (worn)" } else { "" };
+            format!("  {}{}", item.name, worn)
+        })
+        .collect();
+    st.add_system_output(format!("{} ({} items):", title, lines.len()));
+    for line in lines {
+        st.add_system_output(line);
+    }
+}
+
+/// `/macro [list]`, `/macro <key>`, `/macro <key> <cmd>[;<cmd>...]`, `/macro remove <key>`.
+fn macro_command(st: &mut AppState, args: &[&str]) {
+    match args {
+        [] | ["list"] => {
+            let lines = st.macros.describe();
+            if lines.is_empty() {
+                st.add_system_output("No macros defined");
+            }
+            for line in lines {
+                st.add_system_output(line);
+            }
+        }
+        ["remove", key] => match MacroKey::parse(key) {
+            Some(key) if st.macros.remove(key) => st.add_system_output(format!("Macro {} removed", key)),
+            Some(key) => st.add_system_output(format!("No macro on {}", key)),
+            None => st.add_system_output(format!("Unknown key '{}'", key)),
+        },
+        [key, rest @ ..] => {
+            let Some(key) = MacroKey::parse(key) else {
+                return st.add_system_output(format!("Unknown key '{}' (try F6, ctrl+k, alt+1)", key));
+            };
+            if rest.is_empty() {
+                let line = match st.macros.get(key) {
+                    Some(commands) => format!("{}: {}", key, commands.join("; ")),
+                    None => format!("No macro on {}", key),
+                };
+                return st.add_system_output(line);
+            }
+            let commands = macros::split_commands(&rest.join(" "));
+            if commands.is_empty() {
+                return st.add_system_output("Usage: /macro <key> <command>[;<command>...]");
+            }
+            st.add_system_output(format!("Macro {} sends: {}", key, commands.join("; ")));
+            st.macros.bind(key, commands);
+        }
+    }
+}
+
+/// `/group` sets or toggles the group panel mode.
+fn group_command(st: &mut AppState, args: &[&str]) {
+    st.group_panel = match args {
+        [] if st.group_panel == GroupPanel::Full => GroupPanel::Compact,
+        [] => GroupPanel::Full,
+        ["full"] => GroupPanel::Full,
+        ["compact"] => GroupPanel::Compact,
+        ["off"] => GroupPanel::Off,
+        _ => return st.add_system_output("Usage: /group [full|compact|off]"),
+    };
+    let name = match st.group_panel {
+        GroupPanel::Full => "full",
+        GroupPanel::Compact => "compact",
+        GroupPanel::Off => "off",
+    };
+    st.add_system_output(format!("Group panel: {}", name));
+}
+
+/// `/trigger` lists triggers and enables or disables them by name or group.
+fn trigger_command(st: &mut AppState, args: &[&str]) {
+    match args {
+        [] | ["list"] => {
+            let lines = st.triggers.describe();
+            if lines.is_empty() {
+                st.add_system_output("No triggers defined");
+            }
+            for line in lines {
+                st.add_system_output(line);
+            }
+        }
+        [action @ ("enable" | "disable"), target @ ..] if !target.is_empty() => {
+            let target = target.join(" ");
+            let enabled = *action == "enable";
+            let line = match st.triggers.set_enabled(&target, enabled) {
+                0 => format!("No trigger or group named {}", target),
+                count => format!("{} trigger(s) {}d: {}", count, action, target),
+            };
+            st.add_system_output(line);
+        }
+        _ => st.add_system_output("Usage: /trigger list|enable|disable [<name|group>]"),
+    }
+}
+
+/// `/sub` lists, adds and removes output substitutions.
+fn sub_command(st: &mut AppState, args: &[&str]) {
+    match args {
+        [] | ["list"] => {
+            let lines = st.substitutions.describe();
+            if lines.is_empty() {
+                st.add_system_output("No substitutions defined");
+            }
+            for line in lines {
+                st.add_system_output(line);
+            }
+        }
+        ["remove", which] => {
+            let line = if st.substitutions.remove(which) {
+                format!("Substitution {} removed", which)
+            } else {
+                format!("No substitution {}", which)
+            };
+            st.add_system_output(line);
+        }
+        [pattern, replace @ ..] if !replace.is_empty() => {
+            let replace = replace.join(" ");
+            match st.substitutions.add(pattern, &replace) {
+                Ok(()) => st.add_system_output(format!("Substituting {} => {}", pattern, replace)),
+                Err(e) => st.add_system_output(e),
+            }
+        }
+        _ => st.add_system_output("Usage: /sub <pattern> <replacement>"),
+    }
+}
+
+/// `/raw <text>` sends text with no line ending.
+fn raw_command(st: &mut AppState, client: &TelnetClient, args: &[&str]) {
+    if args.is_empty() {
+        return st.add_system_output("Usage: /raw <text>");
+    }
+    let text = args.join(" ");
+    let client = client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.send_raw(&text).await {
+            error!("Failed to send raw text: {}", e);
+        }
+    });
+}
+
+/// `/disconnect` closes the current connection.
+fn disconnect_command(st: &mut AppState, client: &TelnetClient) {
+    if !st.connected {
+        st.add_system_output("Not connected");
+        return;
+    }
+    st.closing_connection();
+    let client = client.clone();
+    tokio::spawn(async move { client.disconnect().await });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::{client, state};
+
+    #[test]
+    fn input_without_the_prefix_is_not_a_command() {
+        let mut st = state();
+        assert!(!handle_command(&mut st, &client(), "timers"));
+        assert!(st.recent_output(1).is_empty());
+    }
+
+    #[test]
+    fn every_name_of_a_command_runs_it() {
+        let mut st = state();
+        assert!(handle_command(&mut st, &client(), "/timer"));
+        assert!(handle_command(&mut st, &client(), "  /timers list "));
+        assert_eq!(st.recent_output(5), ["No timers defined", "No timers defined"]);
+    }
+
+    #[test]
+    fn unknown_command_is_reported_and_consumed() {
+        let mut st = state();
+        assert!(handle_command(&mut st, &client(), "/frobnicate now"));
+        assert_eq!(st.recent_output(1), ["Unknown command: /frobnicate (try /help)"]);
+    }
+
+    #[test]
+    fn bad_arguments_show_the_usage() {
+        let mut st = state();
+        let capture = st.mouse_capture;
+        handle_command(&mut st, &client(), "/mouse sideways");
+        handle_command(&mut st, &client(), "/session 0");
+        handle_command(&mut st, &client(), "/connect onlyhost");
+        assert_eq!(
+            st.recent_output(3),
+            ["Usage: /mouse [on|off]", "Usage: /session [new <host> <port>|<n>|close]", "Usage: /connect [<host> <port>]"]
+        );
+        assert_eq!(st.mouse_capture, capture);
+        assert!(st.session_request.is_none());
+    }
+
+    #[test]
+    fn doubled_prefix_is_sent_with_one_prefix() {
+        let st = state();
+        assert_eq!(unescape(&st, "//say hi"), Some("/say hi"));
+        assert_eq!(unescape(&st, "  //who"), Some("/who"));
+        assert_eq!(unescape(&st, "/say hi"), None);
+        assert_eq!(unescape(&st, "say //hi"), None);
+    }
+
+    #[test]
+    fn bare_prefix_is_not_escaped() {
+        let st = state();
+        assert_eq!(unescape(&st, "/"), None);
+        assert_eq!(unescape(&st, "//"), Some("/"));
+    }
+
+    #[test]
+    fn custom_prefix_is_escaped_instead_of_the_slash() {
+        let mut st = state();
+        st.config.input.command_prefix = "#".to_string();
+        assert_eq!(unescape(&st, "##say hi"), Some("#say hi"));
+        assert_eq!(unescape(&st, "//say hi"), None);
+        assert!(handle_command(&mut st, &client(), "#timers"));
+        assert!(!handle_command(&mut st, &client(), "/timers"));
+    }
+}
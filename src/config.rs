@@ -0,0 +1,538 @@
+// src/config.rs - User configuration for MudForge TUI
+use crate::affects::AffectsConfig;
+use crate::autologin::LoginConfig;
+use crate::away::AwayConfig;
+use crate::gauges::{GaugeGlyphs, GaugeLabel, LabelColors};
+use crate::logging::LoggingConfig;
+use crate::mapper::MapperConfig;
+use crate::prompt::PromptConfig;
+use crate::scripting::ScriptingConfig;
+use crate::session_log::SessionLogConfig;
+use crate::substitutions::SubstitutionConfig;
+use crate::tick::TickConfig;
+use crate::telnet_client::ClientOptions;
+use crate::timers::TimerConfig;
+use crate::triggers::TriggerConfig;
+use crate::ansi_color::COLOR_MAP;
+use log::{info, warn};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Default location of the configuration file (relative to the working directory).
+pub const CONFIG_FILE: &str = "mud_tui.toml";
+
+/// Top-level client configuration, loaded from `mud_tui.toml`.
+///
+/// Every section falls back to its defaults, so a partial file only needs
+/// to list the settings it wants to change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub connection: ClientOptions,
+    pub login: LoginConfig,
+    pub movement: MovementConfig,
+    pub timers: Vec<TimerConfig>,
+    pub triggers: Vec<TriggerConfig>,
+    pub substitutions: Vec<SubstitutionConfig>,
+    pub scripting: ScriptingConfig,
+    pub ui: UiConfig,
+    pub gauge_colors: GaugeColorsConfig,
+    pub input: InputConfig,
+    pub chat: ChatConfig,
+    pub mapper: MapperConfig,
+    pub prompt: PromptConfig,
+    pub log: LoggingConfig,
+    pub idle: IdleConfig,
+    pub affects: AffectsConfig,
+    pub session_log: SessionLogConfig,
+    /// Keys bound to commands, e.g. `F6 = ["quaff heal", "kick"]`.
+    pub macros: BTreeMap<String, Vec<String>>,
+    pub away: AwayConfig,
+    pub tick: TickConfig,
+}
+
+/// Chat pane colors. Color values are names ("lightred"), xterm indexes ("208")
+/// or hex ("#ff8800").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// Colors for channels that have no entry of their own.
+    pub default: ChannelColors,
+    /// Per-channel colors keyed by channel name (e.g. "tell", "gossip"); case-insensitive.
+    pub channels: BTreeMap<String, ChannelColors>,
+    /// Channels that ring the bell, flag the status bar and are kept in the
+    /// F7 recent-tells list; case-insensitive.
+    pub important: Vec<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            default: ChannelColors::default(),
+            channels: BTreeMap::new(),
+            important: vec!["tell".to_string(), "whisper".to_string()],
+        }
+    }
+}
+
+/// Colors for the parts of a chat line: `[tag] player: message`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelColors {
+    pub tag: Option<String>,
+    pub player: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Resolved colors for one chat line.
+pub struct ChatColors {
+    pub tag: Color,
+    pub player: Color,
+    pub message: Color,
+}
+
+/// Label colors for each gauge.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GaugeColorsConfig {
+    pub hp: GaugeColorConfig,
+    pub mana: GaugeColorConfig,
+    pub moves: GaugeColorConfig,
+    pub enemy: GaugeColorConfig,
+    pub xp: GaugeColorConfig,
+}
+
+/// Colors of a gauge's numbers by percentage: `low_color` below `low`,
+/// `mid_color` below `mid`, `high_color` from there up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GaugeColorConfig {
+    pub low: i64,
+    pub mid: i64,
+    pub low_color: String,
+    pub mid_color: String,
+    pub high_color: String,
+}
+
+impl Default for GaugeColorConfig {
+    fn default() -> Self {
+        Self {
+            low: 25,
+            mid: 50,
+            low_color: "red".to_string(),
+            mid_color: "yellow".to_string(),
+            high_color: "green".to_string(),
+        }
+    }
+}
+
+impl GaugeColorConfig {
+    /// The resolved colors; ones that cannot be parsed keep their default.
+    pub fn label_colors(&self) -> LabelColors {
+        let default = LabelColors::default();
+        LabelColors {
+            low: self.low,
+            mid: self.mid,
+            low_color: parse_color(&self.low_color).unwrap_or(default.low_color),
+            mid_color: parse_color(&self.mid_color).unwrap_or(default.mid_color),
+            high_color: parse_color(&self.high_color).unwrap_or(default.high_color),
+        }
+    }
+}
+
+/// Parses a color from user configuration: a name ("lightred", "dark gray"),
+/// an xterm 256-color index ("208", looked up in `COLOR_MAP`) or hex ("#ff8800").
+pub fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim();
+    if let Ok(index) = text.parse::<u8>() {
+        let key = format!("38;5;{}", index);
+        return COLOR_MAP.get(key.as_str()).map(|&rgb| rgb.into());
+    }
+    text.parse::<Color>().ok()
+}
+
+impl ChatConfig {
+    pub fn is_important(&self, channel: &str) -> bool {
+        self.important.iter().any(|name| name.eq_ignore_ascii_case(channel))
+    }
+
+    /// Picks the colors for `channel`: its own entry first, then `default`,
+    /// then the built-in green tag/player and white message.
+    pub fn colors_for(&self, channel: &str) -> ChatColors {
+        let own = self
+            .channels
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(channel))
+            .map(|(_, colors)| colors);
+        let pick = |field: fn(&ChannelColors) -> &Option<String>| {
+            own.and_then(|c| field(c).as_deref())
+                .or_else(|| field(&self.default).as_deref())
+                .and_then(parse_color)
+        };
+        let tag = pick(|c| &c.tag).unwrap_or(Color::Green);
+        ChatColors {
+            tag,
+            player: pick(|c| &c.player).unwrap_or(tag),
+            message: pick(|c| &c.message).unwrap_or(Color::White),
+        }
+    }
+}
+
+/// Warning about (and optionally preventing) the MUD's idle disconnect.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// Minutes without sending a command before warning; 0 turns it off.
+    pub warn_after_mins: u64,
+    /// A harmless command to send when the warning fires (e.g. "score").
+    pub keepalive: Option<String>,
+}
+
+/// Command line behaviour.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub history_dedup: HistoryDedup,
+    /// Characters separating words for Tab completion.
+    pub word_delimiters: String,
+    /// Extra words Tab can complete (spell names, targets, ...).
+    pub completion_words: Vec<String>,
+    /// Key that puts a line break in the input instead of sending it.
+    pub newline_key: NewlineKey,
+    /// Input starting with this is a client command rather than text for the
+    /// MUD (e.g. "#" for servers whose own commands start with "/").
+    pub command_prefix: String,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            history_dedup: HistoryDedup::default(),
+            word_delimiters: " ".to_string(),
+            completion_words: Vec::new(),
+            newline_key: NewlineKey::default(),
+            command_prefix: "/".to_string(),
+        }
+    }
+}
+
+impl InputConfig {
+    /// `command_prefix`, or "/" when it was set to nothing (which would turn
+    /// every line into a client command).
+    pub fn command_prefix(&self) -> &str {
+        match self.command_prefix.trim() {
+            "" => "/",
+            prefix => prefix,
+        }
+    }
+}
+
+/// Which Enter combination inserts a line break for multi-line input.
+/// Shift+Enter only differs from Enter on terminals that report modifier
+/// keys unambiguously (kitty, WezTerm, foot, recent xterm...); Alt+Enter
+/// works nearly everywhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NewlineKey {
+    #[default]
+    #[serde(rename = "shift+enter")]
+    ShiftEnter,
+    #[serde(rename = "ctrl+enter")]
+    CtrlEnter,
+    #[serde(rename = "alt+enter")]
+    AltEnter,
+    #[serde(rename = "off")]
+    Off,
+}
+
+/// How repeated commands are kept in the history.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryDedup {
+    /// Skip a command identical to the previous one (shell `ignoredups`).
+    #[default]
+    Consecutive,
+    /// Drop any earlier copy and re-add the command at the end (shell `erasedups`).
+    All,
+}
+
+/// Display preferences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Print a readable line into the output pane for every GMCP update.
+    pub gmcp_debug: bool,
+    /// Ring the terminal bell when chat arrives while the chat pane is scrolled back.
+    pub chat_bell: bool,
+    /// Echo commands sent by timers into the output pane.
+    pub echo_timers: bool,
+    /// Number of output lines Ctrl+Y copies to the clipboard.
+    pub copy_lines: usize,
+    /// Lines scrolled per mouse-wheel tick.
+    pub wheel_lines: u16,
+    /// Capture the mouse for wheel scrolling; off leaves selection to the terminal.
+    pub mouse_capture: bool,
+    /// Ask for a second Ctrl+C before quitting instead of quitting at once.
+    pub confirm_quit: bool,
+    /// What the gauges show after their bars at startup; F4 cycles it.
+    pub gauge_label: GaugeLabel,
+    /// Draw gauge bars with ASCII segments or smoother Unicode blocks.
+    pub gauge_glyphs: GaugeGlyphs,
+    pub group_panel: GroupPanel,
+    /// What to do when the server sends a bell (BEL).
+    pub server_bell: BellMode,
+    /// Mark the output pane's title with SCROLL LOCK while it is scrolled back.
+    pub scroll_lock_title: bool,
+    /// Jump the output pane back to the newest line when a command is sent.
+    pub snap_on_send: bool,
+    /// Return the output pane to the newest line after it has been left
+    /// scrolled back this many seconds without scrolling; 0 = never.
+    pub scroll_return_secs: u64,
+    /// Extend a background color left on at the end of a line to the pane's
+    /// right edge, as a terminal would.
+    pub fill_background: bool,
+    /// Put in front of commands echoed into the output pane.
+    pub echo_prefix: String,
+    /// Color of echoed commands (see `parse_color`); yellow when unset.
+    pub echo_color: Option<String>,
+    /// Show at most this many blank server lines in a row; unset keeps them all.
+    pub max_blank_lines: Option<usize>,
+    /// What happens to server lines longer than `max_line_length` characters.
+    pub long_lines: LongLines,
+    pub max_line_length: usize,
+    /// Experience needed for a whole level, for the XP gauge; unset learns
+    /// it from the largest `tnl` seen at each level.
+    pub xp_per_level: Option<i64>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            gmcp_debug: false,
+            chat_bell: true,
+            echo_timers: true,
+            copy_lines: 20,
+            wheel_lines: 3,
+            mouse_capture: true,
+            confirm_quit: true,
+            gauge_label: GaugeLabel::default(),
+            gauge_glyphs: GaugeGlyphs::default(),
+            group_panel: GroupPanel::default(),
+            server_bell: BellMode::default(),
+            scroll_lock_title: true,
+            snap_on_send: false,
+            scroll_return_secs: 0,
+            fill_background: false,
+            echo_prefix: "> ".to_string(),
+            echo_color: None,
+            max_blank_lines: None,
+            long_lines: LongLines::Off,
+            max_line_length: 2000,
+            xp_per_level: None,
+        }
+    }
+}
+
+/// How the group panel above the chat is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupPanel {
+    /// A bordered box with a line per member.
+    #[default]
+    Full,
+    /// One line summarizing every member's HP.
+    Compact,
+    Off,
+}
+
+/// How server lines over `ui.max_line_length` are kept in the output buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LongLines {
+    /// Kept whole, however long.
+    #[default]
+    Off,
+    /// Cut, ending in `…`.
+    Truncate,
+    /// Split into several buffer lines.
+    Wrap,
+}
+
+/// How a bell sent by the server is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellMode {
+    /// Ring the terminal bell.
+    #[default]
+    Ring,
+    /// Briefly highlight the output pane's border.
+    Flash,
+    /// Count bells in the status bar until the next command is sent.
+    Count,
+    /// Ignore them.
+    Off,
+}
+
+/// Keys used while movement mode is active.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovementConfig {
+    /// Maps a typed character to the command it sends (e.g. `'8' => "north"`).
+    #[serde(serialize_with = "serialize_char_keys")]
+    pub keys: BTreeMap<char, String>,
+}
+
+/// TOML keys must be strings, so the movement keys are written as such.
+fn serialize_char_keys<S: Serializer>(keys: &BTreeMap<char, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(keys.iter().map(|(key, command)| (key.to_string(), command)))
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        let keys = [
+            ('8', "north"),
+            ('2', "south"),
+            ('6', "east"),
+            ('4', "west"),
+            ('9', "northeast"),
+            ('7', "northwest"),
+            ('3', "southeast"),
+            ('1', "southwest"),
+            ('5', "look"),
+            ('+', "up"),
+            ('-', "down"),
+        ];
+        Self {
+            keys: keys.iter().map(|(k, v)| (*k, v.to_string())).collect(),
+        }
+    }
+}
+
+/// Three-way merge of one setting: `ours` where it differs from `base`,
+/// else `theirs`. Tables are merged key by key; `None` is a missing key.
+fn merge_value(base: Option<&toml::Value>, ours: Option<toml::Value>, theirs: Option<toml::Value>) -> Option<toml::Value> {
+    match (ours, theirs) {
+        (Some(toml::Value::Table(mut ours)), Some(toml::Value::Table(mut theirs))) => {
+            let base = base.and_then(toml::Value::as_table);
+            let mut keys: Vec<String> = ours.keys().chain(theirs.keys()).cloned().collect();
+            keys.sort();
+            keys.dedup();
+            let mut merged = toml::Table::new();
+            for key in keys {
+                let base = base.and_then(|base| base.get(&key));
+                if let Some(value) = merge_value(base, ours.remove(&key), theirs.remove(&key)) {
+                    merged.insert(key, value);
+                }
+            }
+            Some(toml::Value::Table(merged))
+        }
+        (ours, theirs) if ours.as_ref() == base => theirs,
+        (ours, _) => ours,
+    }
+}
+
+impl Config {
+    /// Writes the configuration to `path` as TOML. The file is replaced as a
+    /// whole, so comments in it are lost.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = self.to_toml()?;
+        // Written next to the target first so a failed write never leaves a
+        // truncated config behind.
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, text).map_err(|e| format!("Cannot write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Cannot replace {}: {}", path.display(), e))?;
+        info!("Saved config to {}", path.display());
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| format!("Cannot serialize the config: {}", e))
+    }
+
+    /// This configuration with every setting it has not changed since `base`
+    /// (the TOML it was last loaded or saved as) taken from `theirs`, so that
+    /// saving it keeps what another session saved in the meantime.
+    pub fn merge_changes(&self, base: &str, theirs: &Config) -> Result<Config, String> {
+        let to_value = |config: &Config| {
+            toml::Value::try_from(config).map_err(|e| format!("Cannot serialize the config: {}", e))
+        };
+        let base: toml::Value = toml::from_str(base).unwrap_or(toml::Value::Table(toml::Table::new()));
+        let merged = merge_value(Some(&base), Some(to_value(self)?), Some(to_value(theirs)?));
+        let merged = merged.unwrap_or(toml::Value::Table(toml::Table::new()));
+        merged.try_into().map_err(|e| format!("Cannot merge the config: {}", e))
+    }
+
+    /// Reads the configuration at `path` strictly: `None` when there is no
+    /// file, an error when it cannot be read or parsed.
+    pub fn read(path: &Path) -> Result<Option<Self>, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        toml::from_str(&text).map(Some).map_err(|e| format!("Malformed config {}: {}", path.display(), e))
+    }
+
+    /// Loads the configuration from `path`.
+    ///
+    /// A missing file yields the defaults; an unreadable or malformed file is
+    /// logged and also falls back to the defaults rather than aborting startup.
+    pub fn load(path: &Path) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!("No config file at {}, using defaults", path.display());
+                return Self::default();
+            }
+            Err(e) => {
+                warn!("Failed to read {}: {}; using defaults", path.display(), e);
+                return Self::default();
+            }
+        };
+        match toml::from_str(&text) {
+            Ok(config) => {
+                info!("Loaded config from {}", path.display());
+                config
+            }
+            Err(e) => {
+                warn!("Malformed config {}: {}; using defaults", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_changes_from_both_sides() {
+        let base = Config::default();
+        let base_text = base.to_toml().unwrap();
+
+        let mut ours = base.clone();
+        ours.ui.mouse_capture = !base.ui.mouse_capture;
+        let mut theirs = base.clone();
+        theirs.ui.gmcp_debug = !base.ui.gmcp_debug;
+        theirs.input.command_prefix = "#".to_string();
+
+        let merged = ours.merge_changes(&base_text, &theirs).unwrap();
+        assert_eq!(merged.ui.mouse_capture, ours.ui.mouse_capture);
+        assert_eq!(merged.ui.gmcp_debug, theirs.ui.gmcp_debug);
+        assert_eq!(merged.input.command_prefix, "#");
+    }
+
+    #[test]
+    fn merge_prefers_our_change_to_the_same_setting() {
+        let base = Config::default();
+        let base_text = base.to_toml().unwrap();
+        let mut ours = base.clone();
+        ours.input.command_prefix = "!".to_string();
+        let mut theirs = base.clone();
+        theirs.input.command_prefix = "#".to_string();
+        assert_eq!(ours.merge_changes(&base_text, &theirs).unwrap().input.command_prefix, "!");
+    }
+}
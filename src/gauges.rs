@@ -0,0 +1,524 @@
+// src/gauges.rs - Gauge rendering for HP, mana, movement, experience and enemy health
+use crate::ansi_color;
+use crate::telnet_client::GroupMember;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use serde::{Deserialize, Serialize};
+
+/// What each gauge shows after its bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GaugeLabel {
+    /// `current/max`
+    #[default]
+    Numbers,
+    /// `NN%`
+    Percent,
+    /// `current/max (NN%)`
+    Both,
+    /// Just the bar.
+    None,
+}
+
+impl GaugeLabel {
+    /// The next mode in the cycle numbers → percent → both → none.
+    pub fn next(self) -> Self {
+        match self {
+            GaugeLabel::Numbers => GaugeLabel::Percent,
+            GaugeLabel::Percent => GaugeLabel::Both,
+            GaugeLabel::Both => GaugeLabel::None,
+            GaugeLabel::None => GaugeLabel::Numbers,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GaugeLabel::Numbers => "numbers",
+            GaugeLabel::Percent => "percent",
+            GaugeLabel::Both => "both",
+            GaugeLabel::None => "none",
+        }
+    }
+
+    /// The text appended after a gauge's closing bracket, if any, colored by
+    /// how full the gauge is. Values over the maximum (overheal, buffs) are
+    /// highlighted instead.
+    fn span(self, current: i32, max: i32, colors: &LabelColors) -> Option<Span<'static>> {
+        let percent = if max > 0 { current as i64 * 100 / max as i64 } else { 0 };
+        let text = match self {
+            GaugeLabel::Numbers => format!(" {}/{}", current, max),
+            GaugeLabel::Percent => format!(" {}%", percent),
+            GaugeLabel::Both => format!(" {}/{} ({}%)", current, max, percent),
+            GaugeLabel::None => return None,
+        };
+        if overcharged(current, max) {
+            Some(Span::styled(text, Style::default().fg(OVERCHARGED).add_modifier(Modifier::BOLD)))
+        } else {
+            Some(Span::styled(text, Style::default().fg(colors.color_for(percent))))
+        }
+    }
+}
+
+/// Colors of a gauge's label by how full it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabelColors {
+    /// Below this percentage the label is `low_color`.
+    pub low: i64,
+    /// Below this percentage (and at or above `low`) it is `mid_color`.
+    pub mid: i64,
+    pub low_color: Color,
+    pub mid_color: Color,
+    /// At or above `mid`.
+    pub high_color: Color,
+}
+
+impl Default for LabelColors {
+    fn default() -> Self {
+        Self { low: 25, mid: 50, low_color: Color::Red, mid_color: Color::Yellow, high_color: Color::Green }
+    }
+}
+
+impl LabelColors {
+    /// The color for a gauge `percent` full. The thresholds are exclusive
+    /// upper bounds, so exactly `low` is already the middle color.
+    ///
+    /// ```
+    /// use mud_tui::gauges::LabelColors;
+    /// use ratatui::style::Color;
+    ///
+    /// let colors = LabelColors::default();
+    /// assert_eq!(colors.color_for(0), Color::Red);
+    /// assert_eq!(colors.color_for(24), Color::Red);
+    /// assert_eq!(colors.color_for(25), Color::Yellow);
+    /// assert_eq!(colors.color_for(49), Color::Yellow);
+    /// assert_eq!(colors.color_for(50), Color::Green);
+    /// assert_eq!(colors.color_for(100), Color::Green);
+    /// ```
+    pub fn color_for(&self, percent: i64) -> Color {
+        if percent < self.low {
+            self.low_color
+        } else if percent < self.mid {
+            self.mid_color
+        } else {
+            self.high_color
+        }
+    }
+}
+
+/// How gauge bars are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GaugeGlyphs {
+    /// Whole segments of `*` (or `#`) and blanks; works with any font.
+    #[default]
+    Ascii,
+    /// Unicode blocks, with the last cell filled to the nearest eighth.
+    Blocks,
+}
+
+/// Left-aligned blocks from one eighth to seven eighths of a cell.
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// The glyph for a cell filled `eighths`/8: blank, a partial block or `█`.
+///
+/// ```
+/// use mud_tui::gauges::{block_glyph, gauge_fill};
+///
+/// assert_eq!(block_glyph(0), ' ');
+/// assert_eq!(block_glyph(1), '▏');
+/// assert_eq!(block_glyph(4), '▌');
+/// assert_eq!(block_glyph(7), '▉');
+/// assert_eq!(block_glyph(8), '█');
+/// // 55% of a 10-cell bar: five full cells and half of the sixth.
+/// let eighths = gauge_fill(55, 100, 10 * 8);
+/// assert_eq!((eighths / 8, block_glyph(eighths % 8)), (5, '▌'));
+/// ```
+pub fn block_glyph(eighths: usize) -> char {
+    match eighths {
+        0 => ' ',
+        1..=7 => PARTIAL_BLOCKS[eighths - 1],
+        _ => '█',
+    }
+}
+
+/// Draws a gauge's bar: one span per `(color, width)` segment. In ASCII a
+/// segment is all `fill` or all `empty` (in `empty_color`); with blocks the
+/// cells fill left to right to the nearest eighth.
+fn bar(
+    current: i32,
+    max: i32,
+    segments: &[(Color, usize)],
+    fill: char,
+    empty: (char, Color),
+    glyphs: GaugeGlyphs,
+) -> Vec<Span<'static>> {
+    match glyphs {
+        GaugeGlyphs::Ascii => {
+            let filled = gauge_fill(current, max, segments.len());
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, &(color, width))| {
+                    let (ch, color) = if i < filled { (fill, color) } else { empty };
+                    Span::styled(ch.to_string().repeat(width), Style::default().fg(color))
+                })
+                .collect()
+        }
+        GaugeGlyphs::Blocks => {
+            let cells: usize = segments.iter().map(|&(_, width)| width).sum();
+            let mut eighths = gauge_fill(current, max, cells * 8);
+            segments
+                .iter()
+                .map(|&(color, width)| {
+                    let text: String = (0..width)
+                        .map(|_| {
+                            let cell = eighths.min(8);
+                            eighths -= cell;
+                            block_glyph(cell)
+                        })
+                        .collect();
+                    Span::styled(text, Style::default().fg(color))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Converts a marker like "$x196" or "$G" into a Color, exactly as chat text
+/// colors it (see `ansi_color::convert_color_marker`); resets and unknown
+/// markers give white.
+///
+/// ```
+/// use mud_tui::ansi_color;
+/// use mud_tui::gauges::convert_color_marker;
+/// use ratatui::style::Color;
+///
+/// for marker in ["$x196", "$x048", "$G", "$R", "$g", "$D", "$W", "$0", "$n", "$?"] {
+///     let shared = ansi_color::convert_color_marker(marker).map_or(Color::White, Color::from);
+///     assert_eq!(convert_color_marker(marker), shared);
+/// }
+/// assert_eq!(convert_color_marker("$G"), Color::Rgb(0, 255, 0));
+/// ```
+pub fn convert_color_marker(marker: &str) -> Color {
+    ansi_color::convert_color_marker(marker).map_or(Color::White, Color::from)
+}
+
+/// Color of the closing bracket and label of a gauge above its maximum.
+const OVERCHARGED: Color = Color::LightCyan;
+
+fn overcharged(current: i32, max: i32) -> bool {
+    max > 0 && current > max
+}
+
+/// The gauge's closing bracket, in `OVERCHARGED` when above the maximum.
+fn closing_bracket(current: i32, max: i32, color: Color) -> Span<'static> {
+    let color = if overcharged(current, max) { OVERCHARGED } else { color };
+    Span::styled("]", Style::default().fg(color))
+}
+
+/// How many of a gauge's `segments` are filled for `current` out of `max`,
+/// rounding down. A `max` of zero or less gives an empty gauge; `current` is
+/// clamped to `0..=max`, so an overfull value fills every segment.
+///
+/// ```
+/// use mud_tui::gauges::gauge_fill;
+///
+/// assert_eq!(gauge_fill(50, 100, 10), 5);
+/// assert_eq!(gauge_fill(99, 100, 10), 9);
+/// assert_eq!(gauge_fill(100, 100, 10), 10);
+/// assert_eq!(gauge_fill(10, 0, 10), 0);
+/// assert_eq!(gauge_fill(10, -5, 10), 0);
+/// assert_eq!(gauge_fill(1200, 1000, 10), 10);
+/// assert_eq!(gauge_fill(-30, 100, 10), 0);
+/// ```
+pub fn gauge_fill(current: i32, max: i32, segments: usize) -> usize {
+    if max <= 0 {
+        return 0;
+    }
+    let current = current.clamp(0, max) as i64;
+    (current * segments as i64 / max as i64) as usize
+}
+
+/// Renders the HP gauge using the defined color progression. Above the
+/// maximum the bar stays full and the closing bracket and label change color.
+///
+/// ```
+/// use mud_tui::gauges::{render_hp_gauge, GaugeGlyphs, GaugeLabel, LabelColors};
+/// use ratatui::style::Color;
+///
+/// let colors = LabelColors::default();
+/// let spans = render_hp_gauge(1200, 1000, GaugeLabel::Percent, GaugeGlyphs::Ascii, &colors);
+/// let bar: String = spans.iter().map(|s| s.content.as_ref()).collect();
+/// assert_eq!(bar, "HP: [**********] 120%");
+///
+/// let spans = render_hp_gauge(50, 100, GaugeLabel::None, GaugeGlyphs::Blocks, &colors);
+/// let bar: String = spans.iter().map(|s| s.content.as_ref()).collect();
+/// assert_eq!(bar, "HP: [█████     ]");
+///
+/// // The label is colored by the percentage, rounded down: 249/1000 is 24%.
+/// for (current, color) in [(249, Color::Red), (250, Color::Yellow), (499, Color::Yellow), (500, Color::Green)] {
+///     let spans = render_hp_gauge(current, 1000, GaugeLabel::Numbers, GaugeGlyphs::Ascii, &colors);
+///     assert_eq!(spans.last().unwrap().style.fg, Some(color));
+/// }
+/// ```
+pub fn render_hp_gauge(
+    current: i32,
+    max: i32,
+    label: GaugeLabel,
+    glyphs: GaugeGlyphs,
+    colors: &LabelColors,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let label_color = convert_color_marker("$x048");
+    spans.push(Span::styled("HP: ", Style::default().fg(label_color)));
+    let bracket_color = convert_color_marker("$x238");
+    spans.push(Span::styled("[", Style::default().fg(bracket_color)));
+
+    let fill_codes = ["$x196", "$x202", "$x208", "$x214", "$x220", "$x226", "$x190", "$x154", "$x010"];
+    // The last segment is two cells wide.
+    let segments: Vec<(Color, usize)> = fill_codes
+        .iter()
+        .enumerate()
+        .map(|(i, code)| (convert_color_marker(code), if i == fill_codes.len() - 1 { 2 } else { 1 }))
+        .collect();
+    let empty = (' ', convert_color_marker("$0"));
+    spans.extend(bar(current, max, &segments, '*', empty, glyphs));
+    spans.push(closing_bracket(current, max, bracket_color));
+    spans.extend(label.span(current, max, colors));
+    spans
+}
+
+/// Renders the Mana gauge.
+pub fn render_mana_gauge(
+    current: i32,
+    max: i32,
+    label: GaugeLabel,
+    glyphs: GaugeGlyphs,
+    colors: &LabelColors,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let label_color = convert_color_marker("$x171");
+    spans.push(Span::styled("MN: ", Style::default().fg(label_color)));
+    let bracket_color = convert_color_marker("$x238");
+    spans.push(Span::styled("[", Style::default().fg(bracket_color)));
+
+    let fill_codes = ["$x027", "$x063", "$x099", "$x135", "$x171"];
+    let segments: Vec<(Color, usize)> = fill_codes.iter().map(|code| (convert_color_marker(code), 2)).collect();
+    let empty = (' ', convert_color_marker("$x238"));
+    spans.extend(bar(current, max, &segments, '*', empty, glyphs));
+    spans.push(closing_bracket(current, max, bracket_color));
+    spans.extend(label.span(current, max, colors));
+    spans
+}
+
+/// Renders the Movement gauge.
+pub fn render_mv_gauge(
+    current: i32,
+    max: i32,
+    label: GaugeLabel,
+    glyphs: GaugeGlyphs,
+    colors: &LabelColors,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    
+    let label_color = convert_color_marker("$x228");
+    spans.push(Span::styled("MV: ", Style::default().fg(label_color)));
+
+    let bracket_color = convert_color_marker("$x238");
+    spans.push(Span::styled("[", Style::default().fg(bracket_color)));
+
+    let fill_codes = ["$x172", "$x178", "$x220", "$x221", "$x228"];
+    let segments: Vec<(Color, usize)> = fill_codes.iter().map(|code| (convert_color_marker(code), 2)).collect();
+    let empty = (' ', convert_color_marker("$x238"));
+    spans.extend(bar(current, max, &segments, '*', empty, glyphs));
+
+    spans.push(closing_bracket(current, max, bracket_color));
+    spans.extend(label.span(current, max, colors));
+
+    spans
+}
+
+/// Progress toward the next level from char.status, which only sends the
+/// experience still needed (`tnl`). Without a configured amount per level,
+/// the largest `tnl` seen during the level stands for the whole level, so a
+/// session started mid-level counts from where it started.
+///
+/// ```
+/// use mud_tui::gauges::TnlProgress;
+///
+/// let mut xp = TnlProgress::new(10, 1000);
+/// assert_eq!(xp.percent(None), Some(0));
+/// xp.update(10, 250);
+/// assert_eq!(xp.percent(None), Some(75));
+/// // A configured amount per level replaces the learned one.
+/// assert_eq!(xp.percent(Some(2000)), Some(87));
+///
+/// // Leveling up starts again from the new tnl.
+/// xp.update(11, 1200);
+/// assert_eq!(xp.percent(None), Some(0));
+/// xp.update(11, 300);
+/// assert_eq!(xp.percent(None), Some(75));
+///
+/// // Losing experience within a level raises the baseline.
+/// xp.update(11, 1500);
+/// assert_eq!(xp.percent(None), Some(0));
+///
+/// // At the level cap the server sends 0: there is nothing to show.
+/// assert_eq!(TnlProgress::new(50, 0).percent(None), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TnlProgress {
+    level: i32,
+    tnl: i64,
+    /// The largest `tnl` seen at this level.
+    baseline: i64,
+}
+
+impl TnlProgress {
+    pub fn new(level: i32, tnl: i64) -> Self {
+        Self { level, tnl, baseline: tnl }
+    }
+
+    /// Takes a char.status update, re-baselining on a new level.
+    pub fn update(&mut self, level: i32, tnl: i64) {
+        if level != self.level || tnl > self.baseline {
+            self.baseline = tnl;
+        }
+        self.level = level;
+        self.tnl = tnl;
+    }
+
+    /// Experience gained this level and the level's total, using
+    /// `per_level` as the total when set.
+    fn progress(&self, per_level: Option<i64>) -> Option<(i64, i64)> {
+        let total = per_level.filter(|&n| n > 0).unwrap_or(self.baseline);
+        (total > 0).then(|| ((total - self.tnl).clamp(0, total), total))
+    }
+
+    /// Percent of the level done, rounded down; `None` when there is no
+    /// level total to measure against.
+    pub fn percent(&self, per_level: Option<i64>) -> Option<i64> {
+        self.progress(per_level).map(|(done, total)| done * 100 / total)
+    }
+}
+
+/// Renders the experience gauge: progress through the current level, or
+/// nothing when it cannot be told (see [`TnlProgress::percent`]).
+pub fn render_tnl_gauge(
+    progress: &TnlProgress,
+    per_level: Option<i64>,
+    label: GaugeLabel,
+    glyphs: GaugeGlyphs,
+    colors: &LabelColors,
+) -> Option<Vec<Span<'static>>> {
+    let (done, total) = progress.progress(per_level)?;
+    // Experience totals can pass i32; the bar and label only need the ratio
+    // at that size.
+    let scale = (total / i32::MAX as i64) + 1;
+    let (current, max) = ((done / scale) as i32, (total / scale) as i32);
+    let mut spans = Vec::new();
+    let bracket_color = convert_color_marker("$x238");
+    spans.push(Span::styled("XP: ", Style::default().fg(convert_color_marker("$x075"))));
+    spans.push(Span::styled("[", Style::default().fg(bracket_color)));
+    let fill_codes = ["$x025", "$x032", "$x039", "$x045", "$x051"];
+    let segments: Vec<(Color, usize)> = fill_codes.iter().map(|code| (convert_color_marker(code), 2)).collect();
+    spans.extend(bar(current, max, &segments, '*', (' ', bracket_color), glyphs));
+    spans.push(closing_bracket(current, max, bracket_color));
+    spans.extend(label.span(current, max, colors));
+    Some(spans)
+}
+
+/// Longest enemy name shown before the enemy gauge; longer ones are cut.
+const ENEMY_NAME_WIDTH: usize = 16;
+
+/// Renders the enemy gauge using enemy hp and maximum hp, labelled with the
+/// enemy's `name` ("EN" when empty).
+pub fn render_enemy_gauge(
+    name: &str,
+    current: i32,
+    max: i32,
+    label: GaugeLabel,
+    glyphs: GaugeGlyphs,
+    colors: &LabelColors,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let name = if name.is_empty() {
+        "EN".to_string()
+    } else if name.chars().count() > ENEMY_NAME_WIDTH {
+        let kept: String = name.chars().take(ENEMY_NAME_WIDTH - 1).collect();
+        format!("{}…", kept)
+    } else {
+        name.to_string()
+    };
+    spans.push(Span::styled(format!("{}: ", name), Style::default().fg(Color::Red)));
+    spans.push(Span::styled("[", Style::default().fg(Color::Gray)));
+    let segments = [(Color::Red, 2); 10];
+    spans.extend(bar(current, max, &segments, '#', ('-', Color::DarkGray), glyphs));
+    spans.push(closing_bracket(current, max, Color::Gray));
+    spans.extend(label.span(current, max, colors));
+    spans
+}
+
+/// The group on one line, e.g. `Group 3/4: Alice 95% (Bob) 40% Carol 100%`:
+/// members in the room out of all members, then each member's HP percentage
+/// in `colors`. Members who are not in the room are shown in parentheses and
+/// dimmed. Members that do not fit in `width` columns are counted as `+N`.
+///
+/// ```
+/// use mud_tui::gauges::{render_group_summary, LabelColors};
+/// use mud_tui::telnet_client::GroupMember;
+/// use ratatui::style::Color;
+/// use serde_json::json;
+///
+/// let member = |name: &str, hp: i32, here: i32| -> GroupMember {
+///     serde_json::from_value(json!({"name": name, "info": {"hp": hp, "mhp": 100, "mn": 0, "mmn": 0,
+///         "mv": 0, "mmv": 0, "align": 0, "tnl": 0, "qt": 0, "qs": 0, "lvl": 1, "here": here}}))
+///     .unwrap()
+/// };
+/// let group = [member("Alice", 95, 1), member("Bob", 40, 0), member("Carol", 100, 1), member("Dave", 12, 1)];
+/// let text = |spans: &[ratatui::text::Span]| spans.iter().map(|s| s.content.as_ref()).collect::<String>();
+///
+/// let spans = render_group_summary(&group, 80, &LabelColors::default());
+/// assert_eq!(text(&spans), "Group 3/4: Alice 95% (Bob) 40% Carol 100% Dave 12%");
+/// let dave = spans.last().unwrap();
+/// assert_eq!((dave.content.as_ref(), dave.style.fg), ("12%", Some(Color::Red)));
+///
+/// // Whoever does not fit is counted at the end.
+/// let spans = render_group_summary(&group, 30, &LabelColors::default());
+/// assert_eq!(text(&spans), "Group 3/4: Alice 95% +3");
+/// ```
+pub fn render_group_summary(members: &[GroupMember], width: usize, colors: &LabelColors) -> Vec<Span<'static>> {
+    let here = members.iter().filter(|m| m.info.here != 0).count();
+    let title = format!("Group {}/{}:", here, members.len());
+    let mut used = title.chars().count();
+    let mut spans = vec![Span::styled(title, Style::default().fg(Color::Gray))];
+    for (i, member) in members.iter().enumerate() {
+        let percent = if member.info.mhp > 0 { member.info.hp as i64 * 100 / member.info.mhp as i64 } else { 0 };
+        let (name, name_style) = if member.info.here != 0 {
+            (member.name.clone(), Style::default().fg(Color::White))
+        } else {
+            (format!("({})", member.name), Style::default().fg(Color::DarkGray))
+        };
+        let percent_text = format!("{}%", percent);
+        let piece = 1 + name.chars().count() + 1 + percent_text.len();
+        let left = members.len() - i - 1;
+        let reserve = if left > 0 { format!(" +{}", left).len() } else { 0 };
+        if used + piece + reserve > width {
+            let more = format!(" +{}", members.len() - i);
+            if used + more.len() <= width {
+                spans.push(Span::styled(more, Style::default().fg(Color::Gray)));
+            }
+            break;
+        }
+        let mut percent_style = Style::default().fg(colors.color_for(percent));
+        if member.info.here == 0 {
+            percent_style = percent_style.add_modifier(Modifier::DIM);
+        }
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(name, name_style));
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(percent_text, percent_style));
+        used += piece;
+    }
+    spans
+}
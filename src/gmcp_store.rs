@@ -16,30 +16,61 @@ impl GMCPStore {
     /// Update the GMCP store with a new message.
     ///
     /// `package` is a dot‑separated string (e.g. "room.info" or "char.vitals").
-    /// `value` is the JSON value associated with the package.
+    /// Package names are case-insensitive, so they are stored lowercased:
+    /// `Char.Vitals` and `char.vitals` update the same entry.
+    /// `value` is the JSON value associated with the package. A bare package
+    /// (`Value::Null` payload) is recorded as present without discarding data
+    /// already stored under it.
+    ///
+    /// ```
+    /// use mud_tui::GMCPStore;
+    /// use serde_json::json;
+    ///
+    /// let mut store = GMCPStore::new();
+    /// store.update("Char.Vitals", json!({"hp": 10}));
+    /// store.update("char.vitals", json!({"hp": 12}));
+    /// assert_eq!(store.get("char.vitals.hp"), Some(&json!(12)));
+    /// ```
     pub fn update(&mut self, package: &str, value: Value) {
+        let package = package.to_lowercase();
         let parts: Vec<&str> = package.split('.').collect();
         let mut current = self.data.as_object_mut().expect("GMCPStore data should be an object");
     
         for (i, part) in parts.iter().enumerate() {
             if i == parts.len() - 1 {
-                // Clone value only when needed
-                current.insert((*part).to_string(), value.clone());
-            } else {
-                if !current.contains_key(*part) {
-                    current.insert((*part).to_string(), Value::Object(Map::new()));
+                if value.is_null() && current.contains_key(*part) {
+                    return;
                 }
-                current = current.get_mut(*part)
-                    .and_then(|v| v.as_object_mut())
-                    .expect("Expected object in GMCPStore");
+                current.insert((*part).to_string(), value);
+                return;
+            }
+            let entry = current
+                .entry((*part).to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            // A package seen bare (or as a scalar) may later gain sub-packages.
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
             }
+            current = entry.as_object_mut().expect("entry was made an object above");
         }
     }
     
 
+    /// A copy of everything stored, for viewing without holding the lock.
+    pub fn snapshot(&self) -> Value {
+        self.data.clone()
+    }
+
+    /// Forget everything received so far (e.g. when switching servers).
+    pub fn clear(&mut self) {
+        self.data = Value::Object(Map::new());
+    }
+
     /// Retrieve a value from the GMCP store by a dot‑separated key path.
     ///
     /// For example, calling `get("room.info.exits")` returns the corresponding value if present.
+    /// Package names must be given in lowercase; keys inside the payload keep
+    /// the server's casing.
     pub fn get(&self, key: &str) -> Option<&Value> {
         let mut current = &self.data;
         for part in key.split('.') {
@@ -0,0 +1,139 @@
+// src/inspector.rs - Read-only, foldable view of a GMCP store snapshot (F5)
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// One visible line of the tree.
+struct Row {
+    path: String,
+    depth: usize,
+    key: String,
+    /// `Some(child count)` for objects and arrays.
+    children: Option<usize>,
+    /// Leaf values, printed as JSON.
+    value: String,
+}
+
+/// The inspector works on a copy of the store taken when it was opened, so
+/// the receive task never waits on it. Nodes start folded below the top level.
+pub(crate) struct GmcpInspector {
+    data: Value,
+    expanded: HashSet<String>,
+    cursor: usize,
+    top: usize,
+}
+
+impl GmcpInspector {
+    pub(crate) fn new(data: Value) -> Self {
+        Self { data, expanded: HashSet::new(), cursor: 0, top: 0 }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        if let Some(map) = self.data.as_object() {
+            for (key, value) in map {
+                self.collect(key, key, value, 0, &mut rows);
+            }
+        }
+        rows
+    }
+
+    fn collect(&self, path: &str, key: &str, value: &Value, depth: usize, rows: &mut Vec<Row>) {
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            Value::Array(items) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v)).collect(),
+            _ => {
+                let value = value.to_string();
+                rows.push(Row { path: path.to_string(), depth, key: key.to_string(), children: None, value });
+                return;
+            }
+        };
+        rows.push(Row {
+            path: path.to_string(),
+            depth,
+            key: key.to_string(),
+            children: Some(children.len()),
+            value: String::new(),
+        });
+        if self.expanded.contains(path) {
+            for (child_key, child) in children {
+                self.collect(&format!("{}.{}", path, child_key), &child_key, child, depth + 1, rows);
+            }
+        }
+    }
+
+    pub(crate) fn up(&mut self, lines: usize) {
+        self.cursor = self.cursor.saturating_sub(lines);
+    }
+
+    pub(crate) fn down(&mut self, lines: usize) {
+        let last = self.rows().len().saturating_sub(1);
+        self.cursor = (self.cursor + lines).min(last);
+    }
+
+    /// Unfolds (`true`) or folds the node under the cursor. Folding a leaf or
+    /// an already folded node moves to its parent instead.
+    pub(crate) fn set_expanded(&mut self, expand: bool) {
+        let rows = self.rows();
+        let Some(row) = rows.get(self.cursor) else { return };
+        if expand {
+            if row.children.is_some() {
+                self.expanded.insert(row.path.clone());
+            }
+        } else if !self.expanded.remove(&row.path) {
+            if let Some(parent) = rows[..self.cursor].iter().rposition(|r| r.depth < row.depth) {
+                self.cursor = parent;
+            }
+        }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        let expand = self.rows().get(self.cursor).is_some_and(|row| !self.expanded.contains(&row.path));
+        self.set_expanded(expand);
+    }
+
+    /// The rows that fit in `height`, scrolled to keep the cursor in view.
+    pub(crate) fn lines(&mut self, height: usize) -> Vec<Line<'static>> {
+        let rows = self.rows();
+        if rows.is_empty() {
+            return vec![Line::from("No GMCP data received yet")];
+        }
+        self.cursor = self.cursor.min(rows.len() - 1);
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if height > 0 && self.cursor >= self.top + height {
+            self.top = self.cursor + 1 - height;
+        }
+        rows.iter()
+            .enumerate()
+            .skip(self.top)
+            .take(height)
+            .map(|(i, row)| {
+                let indent = "  ".repeat(row.depth);
+                let mut spans = match row.children {
+                    Some(count) => {
+                        let marker = if self.expanded.contains(&row.path) { "▾" } else { "▸" };
+                        vec![
+                            Span::raw(format!("{}{} ", indent, marker)),
+                            Span::styled(row.key.clone(), Style::default().fg(Color::LightCyan)),
+                            Span::styled(format!(" ({})", count), Style::default().fg(Color::DarkGray)),
+                        ]
+                    }
+                    None => vec![
+                        Span::raw(format!("{}  ", indent)),
+                        Span::styled(row.key.clone(), Style::default().fg(Color::LightCyan)),
+                        Span::raw(": "),
+                        Span::styled(row.value.clone(), Style::default().fg(Color::White)),
+                    ],
+                };
+                if i == self.cursor {
+                    for span in &mut spans {
+                        span.style = span.style.add_modifier(Modifier::REVERSED);
+                    }
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
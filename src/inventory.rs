@@ -0,0 +1,51 @@
+// src/inventory.rs - Live item lists kept in sync from GMCP char.items.*
+use crate::telnet_client::Item;
+use std::collections::BTreeMap;
+
+/// Items per location ("inv", "room", or a container id), in the order the
+/// server listed them.
+#[derive(Debug, Default)]
+pub struct Inventory {
+    locations: BTreeMap<String, Vec<Item>>,
+}
+
+impl Inventory {
+    /// char.items.list: the full contents of `location`, replacing what was known.
+    pub fn set(&mut self, location: String, items: Vec<Item>) {
+        self.locations.insert(location, items);
+    }
+
+    /// char.items.add: `item` arrived in `location`. An item already listed
+    /// under the same id is replaced rather than duplicated.
+    pub fn add(&mut self, location: String, item: Item) {
+        let items = self.locations.entry(location).or_default();
+        match items.iter_mut().find(|i| i.id == item.id) {
+            Some(existing) => *existing = item,
+            None => items.push(item),
+        }
+    }
+
+    /// char.items.remove: the item with `id` left `location`.
+    pub fn remove(&mut self, location: &str, id: &str) {
+        if let Some(items) = self.locations.get_mut(location) {
+            items.retain(|i| i.id != id);
+        }
+    }
+
+    /// char.items.update: an item's name or attributes changed (e.g. worn).
+    pub fn update(&mut self, location: String, item: Item) {
+        self.add(location, item);
+    }
+
+    pub fn items(&self, location: &str) -> &[Item] {
+        self.locations.get(location).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.locations.clear();
+    }
+}
@@ -0,0 +1,73 @@
+//! MudForge: an asynchronous MUD client core with a terminal front-end.
+//!
+//! The networking layer (telnet negotiation, GMCP/MSDP, ANSI parsing) is usable
+//! on its own, e.g. to build a different front-end. Connect a [`TelnetClient`]
+//! and read [`TelnetMessage`]s from the channel it was created with:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use mud_tui::{GMCPStore, TelnetClient, TelnetMessage};
+//! use tokio::sync::{mpsc, Mutex};
+//!
+//! # async fn example() -> Result<(), String> {
+//! let (tx, mut rx) = mpsc::channel(100);
+//! let client = TelnetClient::new(tx);
+//! let gmcp = Arc::new(Mutex::new(GMCPStore::new()));
+//! client.connect("darkwiz.org", "6969", gmcp.clone()).await?;
+//! client.send_command("look").await?;
+//!
+//! while let Some(msg) = rx.recv().await {
+//!     match msg {
+//!         TelnetMessage::MUDOutput(line) => println!("{}", line.text()),
+//!         TelnetMessage::CharVitals(hp, mana, movement) => println!("HP {} MN {} MV {}", hp, mana, movement),
+//!         TelnetMessage::Disconnect(reason) => {
+//!             println!("Disconnected: {}", reason);
+//!             break;
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`run`] starts the full terminal client, exactly as the `mud_tui` binary does.
+
+pub mod ansi_color;
+pub mod gauges;
+pub mod gmcp_store;
+pub mod msdp_store;
+pub mod styled;
+pub mod telnet_client;
+
+mod affects;
+mod app;
+mod autologin;
+mod away;
+mod clipboard;
+mod commands;
+mod config;
+mod inspector;
+mod inventory;
+mod logging;
+mod macros;
+mod mapper;
+mod prompt;
+mod scripting;
+mod session_log;
+mod skills;
+mod status_socket;
+mod substitutions;
+mod tick;
+mod timers;
+mod triggers;
+mod tui;
+mod ui;
+
+pub use ansi_color::parse_ansi_codes;
+pub use gauges::{render_enemy_gauge, render_hp_gauge, render_mana_gauge, render_mv_gauge};
+pub use gmcp_store::GMCPStore;
+pub use logging::init_logging;
+pub use styled::{Rgb, Segment, StyledLine, TextStyle};
+pub use telnet_client::{parse_gmcp, GmcpMessage, TelnetClient, TelnetMessage};
+pub use tui::{run, RunOptions};
@@ -0,0 +1,52 @@
+// src/logging.rs - Setting up the debug log from the config file and command line
+use crate::config::{Config, CONFIG_FILE};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use simplelog::{Config as LogConfig, WriteLogger};
+use std::fs::File;
+use std::path::Path;
+
+/// The `[log]` config section; `--log-file` and `--log-level` override it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub file: String,
+    /// `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            file: "mud_tui_debug.log".to_string(),
+            level: "debug".to_string(),
+        }
+    }
+}
+
+/// Starts logging to the configured file at the configured level. `file` and
+/// `level` (from the command line) take precedence over the config file.
+///
+/// Level `off` installs no logger at all. If the log file cannot be created
+/// the log goes to stderr instead.
+pub fn init_logging(file: Option<String>, level: Option<String>) -> Result<(), String> {
+    let config = Config::load(Path::new(CONFIG_FILE)).log;
+    let file = file.unwrap_or(config.file);
+    let level = level.unwrap_or(config.level);
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| format!("Invalid log level '{}' (use off, error, warn, info, debug or trace)", level))?;
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+    let result = match File::create(&file) {
+        Ok(out) => WriteLogger::init(level, LogConfig::default(), out),
+        Err(e) => {
+            eprintln!("Cannot create log file {}: {}; logging to stderr", file, e);
+            WriteLogger::init(level, LogConfig::default(), std::io::stderr())
+        }
+    };
+    result.map_err(|e| format!("Failed to start logging: {}", e))?;
+    log::info!("Starting MUD TUI. Logs in {}", file);
+    Ok(())
+}
@@ -0,0 +1,166 @@
+// src/macros.rs - Keys bound to a sequence of commands for the MUD
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::warn;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A key combination such as `F6`, `ctrl+k` or `alt+shift+f2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacroKey {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl MacroKey {
+    /// Parses `[ctrl+][alt+][shift+]<key>`, case-insensitively. `<key>` is
+    /// F1-F24, a single character, or one of the named keys below.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.trim().split('+').collect();
+        let key = parts.pop()?.to_lowercase();
+        for part in parts {
+            match part.trim().to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => return None,
+            }
+        }
+        let code = match key.as_str() {
+            "insert" | "ins" => KeyCode::Insert,
+            "delete" | "del" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            _ => match key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=24).contains(&n) => KeyCode::F(n),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+        Some(Self { code, modifiers }.normalized())
+    }
+
+    /// Terminals report Shift on letters as an upper-case character, with or
+    /// without the SHIFT flag, so character keys compare lower-cased and
+    /// without Shift.
+    fn normalized(self) -> Self {
+        match self.code {
+            KeyCode::Char(c) => Self {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers: self.modifiers - KeyModifiers::SHIFT,
+            },
+            _ => self,
+        }
+    }
+
+    fn matches(&self, key: &KeyEvent) -> bool {
+        let pressed = Self { code: key.code, modifiers: key.modifiers }.normalized();
+        pressed == *self
+    }
+}
+
+impl fmt::Display for MacroKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (flag, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(flag) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Splits `/macro` text into commands: `quaff heal;kick` sends two.
+pub fn split_commands(text: &str) -> Vec<String> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|cmd| !cmd.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Macro bindings, from the `[macros]` config section and `/macro`.
+#[derive(Default)]
+pub struct Macros {
+    bindings: Vec<(MacroKey, Vec<String>)>,
+}
+
+impl Macros {
+    /// Builds the bindings from config, skipping (and logging) keys that
+    /// cannot be parsed.
+    pub fn from_config(config: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut macros = Self::default();
+        for (key, commands) in config {
+            match MacroKey::parse(key) {
+                Some(key) => macros.bind(key, commands.clone()),
+                None => warn!("Invalid macro key '{}'", key),
+            }
+        }
+        macros
+    }
+
+    /// Binds `key`, replacing what it was bound to before.
+    pub fn bind(&mut self, key: MacroKey, commands: Vec<String>) {
+        match self.bindings.iter_mut().find(|(k, _)| *k == key) {
+            Some(binding) => binding.1 = commands,
+            None => self.bindings.push((key, commands)),
+        }
+    }
+
+    pub fn remove(&mut self, key: MacroKey) -> bool {
+        let before = self.bindings.len();
+        self.bindings.retain(|(k, _)| *k != key);
+        self.bindings.len() != before
+    }
+
+    /// The commands bound to the pressed key, if any.
+    pub fn lookup(&self, key: &KeyEvent) -> Option<&[String]> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| k.matches(key))
+            .map(|(_, commands)| commands.as_slice())
+    }
+
+    pub fn get(&self, key: MacroKey) -> Option<&[String]> {
+        self.bindings.iter().find(|(k, _)| *k == key).map(|(_, commands)| commands.as_slice())
+    }
+
+    /// The bindings in the form of the `[macros]` config section.
+    pub fn to_config(&self) -> BTreeMap<String, Vec<String>> {
+        self.bindings
+            .iter()
+            .map(|(key, commands)| (key.to_string(), commands.clone()))
+            .collect()
+    }
+
+    /// One `key: cmd; cmd` line per binding, in the order they were made.
+    pub fn describe(&self) -> Vec<String> {
+        self.bindings
+            .iter()
+            .map(|(key, commands)| format!("{}: {}", key, commands.join("; ")))
+            .collect()
+    }
+}
@@ -0,0 +1,241 @@
+// src/mapper.rs - Minimal auto-mapper built from room.info ids and exits
+use crate::telnet_client::{RoomInfo, UNKNOWN_ROOM};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Mapper settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapperConfig {
+    pub enabled: bool,
+    /// Where discovered rooms are saved between sessions.
+    pub file: String,
+}
+
+impl Default for MapperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: "mud_map.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MappedRoom {
+    pub name: String,
+    pub zone: String,
+    /// Direction => destination room id, `UNKNOWN_ROOM` until learned.
+    pub exits: HashMap<String, i32>,
+}
+
+/// Every room seen so far, plus where the character is now.
+#[derive(Debug, Default)]
+pub struct Mapper {
+    rooms: HashMap<i32, MappedRoom>,
+    current: Option<i32>,
+    /// Direction of the last movement command sent, used to learn where an
+    /// exit with an unknown destination leads once the next room arrives.
+    pending_move: Option<&'static str>,
+    path: Option<PathBuf>,
+}
+
+impl Mapper {
+    /// Loads a previously saved map from `path`; a missing or unreadable file
+    /// starts an empty map. The map is saved back to the same file as it grows.
+    pub fn load(path: &Path) -> Self {
+        let rooms = match fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(rooms) => rooms,
+                Err(e) => {
+                    warn!("Malformed map file {}: {}; starting a new map", path.display(), e);
+                    HashMap::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                warn!("Failed to read map file {}: {}; starting a new map", path.display(), e);
+                HashMap::new()
+            }
+        };
+        info!("Loaded {} mapped rooms from {}", rooms.len(), path.display());
+        Self {
+            rooms,
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        }
+    }
+
+    pub fn current(&self) -> Option<i32> {
+        self.current
+    }
+
+    /// Remembers the direction of a command about to be sent, if it is a movement.
+    pub fn note_command(&mut self, cmd: &str) {
+        self.pending_move = direction(cmd.trim()).map(|d| d.name);
+    }
+
+    /// Records the room the character just entered and links it to the
+    /// previous room through the direction they walked.
+    pub fn enter(&mut self, room: &RoomInfo) {
+        if room.num == UNKNOWN_ROOM {
+            return;
+        }
+        let mut changed = false;
+        let entry = self.rooms.entry(room.num).or_default();
+        if entry.name != room.name || entry.zone != room.zone {
+            entry.name = room.name.clone();
+            entry.zone = room.zone.clone();
+            changed = true;
+        }
+        for (dir, &dest) in &room.exits {
+            let known = entry.exits.get(dir).copied();
+            // Keep a learned destination if the server doesn't send one.
+            if dest != UNKNOWN_ROOM || known.is_none() {
+                changed |= known != Some(dest);
+                entry.exits.insert(dir.clone(), dest);
+            }
+        }
+
+        let moved = self.pending_move.take();
+        if let (Some(from), Some(dir)) = (self.current, moved) {
+            if from != room.num {
+                changed |= self.link(from, dir, room.num);
+                if let Some(back) = direction(dir).map(|d| d.opposite) {
+                    changed |= self.link(room.num, back, from);
+                }
+            }
+        }
+        self.current = Some(room.num);
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Fills in the destination of `from`'s exit in `dir` if it is unknown.
+    fn link(&mut self, from: i32, dir: &str, to: i32) -> bool {
+        let Some(room) = self.rooms.get_mut(&from) else {
+            return false;
+        };
+        let exit = room.exits.iter_mut().find(|(name, _)| {
+            direction(name).map(|d| d.name) == Some(dir)
+        });
+        match exit {
+            Some((_, dest)) if *dest == UNKNOWN_ROOM => {
+                *dest = to;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        match serde_json::to_string(&self.rooms) {
+            Ok(text) => {
+                if let Err(e) = fs::write(path, text) {
+                    warn!("Failed to save map to {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize map: {}", e),
+        }
+    }
+
+    /// Draws the rooms around the current one into `width` x `height` characters.
+    ///
+    /// Rooms are `[ ]` boxes two columns apart horizontally and one row apart
+    /// vertically, joined by `-`, `|`, `/` and `\`. The current room is `[@]`;
+    /// other rooms show `^`, `v` or `%` for exits up, down, or both. Exits whose
+    /// destination is unknown are drawn as stubs.
+    pub fn render(&self, width: u16, height: u16) -> Vec<String> {
+        let (width, height) = (width as i32, height as i32);
+        let mut grid = vec![vec![' '; width.max(0) as usize]; height.max(0) as usize];
+        let Some(start) = self.current else {
+            return Vec::new();
+        };
+        let mut put = |col: i32, row: i32, ch: char| {
+            if (0..width).contains(&col) && (0..height).contains(&row) {
+                grid[row as usize][col as usize] = ch;
+            }
+        };
+        let (origin_col, origin_row) = (width / 2, height / 2);
+
+        let mut placed: HashMap<(i32, i32), i32> = HashMap::new();
+        let mut seen: HashSet<i32> = HashSet::new();
+        let mut queue = VecDeque::from([(start, 0, 0)]);
+        placed.insert((0, 0), start);
+        seen.insert(start);
+        while let Some((num, x, y)) = queue.pop_front() {
+            let (col, row) = (origin_col + x * 4, origin_row + y * 2);
+            if !(0..width).contains(&col) || !(0..height).contains(&row) {
+                continue;
+            }
+            let room = self.rooms.get(&num);
+            let exits = room.map(|r| &r.exits);
+            let has = |name: &str| {
+                exits.is_some_and(|e| e.keys().any(|k| direction(k).map(|d| d.name) == Some(name)))
+            };
+            let mark = if num == start {
+                '@'
+            } else {
+                match (has("up"), has("down")) {
+                    (true, true) => '%',
+                    (true, false) => '^',
+                    (false, true) => 'v',
+                    _ => ' ',
+                }
+            };
+            put(col - 1, row, '[');
+            put(col, row, mark);
+            put(col + 1, row, ']');
+
+            for (name, &dest) in exits.into_iter().flatten() {
+                let Some(dir) = direction(name) else { continue };
+                let Some((dx, dy)) = dir.offset else { continue };
+                put(col + dx * 2, row + dy, dir.connector);
+                let target = (x + dx, y + dy);
+                if dest != UNKNOWN_ROOM && !seen.contains(&dest) && !placed.contains_key(&target) {
+                    placed.insert(target, dest);
+                    seen.insert(dest);
+                    queue.push_back((dest, target.0, target.1));
+                }
+            }
+        }
+        grid.into_iter().map(|row| row.into_iter().collect()).collect()
+    }
+}
+
+struct Direction {
+    name: &'static str,
+    opposite: &'static str,
+    /// Grid step for compass directions; `None` for up/down.
+    offset: Option<(i32, i32)>,
+    connector: char,
+}
+
+static DIRECTIONS: [(&str, &str, Direction); 10] = [
+    ("n", "north", Direction { name: "north", opposite: "south", offset: Some((0, -1)), connector: '|' }),
+    ("s", "south", Direction { name: "south", opposite: "north", offset: Some((0, 1)), connector: '|' }),
+    ("e", "east", Direction { name: "east", opposite: "west", offset: Some((1, 0)), connector: '-' }),
+    ("w", "west", Direction { name: "west", opposite: "east", offset: Some((-1, 0)), connector: '-' }),
+    ("ne", "northeast", Direction { name: "northeast", opposite: "southwest", offset: Some((1, -1)), connector: '/' }),
+    ("sw", "southwest", Direction { name: "southwest", opposite: "northeast", offset: Some((-1, 1)), connector: '/' }),
+    ("nw", "northwest", Direction { name: "northwest", opposite: "southeast", offset: Some((-1, -1)), connector: '\\' }),
+    ("se", "southeast", Direction { name: "southeast", opposite: "northwest", offset: Some((1, 1)), connector: '\\' }),
+    ("u", "up", Direction { name: "up", opposite: "down", offset: None, connector: ' ' }),
+    ("d", "down", Direction { name: "down", opposite: "up", offset: None, connector: ' ' }),
+];
+
+/// Recognizes a direction by its short or long name ("n", "North").
+fn direction(text: &str) -> Option<&'static Direction> {
+    let text = text.to_lowercase();
+    DIRECTIONS
+        .iter()
+        .find(|(short, long, _)| text == *short || text == *long)
+        .map(|(_, _, dir)| dir)
+}
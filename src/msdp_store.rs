@@ -0,0 +1,135 @@
+// src/msdp_store.rs - MSDP (Mud Server Data Protocol, telnet option 69) variables
+use std::collections::BTreeMap;
+
+pub const MSDP_VAR: u8 = 1;
+pub const MSDP_VAL: u8 = 2;
+pub const MSDP_TABLE_OPEN: u8 = 3;
+pub const MSDP_TABLE_CLOSE: u8 = 4;
+pub const MSDP_ARRAY_OPEN: u8 = 5;
+pub const MSDP_ARRAY_CLOSE: u8 = 6;
+
+/// A single MSDP value: plain text, an array, or a table of named values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsdpValue {
+    Text(String),
+    Array(Vec<MsdpValue>),
+    Table(BTreeMap<String, MsdpValue>),
+}
+
+/// The latest value of every MSDP variable the server has sent, the MSDP
+/// counterpart of `GMCPStore`.
+#[derive(Debug, Default)]
+pub struct MsdpStore {
+    vars: BTreeMap<String, MsdpValue>,
+}
+
+impl MsdpStore {
+    pub fn update(&mut self, name: &str, value: MsdpValue) {
+        self.vars.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MsdpValue> {
+        self.vars.get(name)
+    }
+
+    /// The variable as text, if it is a plain value.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            MsdpValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The variable parsed as a whole number.
+    pub fn number(&self, name: &str) -> Option<i32> {
+        self.text(name)?.trim().parse().ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+}
+
+/// Parses the body of an MSDP subnegotiation (without IAC SB MSDP / IAC SE)
+/// into `(variable, value)` pairs. A variable followed by several values is
+/// returned as an array. Malformed input is skipped rather than rejected.
+pub fn parse_msdp(buf: &[u8]) -> Vec<(String, MsdpValue)> {
+    let mut i = 0;
+    parse_pairs(buf, &mut i, None)
+}
+
+fn parse_pairs(buf: &[u8], i: &mut usize, close: Option<u8>) -> Vec<(String, MsdpValue)> {
+    let mut pairs = Vec::new();
+    while *i < buf.len() {
+        let byte = buf[*i];
+        if Some(byte) == close {
+            *i += 1;
+            break;
+        }
+        if byte != MSDP_VAR {
+            *i += 1;
+            continue;
+        }
+        *i += 1;
+        let name = read_text(buf, i);
+        let mut values = Vec::new();
+        while *i < buf.len() && buf[*i] == MSDP_VAL {
+            *i += 1;
+            values.push(parse_value(buf, i));
+        }
+        let value = match values.len() {
+            1 => values.remove(0),
+            _ => MsdpValue::Array(values),
+        };
+        pairs.push((name, value));
+    }
+    pairs
+}
+
+fn parse_value(buf: &[u8], i: &mut usize) -> MsdpValue {
+    match buf.get(*i) {
+        Some(&MSDP_TABLE_OPEN) => {
+            *i += 1;
+            MsdpValue::Table(parse_pairs(buf, i, Some(MSDP_TABLE_CLOSE)).into_iter().collect())
+        }
+        Some(&MSDP_ARRAY_OPEN) => {
+            *i += 1;
+            let mut items = Vec::new();
+            while *i < buf.len() {
+                match buf[*i] {
+                    MSDP_ARRAY_CLOSE => {
+                        *i += 1;
+                        break;
+                    }
+                    MSDP_VAL => {
+                        *i += 1;
+                        items.push(parse_value(buf, i));
+                    }
+                    _ => *i += 1,
+                }
+            }
+            MsdpValue::Array(items)
+        }
+        _ => MsdpValue::Text(read_text(buf, i)),
+    }
+}
+
+/// Reads bytes up to the next MSDP control byte.
+fn read_text(buf: &[u8], i: &mut usize) -> String {
+    let start = *i;
+    while *i < buf.len() && !(MSDP_VAR..=MSDP_ARRAY_CLOSE).contains(&buf[*i]) {
+        *i += 1;
+    }
+    String::from_utf8_lossy(&buf[start..*i]).into_owned()
+}
+
+/// Builds the subnegotiation body asking the server to report `vars` whenever they change.
+pub fn report_request(vars: &[&str]) -> Vec<u8> {
+    let mut body = vec![MSDP_VAR];
+    body.extend_from_slice(b"REPORT");
+    for var in vars {
+        body.push(MSDP_VAL);
+        body.extend_from_slice(var.as_bytes());
+    }
+    body
+}
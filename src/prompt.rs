@@ -0,0 +1,62 @@
+// src/prompt.rs - Reading vitals out of text prompts for MUDs without GMCP
+use log::warn;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+/// The `[prompt]` config section.
+///
+/// `pattern` is a regex with named groups `hp`, `maxhp`, `mana`, `maxmana`,
+/// `movement` and `maxmove`; any subset may be present.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptConfig {
+    pub pattern: Option<String>,
+    /// Hide matched prompt lines from the output pane.
+    pub gag: bool,
+}
+
+/// Values read from one prompt line; `None` for groups the pattern lacks or
+/// that did not take part in the match.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PromptVitals {
+    pub hp: Option<i32>,
+    pub maxhp: Option<i32>,
+    pub mana: Option<i32>,
+    pub maxmana: Option<i32>,
+    pub movement: Option<i32>,
+    pub maxmove: Option<i32>,
+}
+
+pub struct PromptParser {
+    pattern: Regex,
+    pub gag: bool,
+}
+
+impl PromptParser {
+    /// Builds the parser, or `None` when no pattern is configured or it is
+    /// not a valid regex (which is logged).
+    pub fn from_config(cfg: &PromptConfig) -> Option<Self> {
+        let pattern = cfg.pattern.as_deref()?;
+        match Regex::new(pattern) {
+            Ok(pattern) => Some(Self { pattern, gag: cfg.gag }),
+            Err(e) => {
+                warn!("Invalid prompt pattern '{}': {}", pattern, e);
+                None
+            }
+        }
+    }
+
+    /// Reads the vitals from `line` if it is a prompt.
+    pub fn parse(&self, line: &str) -> Option<PromptVitals> {
+        let caps = self.pattern.captures(line)?;
+        let num = |caps: &Captures, name: &str| caps.name(name)?.as_str().trim().parse().ok();
+        Some(PromptVitals {
+            hp: num(&caps, "hp"),
+            maxhp: num(&caps, "maxhp"),
+            mana: num(&caps, "mana"),
+            maxmana: num(&caps, "maxmana"),
+            movement: num(&caps, "movement"),
+            maxmove: num(&caps, "maxmove"),
+        })
+    }
+}
@@ -0,0 +1,226 @@
+// src/scripting.rs - Embedded Rhai scripting engine
+//
+// Scripts are `*.rhai` files loaded from the scripts directory at startup.
+// They can call:
+//   send(cmd)             - send a command to the MUD
+//   echo(text)            - print a line in the output pane
+//   gmcp_get(path)        - read a value from the GMCP store (alias: gmcp)
+//   trigger(pattern, fn)  - call `fn(line, captures)` when output matches `pattern`
+//
+// Script code runs on tokio's blocking pool with an operation limit, so a
+// runaway script cannot stall the async runtime.
+use crate::gmcp_store::GMCPStore;
+use crate::telnet_client::{TelnetClient, TelnetMessage};
+use log::{error, info, warn};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+
+/// Upper bound on operations per script run before it is aborted.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// Settings from the `[scripting]` config section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptingConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for ScriptingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "scripts".to_string(),
+        }
+    }
+}
+
+/// Side effects requested by a script, applied after it finishes.
+pub enum ScriptAction {
+    Send(String),
+    Echo(String),
+}
+
+/// A trigger registered by a script via `trigger(pattern, fn)`.
+pub struct ScriptTrigger {
+    pub script: String,
+    pub pattern: String,
+    pub function: String,
+}
+
+/// Everything a script run produced.
+#[derive(Default)]
+pub struct ScriptOutput {
+    pub actions: Vec<ScriptAction>,
+    pub triggers: Vec<ScriptTrigger>,
+}
+
+pub struct ScriptEngine {
+    engine: Engine,
+    dir: PathBuf,
+    ui: Sender<TelnetMessage>,
+    scripts: Mutex<HashMap<String, AST>>,
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    registered: Arc<Mutex<Vec<(String, String)>>>,
+    // Scripts share the action buffers, so only one runs at a time.
+    run_lock: Mutex<()>,
+}
+
+impl ScriptEngine {
+    pub fn new(dir: &Path, gmcp_store: Arc<tokio::sync::Mutex<GMCPStore>>, ui: Sender<TelnetMessage>) -> Self {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let a = actions.clone();
+        engine.register_fn("send", move |cmd: &str| {
+            a.lock().unwrap().push(ScriptAction::Send(cmd.to_string()));
+        });
+        let a = actions.clone();
+        engine.register_fn("echo", move |text: &str| {
+            a.lock().unwrap().push(ScriptAction::Echo(text.to_string()));
+        });
+        for name in ["gmcp_get", "gmcp"] {
+            let store = gmcp_store.clone();
+            engine.register_fn(name, move |path: &str| -> Dynamic {
+                // Scripts only run on the blocking pool, where blocking_lock is allowed.
+                let store = store.blocking_lock();
+                store
+                    .get(path)
+                    .and_then(|v| rhai::serde::to_dynamic(v).ok())
+                    .unwrap_or(Dynamic::UNIT)
+            });
+        }
+        let r = registered.clone();
+        engine.register_fn("trigger", move |pattern: &str, function: &str| {
+            r.lock().unwrap().push((pattern.to_string(), function.to_string()));
+        });
+
+        Self {
+            engine,
+            dir: dir.to_path_buf(),
+            ui,
+            scripts: Mutex::new(HashMap::new()),
+            actions,
+            registered,
+            run_lock: Mutex::new(()),
+        }
+    }
+
+    /// Loads and runs every `*.rhai` file in the scripts directory.
+    /// Must be called from a blocking context.
+    pub fn load_all(&self) -> ScriptOutput {
+        let mut output = ScriptOutput::default();
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            info!("No scripts directory at {}", self.dir.display());
+            return output;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("rhai") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+                continue;
+            };
+            match self.run_file(&name) {
+                Ok(mut out) => {
+                    output.actions.append(&mut out.actions);
+                    output.triggers.append(&mut out.triggers);
+                }
+                Err(e) => warn!("Failed to load script {}: {}", name, e),
+            }
+        }
+        output
+    }
+
+    /// Compiles and runs `<dir>/<name>.rhai`, keeping its functions for later trigger calls.
+    /// Must be called from a blocking context.
+    pub fn run_file(&self, name: &str) -> Result<ScriptOutput, String> {
+        let _guard = self.run_lock.lock().unwrap();
+        let path = self.dir.join(format!("{}.rhai", name.trim_end_matches(".rhai")));
+        let ast = self
+            .engine
+            .compile_file(path.clone())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let result = self.engine.run_ast(&ast);
+        self.scripts.lock().unwrap().insert(name.to_string(), ast);
+        let output = self.take_output(name);
+        result.map_err(|e| e.to_string())?;
+        info!("Ran script {}", name);
+        Ok(output)
+    }
+
+    /// Calls `function(line, captures)` in a loaded script.
+    /// Must be called from a blocking context.
+    pub fn call(&self, script: &str, function: &str, line: String, captures: Vec<String>) -> Result<ScriptOutput, String> {
+        let _guard = self.run_lock.lock().unwrap();
+        let ast = self
+            .scripts
+            .lock()
+            .unwrap()
+            .get(script)
+            .cloned()
+            .ok_or_else(|| format!("script '{}' is not loaded", script))?;
+        let captures: Array = captures.into_iter().map(Dynamic::from).collect();
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &ast, function, (line, captures));
+        let output = self.take_output(script);
+        result.map(|_| output).map_err(|e| e.to_string())
+    }
+
+    fn take_output(&self, script: &str) -> ScriptOutput {
+        let actions = std::mem::take(&mut *self.actions.lock().unwrap());
+        let triggers = std::mem::take(&mut *self.registered.lock().unwrap())
+            .into_iter()
+            .map(|(pattern, function)| ScriptTrigger { script: script.to_string(), pattern, function })
+            .collect();
+        ScriptOutput { actions, triggers }
+    }
+}
+
+/// Runs a script job on the blocking pool, then applies the actions it requested.
+pub fn spawn_script<F>(engine: Arc<ScriptEngine>, client: TelnetClient, job: F)
+where
+    F: FnOnce(&ScriptEngine) -> Result<ScriptOutput, String> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let ui = engine.ui.clone();
+        match tokio::task::spawn_blocking(move || job(&engine)).await {
+            Ok(Ok(output)) => {
+                if !output.triggers.is_empty() {
+                    info!("Ignoring {} trigger(s) registered outside startup", output.triggers.len());
+                }
+                apply_actions(&client, &ui, output.actions).await;
+            }
+            Ok(Err(e)) => {
+                let _ = ui.send(TelnetMessage::Info(format!("Script error: {}", e))).await;
+            }
+            Err(e) => error!("Script task failed: {}", e),
+        }
+    });
+}
+
+/// Performs the sends and echoes a script asked for, in order.
+pub async fn apply_actions(client: &TelnetClient, ui: &Sender<TelnetMessage>, actions: Vec<ScriptAction>) {
+    for action in actions {
+        match action {
+            ScriptAction::Send(cmd) => {
+                if let Err(e) = client.send_command_echoed(&cmd).await {
+                    error!("Script send failed: {}", e);
+                }
+            }
+            ScriptAction::Echo(text) => {
+                let _ = ui.send(TelnetMessage::Info(text)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// src/session_log.rs - Plain-text transcript of the MUD output, and reading it back
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The `[session_log]` config section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLogConfig {
+    pub enabled: bool,
+    /// Output lines are appended here as plain text.
+    pub file: String,
+}
+
+impl Default for SessionLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: "mud_session.log".to_string(),
+        }
+    }
+}
+
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl SessionLog {
+    /// Opens the log for appending, or `None` when it is disabled or the file
+    /// cannot be opened (which is logged).
+    pub fn open(cfg: &SessionLogConfig) -> Option<Self> {
+        if !cfg.enabled {
+            return None;
+        }
+        let path = PathBuf::from(&cfg.file);
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => Some(Self { path, file }),
+            Err(e) => {
+                warn!("Cannot open session log {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        if let Err(e) = writeln!(self.file, "{}", line) {
+            warn!("Failed to write session log {}: {}", self.path.display(), e);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// The last `count` lines of the log at `path`.
+pub fn tail(path: &Path, count: usize) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let lines: Vec<&str> = text.lines().collect();
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..].iter().map(|l| l.to_string()).collect())
+}
@@ -0,0 +1,82 @@
+// src/skills.rs - Skill readiness and cooldowns from GMCP char.skills, counted down locally
+use crate::telnet_client::Skill;
+use std::time::{Duration, Instant};
+
+/// Whether a skill can be used now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkillState {
+    Ready,
+    /// On cooldown for this long.
+    Cooldown(Duration),
+    /// Not available, with no known end (e.g. `"ready": false` without a cooldown).
+    Unavailable,
+}
+
+struct TrackedSkill {
+    name: String,
+    /// When the cooldown ends; `None` when there is none.
+    ready_at: Option<Instant>,
+    /// The server's `ready` flag, if it sent one.
+    ready: Option<bool>,
+}
+
+impl TrackedSkill {
+    fn new(skill: Skill, now: Instant) -> Self {
+        Self {
+            name: skill.name,
+            ready_at: skill.cooldown.filter(|&secs| secs > 0.0).map(|secs| now + Duration::from_secs_f64(secs)),
+            ready: skill.ready,
+        }
+    }
+
+    fn state(&self, now: Instant) -> SkillState {
+        match self.ready_at {
+            Some(end) if end > now => SkillState::Cooldown(end - now),
+            // A cooldown that ran out means ready, whatever the flag said when it started.
+            Some(_) => SkillState::Ready,
+            None if self.ready == Some(false) => SkillState::Unavailable,
+            None => SkillState::Ready,
+        }
+    }
+}
+
+/// Skills in the order the server listed them.
+#[derive(Default)]
+pub struct SkillTracker {
+    skills: Vec<TrackedSkill>,
+}
+
+impl SkillTracker {
+    /// A full char.skills list, replacing what was known.
+    pub fn set(&mut self, skills: Vec<Skill>) {
+        let now = Instant::now();
+        self.skills = skills.into_iter().map(|s| TrackedSkill::new(s, now)).collect();
+    }
+
+    /// An incremental update: the named skills change, others keep their
+    /// state. Skills not seen before are added at the end.
+    pub fn update(&mut self, skills: Vec<Skill>) {
+        let now = Instant::now();
+        for skill in skills {
+            let tracked = TrackedSkill::new(skill, now);
+            match self.skills.iter_mut().find(|s| s.name.eq_ignore_ascii_case(&tracked.name)) {
+                Some(existing) => *existing = tracked,
+                None => self.skills.push(tracked),
+            }
+        }
+    }
+
+    /// Every skill with its state right now.
+    pub fn states(&self) -> Vec<(&str, SkillState)> {
+        let now = Instant::now();
+        self.skills.iter().map(|s| (s.name.as_str(), s.state(now))).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.skills.clear();
+    }
+}
@@ -0,0 +1,194 @@
+// src/status_socket.rs - JSON snapshots of the character's state for other programs, over a Unix socket
+use crate::app::AppState;
+use crate::telnet_client::TelnetMessage;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Snapshots a slow reader may fall behind by before it skips to newer ones.
+const BACKLOG: usize = 64;
+
+/// Pause after a failed accept before trying again.
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// Whether handling `msg` may change what a snapshot holds.
+pub(crate) fn changes_status(msg: &TelnetMessage) -> bool {
+    matches!(
+        msg,
+        TelnetMessage::Connected(_)
+            | TelnetMessage::Disconnect(_)
+            | TelnetMessage::CharLogin(_)
+            | TelnetMessage::CharVitals(..)
+            | TelnetMessage::CharMaxStats(..)
+            | TelnetMessage::CharStatus(_)
+            | TelnetMessage::RoomInfo(_)
+            | TelnetMessage::GroupInfo(_)
+    )
+}
+
+/// The state of one session as a JSON object; see the README for the fields.
+pub(crate) fn snapshot(st: &AppState) -> Value {
+    let session = st.last_server.as_ref().map(|(host, port)| format!("{}:{}", host, port));
+    let vitals = st.gmcp_vitals.as_ref().map(|v| json!({ "hp": v.hp, "mana": v.mana, "movement": v.movement }));
+    let maxstats = st
+        .gmcp_maxstats
+        .as_ref()
+        .map(|m| json!({ "maxhp": m.maxhp, "maxmana": m.maxmana, "maxmove": m.maxmove }));
+    let status = st.char_status.as_ref().map(|s| {
+        json!({ "level": s.level, "tnl": s.tnl, "state": s.state, "enemy": s.enemy, "enemypct": s.enemypct })
+    });
+    let room = st.current_room.as_ref().map(|r| {
+        let exits: BTreeMap<&str, i32> = r.exits.iter().map(|(dir, id)| (dir.as_str(), *id)).collect();
+        json!({ "num": r.num, "name": r.name, "zone": r.zone, "exits": exits })
+    });
+    let group = st.group_info.as_ref().map(|g| {
+        let members: Vec<Value> = g
+            .members
+            .iter()
+            .map(|m| {
+                let i = &m.info;
+                json!({
+                    "name": m.name, "hp": i.hp, "mhp": i.mhp, "mn": i.mn, "mmn": i.mmn,
+                    "mv": i.mv, "mmv": i.mmv, "lvl": i.lvl, "here": i.here != 0,
+                })
+            })
+            .collect();
+        let enemies: Vec<Value> =
+            g.enemies.iter().map(|e| json!({ "name": e.name, "hp": e.info.hp, "mhp": e.info.mhp })).collect();
+        json!({ "name": g.groupname, "leader": g.leader, "members": members, "enemies": enemies })
+    });
+    json!({
+        "session": session,
+        "connected": st.connected,
+        "character": st.char_name,
+        "vitals": vitals,
+        "maxstats": maxstats,
+        "status": status,
+        "room": room,
+        "group": group,
+    })
+}
+
+/// Hands snapshots to the socket's readers. Cloned into every session.
+#[derive(Clone)]
+pub(crate) struct StatusPublisher {
+    sender: broadcast::Sender<Arc<str>>,
+    /// The newest snapshot of each session, sent to readers as they connect.
+    latest: Arc<Mutex<BTreeMap<String, Arc<str>>>>,
+}
+
+impl StatusPublisher {
+    pub(crate) fn publish(&self, st: &AppState) {
+        let snapshot = snapshot(st);
+        let key = snapshot["session"].as_str().unwrap_or_default().to_string();
+        let line: Arc<str> = format!("{}\n", snapshot).into();
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).insert(key, line.clone());
+        // No readers is not an error.
+        let _ = self.sender.send(line);
+    }
+}
+
+/// A listening Unix socket that streams snapshots, one JSON object per line,
+/// to any number of readers.
+pub(crate) struct StatusSocket {
+    path: PathBuf,
+    publisher: StatusPublisher,
+    shutdown: CancellationToken,
+    accept_task: JoinHandle<()>,
+}
+
+impl StatusSocket {
+    /// Listens on `path`, replacing a socket left behind by an earlier run.
+    #[cfg(unix)]
+    pub(crate) fn bind(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+        use tokio::net::UnixListener;
+
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        info!("Status socket listening on {}", path.display());
+        let (sender, _) = broadcast::channel(BACKLOG);
+        let publisher = StatusPublisher { sender, latest: Arc::default() };
+        let shutdown = CancellationToken::new();
+        let accept_publisher = publisher.clone();
+        let accept_shutdown = shutdown.clone();
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            // Errors such as running out of file descriptors tend
+                            // to persist; don't retry (and log) in a tight loop.
+                            warn!("Status socket accept failed: {}", e);
+                            tokio::select! {
+                                _ = tokio::time::sleep(ACCEPT_RETRY) => continue,
+                                _ = accept_shutdown.cancelled() => break,
+                            }
+                        }
+                    },
+                    _ = accept_shutdown.cancelled() => break,
+                };
+                tokio::spawn(serve_reader(stream, accept_publisher.clone(), accept_shutdown.clone()));
+            }
+        });
+        Ok(Self { path: path.to_path_buf(), publisher, shutdown, accept_task })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn bind(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "status sockets need a Unix system"))
+    }
+
+    pub(crate) fn publisher(&self) -> StatusPublisher {
+        self.publisher.clone()
+    }
+
+    /// Disconnects every reader and removes the socket file.
+    pub(crate) async fn close(self) {
+        self.shutdown.cancel();
+        let _ = self.accept_task.await;
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Cannot remove status socket {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Writes the newest snapshots, then every new one, until the reader goes
+/// away or the socket closes.
+#[cfg(unix)]
+async fn serve_reader(mut stream: tokio::net::UnixStream, publisher: StatusPublisher, shutdown: CancellationToken) {
+    use tokio::io::AsyncWriteExt;
+
+    let mut updates = publisher.sender.subscribe();
+    let latest: Vec<Arc<str>> =
+        publisher.latest.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect();
+    for line in latest {
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let line = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(line) => line,
+                // Skipped snapshots are stale anyway.
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = shutdown.cancelled() => return,
+        };
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
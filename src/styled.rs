@@ -0,0 +1,212 @@
+// src/styled.rs - Styled text produced by the parsers, independent of any UI library
+use regex::Regex;
+
+/// A 24-bit color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// Colors and attributes of a run of text. `None` colors mean "whatever the
+/// front-end uses by default" (the TUI draws white on the terminal background).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl TextStyle {
+    pub fn fg(mut self, color: Rgb) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Rgb) -> Self {
+        self.bg = Some(color);
+        self
+    }
+}
+
+/// A run of text sharing one style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub style: TextStyle,
+    /// The URL when the text is a hyperlink (OSC 8).
+    pub link: Option<String>,
+}
+
+/// One line of output as a sequence of styled segments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledLine {
+    pub segments: Vec<Segment>,
+    /// Background color still set when the line ended. Terminals paint it to
+    /// the right edge, which MUD status bars rely on.
+    pub fill_bg: Option<Rgb>,
+}
+
+impl StyledLine {
+    /// Appends `text` in `style`; empty text is ignored.
+    pub fn push(&mut self, text: impl Into<String>, style: TextStyle) {
+        self.push_link(text, style, None);
+    }
+
+    /// Appends `text` in `style` as a hyperlink to `link` (if any).
+    pub fn push_link(&mut self, text: impl Into<String>, style: TextStyle, link: Option<String>) {
+        let text = text.into();
+        if !text.is_empty() {
+            self.segments.push(Segment { text, style, link });
+        }
+    }
+
+    /// The line's text without any styling.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// `text` without inline `$`-color markers, the way the output pane's
+    /// lines are copied and logged.
+    pub fn plain_text(&self) -> String {
+        crate::ansi_color::strip_color_markers(&self.text())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The line cut to `max` characters, the last of which become `marker`;
+    /// `None` when it already fits. The marker takes the style of the text
+    /// before it.
+    ///
+    /// ```
+    /// use mud_tui::styled::{StyledLine, TextStyle};
+    ///
+    /// let mut line = StyledLine::default();
+    /// line.push("x".repeat(5000), TextStyle::default());
+    /// let cut = line.truncated(80, "…").unwrap();
+    /// assert_eq!(cut.text().chars().count(), 80);
+    /// assert!(cut.text().ends_with("x…"));
+    /// assert!(cut.truncated(80, "…").is_none());
+    /// ```
+    pub fn truncated(&self, max: usize, marker: &str) -> Option<StyledLine> {
+        if self.char_len() <= max {
+            return None;
+        }
+        let keep = max.saturating_sub(marker.chars().count());
+        let mut out = self.slice_chars(0, keep);
+        let style = out.segments.last().map(|s| s.style).unwrap_or_default();
+        out.push(marker, style);
+        Some(out)
+    }
+
+    /// The line split into pieces of at most `max` characters (at least one
+    /// piece, however short the line), each keeping the line's styles.
+    ///
+    /// ```
+    /// use mud_tui::styled::{Rgb, StyledLine, TextStyle};
+    ///
+    /// let red = TextStyle::default().fg(Rgb(255, 0, 0));
+    /// let mut line = StyledLine::default();
+    /// line.push("a".repeat(2500), TextStyle::default());
+    /// line.push("b".repeat(2500), red);
+    /// let pieces = line.wrapped(1000);
+    /// assert_eq!(pieces.len(), 5);
+    /// assert!(pieces.iter().all(|p| p.text().chars().count() == 1000));
+    /// // The piece where the colors change holds both.
+    /// assert_eq!(pieces[2].segments.len(), 2);
+    /// assert_eq!(pieces[2].segments[1].style, red);
+    /// assert_eq!(pieces.iter().map(StyledLine::text).collect::<String>(), line.text());
+    /// ```
+    pub fn wrapped(&self, max: usize) -> Vec<StyledLine> {
+        let len = self.char_len();
+        if len <= max || max == 0 {
+            return vec![self.clone()];
+        }
+        (0..len).step_by(max).map(|from| self.slice_chars(from, from + max)).collect()
+    }
+
+    fn char_len(&self) -> usize {
+        self.segments.iter().map(|s| s.text.chars().count()).sum()
+    }
+
+    /// Characters `from..to` of the line, keeping their styles.
+    fn slice_chars(&self, from: usize, to: usize) -> StyledLine {
+        let mut out = StyledLine { segments: Vec::new(), fill_bg: self.fill_bg };
+        let mut start = 0;
+        for segment in &self.segments {
+            let len = segment.text.chars().count();
+            let (a, b) = (from.max(start), to.min(start + len));
+            if a < b {
+                let text: String = segment.text.chars().skip(a - start).take(b - a).collect();
+                out.push_link(text, segment.style, segment.link.clone());
+            }
+            start += len;
+        }
+        out
+    }
+
+    /// Replaces every match of `pattern` in the line's text with
+    /// `replacement`, which may reference capture groups as `$1` or
+    /// `${name}`. The replacement takes the style of the segment where the
+    /// match starts; the text around it keeps its own. `None` when nothing
+    /// matched.
+    ///
+    /// ```
+    /// use mud_tui::styled::{Rgb, StyledLine, TextStyle};
+    /// use regex::Regex;
+    ///
+    /// let red = TextStyle::default().fg(Rgb(255, 0, 0));
+    /// let mut line = StyledLine::default();
+    /// line.push("You hit ", TextStyle::default());
+    /// line.push("a large green dragon", red);
+    /// line.push(" hard.", TextStyle::default());
+    ///
+    /// let pattern = Regex::new(r"a large (\w+) dragon").unwrap();
+    /// let line = line.replace_all(&pattern, "$1 drg").unwrap();
+    /// assert_eq!(line.text(), "You hit green drg hard.");
+    /// assert_eq!(line.segments[1].text, "green drg");
+    /// assert_eq!(line.segments[1].style, red);
+    ///
+    /// let named = Regex::new(r"(?P<verb>hit) (?P<who>\w+)").unwrap();
+    /// assert_eq!(line.replace_all(&named, "${who} gets ${verb}").unwrap().text(), "You green gets hit drg hard.");
+    /// assert!(line.replace_all(&Regex::new("goblin").unwrap(), "gob").is_none());
+    /// ```
+    pub fn replace_all(&self, pattern: &Regex, replacement: &str) -> Option<StyledLine> {
+        let text = self.text();
+        let mut edits = pattern
+            .captures_iter(&text)
+            .map(|caps| {
+                let whole = caps.get(0).expect("group 0 always matches");
+                let mut expanded = String::new();
+                caps.expand(replacement, &mut expanded);
+                (whole.start(), whole.end(), expanded)
+            })
+            .peekable();
+        edits.peek()?;
+
+        let mut out = StyledLine { segments: Vec::new(), fill_bg: self.fill_bg };
+        // Bytes before `consumed` were replaced by an earlier edit.
+        let (mut start, mut consumed) = (0, 0);
+        for segment in &self.segments {
+            let end = start + segment.text.len();
+            let mut cursor = start.max(consumed);
+            while let Some((from, to, with)) = edits.next_if(|(from, _, _)| *from < end) {
+                out.push_link(&text[cursor..from], segment.style, segment.link.clone());
+                out.push_link(with, segment.style, segment.link.clone());
+                cursor = to;
+                consumed = to;
+            }
+            if cursor < end {
+                out.push_link(&text[cursor..end], segment.style, segment.link.clone());
+            }
+            start = end;
+        }
+        // Matches at the very end (e.g. of `$`) take the last segment's style.
+        let last = self.segments.last();
+        for (_, _, with) in edits {
+            out.push_link(with, last.map(|s| s.style).unwrap_or_default(), last.and_then(|s| s.link.clone()));
+        }
+        Some(out)
+    }
+}
@@ -0,0 +1,89 @@
+// src/substitutions.rs - Regex substitutions that rewrite lines of MUD output
+use crate::styled::StyledLine;
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A substitution from the `[[substitutions]]` config section. `replace` may
+/// reference capture groups as `$1` or `${name}`; an empty one deletes the
+/// matched text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubstitutionConfig {
+    pub pattern: String,
+    #[serde(default)]
+    pub replace: String,
+}
+
+struct Substitution {
+    pattern: Regex,
+    replace: String,
+}
+
+#[derive(Default)]
+pub struct Substitutions {
+    list: Vec<Substitution>,
+}
+
+impl Substitutions {
+    /// Builds the substitutions from config, skipping (and logging) invalid patterns.
+    pub fn from_config(configs: &[SubstitutionConfig]) -> Self {
+        let mut subs = Self::default();
+        for cfg in configs {
+            if let Err(e) = subs.add(&cfg.pattern, &cfg.replace) {
+                warn!("{}", e);
+            }
+        }
+        subs
+    }
+
+    /// Adds a substitution, or changes the replacement of one with the same pattern.
+    pub fn add(&mut self, pattern: &str, replace: &str) -> Result<(), String> {
+        if let Some(existing) = self.list.iter_mut().find(|s| s.pattern.as_str() == pattern) {
+            existing.replace = replace.to_string();
+            return Ok(());
+        }
+        let pattern = Regex::new(pattern).map_err(|e| format!("Invalid substitution pattern '{}': {}", pattern, e))?;
+        self.list.push(Substitution { pattern, replace: replace.to_string() });
+        Ok(())
+    }
+
+    /// Removes the substitution with this pattern, or at this 1-based
+    /// position in `describe`.
+    pub fn remove(&mut self, which: &str) -> bool {
+        let before = self.list.len();
+        match which.parse::<usize>() {
+            Ok(n) if (1..=before).contains(&n) => {
+                self.list.remove(n - 1);
+            }
+            _ => self.list.retain(|s| s.pattern.as_str() != which),
+        }
+        self.list.len() != before
+    }
+
+    /// Applies every substitution in order, each to the result of the last.
+    pub fn apply(&self, mut line: StyledLine) -> StyledLine {
+        for sub in &self.list {
+            if let Some(replaced) = line.replace_all(&sub.pattern, &sub.replace) {
+                line = replaced;
+            }
+        }
+        line
+    }
+
+    /// The substitutions in the form of the `[[substitutions]]` config section.
+    pub fn to_config(&self) -> Vec<SubstitutionConfig> {
+        self.list
+            .iter()
+            .map(|s| SubstitutionConfig { pattern: s.pattern.as_str().to_string(), replace: s.replace.clone() })
+            .collect()
+    }
+
+    /// One numbered `pattern => replacement` line per substitution.
+    pub fn describe(&self) -> Vec<String> {
+        self.list
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("{}. {} => {}", i + 1, s.pattern, s.replace))
+            .collect()
+    }
+}
@@ -1,18 +1,30 @@
 // src/telnet.rs - Telnet client implementation for MudForge TUI
-use crate::ansi_color::{parse_ansi_codes, COLOR_MAP, strip_mxp_tags};
+use crate::ansi_color::{
+    apply_sgr, convert_color_marker, marker_color, parse_ansi_codes_with_bell, strip_mxp_tags, Encoding, Utf8Carry,
+};
 use crate::gmcp_store::GMCPStore;
-use log::{debug, error, info};
-use ratatui::style::{Color, Style};
-use ratatui::text::Span;
-use serde::Deserialize;
+use crate::msdp_store::{parse_msdp, report_request, MsdpStore, MsdpValue};
+use crate::styled::{Rgb, StyledLine, TextStyle};
+use log::{debug, error, info, log_enabled, warn, Level};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
-use tokio::sync::{mpsc::Sender, Mutex};
-use tokio::time::{timeout, Duration};
+use tokio::sync::mpsc::{
+    error::{SendError, TrySendError},
+    Sender,
+};
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
+use libmudtelnet::compatibility::CompatibilityTable;
 use libmudtelnet::events::{TelnetEvents, TelnetSubnegotiation};
 use libmudtelnet::Parser;
 
@@ -21,10 +33,68 @@ use libmudtelnet::Parser;
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 const IAC: u8 = 255;
 const WILL: u8 = 251;
+const WONT: u8 = 252;
+const DO: u8 = 253;
+const DONT: u8 = 254;
 const SB: u8 = 250;
 const SE: u8 = 240;
+const TELOPT_ECHO: u8 = 1;
+const TELOPT_NAWS: u8 = 31;
+const TELOPT_MSDP: u8 = 69;
 const TELOPT_GMCP: u8 = 201;
 
+/// MSDP variables requested when the server offers MSDP; they feed the same
+/// messages as the matching GMCP packages.
+const MSDP_REPORTED: &[&str] = &[
+    "HEALTH", "HEALTH_MAX", "MANA", "MANA_MAX", "MOVEMENT", "MOVEMENT_MAX",
+    "ROOM_VNUM", "ROOM_NAME", "AREA_NAME", "ROOM_EXITS",
+];
+
+/// Options the parser negotiates on our behalf.
+fn telnet_support() -> CompatibilityTable {
+    let mut table = CompatibilityTable::new();
+    // Let the server take over echoing so password prompts can be detected.
+    table.support_remote(TELOPT_ECHO);
+    // Accept MSDP when the server offers it (IAC WILL MSDP gets IAC DO MSDP back).
+    table.support_remote(TELOPT_MSDP);
+    // Report the window size when the server asks (IAC DO NAWS).
+    table.support_local(TELOPT_NAWS);
+    table
+}
+
+/// The window size reported over NAWS (RFC 1073), shared with the read loop.
+#[derive(Default)]
+struct Naws {
+    /// Set once the server has asked for NAWS on this connection.
+    enabled: std::sync::atomic::AtomicBool,
+    size: std::sync::Mutex<Option<(u16, u16)>>,
+}
+
+impl Naws {
+    fn enabled(&self) -> bool {
+        self.enabled.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn set_enabled(&self, on: bool) {
+        self.enabled.store(on, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// `IAC SB NAWS <width> <height> IAC SE` for the current size, if known.
+    fn packet(&self) -> Option<Vec<u8>> {
+        let (width, height) = (*self.size.lock().ok()?)?;
+        let mut packet = vec![IAC, SB, TELOPT_NAWS];
+        for byte in width.to_be_bytes().into_iter().chain(height.to_be_bytes()) {
+            packet.push(byte);
+            // A 255 in the data must be doubled so it isn't read as IAC.
+            if byte == IAC {
+                packet.push(IAC);
+            }
+        }
+        packet.extend_from_slice(&[IAC, SE]);
+        Some(packet)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // GMCP data structures for known packages.
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -33,32 +103,110 @@ pub struct CharLogin {
     pub name: String,
 }
 
-// char.vitals gives only current values.
+/// char.vitals gives only current values.
+///
+/// Field names differ between MUDs, so common variants are accepted:
+/// `hp` (`hitpoints`, `health`), `mana` (`sp`, `mp`, `spellpoints`) and
+/// `movement` (`moves`, `move`, `mv`, `stamina`).
+///
+/// ```
+/// use mud_tui::telnet_client::CharVitals;
+/// use serde_json::json;
+///
+/// let v: CharVitals = serde_json::from_value(json!({"hp": 1, "mana": 2, "movement": 3})).unwrap();
+/// assert_eq!((v.hp, v.mana, v.movement), (1, 2, 3));
+/// let v: CharVitals = serde_json::from_value(json!({"hitpoints": 1, "sp": 2, "moves": 3})).unwrap();
+/// assert_eq!((v.hp, v.mana, v.movement), (1, 2, 3));
+/// let v: CharVitals = serde_json::from_value(json!({"health": 1, "mp": 2, "stamina": 3})).unwrap();
+/// assert_eq!((v.hp, v.mana, v.movement), (1, 2, 3));
+/// ```
 #[derive(Debug, Deserialize)]
 pub struct CharVitals {
+    #[serde(alias = "hitpoints", alias = "health")]
     pub hp: i32,
+    #[serde(alias = "sp", alias = "mp", alias = "spellpoints")]
     pub mana: i32,
+    #[serde(alias = "moves", alias = "move", alias = "mv", alias = "stamina")]
     pub movement: i32,
 }
 
-// char.maxstats gives maximum values.
+/// char.maxstats gives maximum values.
+///
+/// Accepted variants: `maxhp` (`maxhitpoints`, `maxhealth`, `max_hp`),
+/// `maxmana` (`maxsp`, `maxmp`, `max_mana`) and `maxmove` (`maxmoves`,
+/// `maxmv`, `maxstamina`, `max_move`). The attribute maxima are optional.
+///
+/// ```
+/// use mud_tui::telnet_client::CharMaxStats;
+/// use serde_json::json;
+///
+/// let m: CharMaxStats = serde_json::from_value(json!({"maxhp": 10, "maxmana": 20, "maxmove": 30})).unwrap();
+/// assert_eq!((m.maxhp, m.maxmana, m.maxmove), (10, 20, 30));
+/// let m: CharMaxStats = serde_json::from_value(json!({"maxhitpoints": 10, "maxsp": 20, "maxmoves": 30})).unwrap();
+/// assert_eq!((m.maxhp, m.maxmana, m.maxmove), (10, 20, 30));
+/// let m: CharMaxStats = serde_json::from_value(json!({"max_hp": 10, "max_mana": 20, "maxstamina": 30})).unwrap();
+/// assert_eq!((m.maxhp, m.maxmana, m.maxmove), (10, 20, 30));
+/// ```
 #[derive(Debug, Deserialize)]
 pub struct CharMaxStats {
+    #[serde(alias = "maxhitpoints", alias = "maxhealth", alias = "max_hp")]
     pub maxhp: i32,
+    #[serde(alias = "maxsp", alias = "maxmp", alias = "max_mana")]
     pub maxmana: i32,
+    #[serde(alias = "maxmoves", alias = "maxmv", alias = "maxstamina", alias = "max_move")]
     pub maxmove: i32,
+    #[serde(default)]
     pub maxstr: i32,
+    #[serde(default)]
     pub maxint: i32,
+    #[serde(default)]
     pub maxwis: i32,
+    #[serde(default)]
     pub maxdex: i32,
+    #[serde(default)]
     pub maxcon: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct RoomInfo {
     pub num: i32,
     pub name: String,
     pub zone: String,
+    /// Direction => destination room id (`UNKNOWN_ROOM` when the server doesn't say).
+    #[serde(default, deserialize_with = "deserialize_exits")]
+    pub exits: HashMap<String, i32>,
+}
+
+/// Room id used for exits whose destination the server didn't send.
+pub const UNKNOWN_ROOM: i32 = -1;
+
+/// Accepts exits either as an object (`{"n": 1234, "e": 1235}`) or as a plain
+/// list of directions (`["n", "e"]`), whose destinations are then unknown.
+fn deserialize_exits<'de, D>(deserializer: D) -> Result<HashMap<String, i32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Exits {
+        Map(HashMap<String, Value>),
+        List(Vec<String>),
+    }
+    Ok(match Option::<Exits>::deserialize(deserializer)? {
+        Some(Exits::Map(map)) => map
+            .into_iter()
+            .map(|(dir, dest)| {
+                let id = match dest {
+                    Value::Number(n) => n.as_i64().map(|n| n as i32),
+                    Value::String(s) => s.trim().parse().ok(),
+                    _ => None,
+                };
+                (dir, id.unwrap_or(UNKNOWN_ROOM))
+            })
+            .collect(),
+        Some(Exits::List(dirs)) => dirs.into_iter().map(|dir| (dir, UNKNOWN_ROOM)).collect(),
+        None => HashMap::new(),
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,14 +216,18 @@ pub struct CommChannel {
     pub player: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// char.status. `state` is the character's position ("fighting", "resting",
+/// ...) and `enemy` the name of whoever it is fighting, empty out of combat.
+#[derive(Debug, Clone, Deserialize)]
 pub struct CharStatus {
     pub level: i32,
     pub tnl: i64,
     pub hunger: i32,
     pub thirst: i32,
     pub align: i32,
+    #[serde(default)]
     pub state: String,
+    #[serde(default)]
     pub enemy: String,
     pub enemypct: i32,
 }
@@ -136,20 +288,311 @@ pub struct GroupEnemyInfo {
     pub here: i32,
 }
 
+/// An item from char.items.*: `attrib` holds flag letters such as `w` (worn)
+/// or `c` (container).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Item {
+    #[serde(deserialize_with = "deserialize_item_id")]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub attrib: String,
+}
+
+impl Item {
+    pub fn is_worn(&self) -> bool {
+        self.attrib.contains('w')
+    }
+}
+
+/// char.items.list: every item in one location ("inv", "room" or a container).
+#[derive(Debug, Deserialize)]
+pub struct ItemList {
+    pub location: String,
+    #[serde(default)]
+    pub items: Vec<Item>,
+}
+
+/// char.items.add / remove / update: one item changing in a location.
+#[derive(Debug, Deserialize)]
+pub struct ItemChange {
+    pub location: String,
+    pub item: Item,
+}
+
+/// A spell or skill effect from char.affects. `duration` is the time left in
+/// seconds; missing or negative means it lasts until removed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Affect {
+    pub name: String,
+    #[serde(default, alias = "time", alias = "remaining")]
+    pub duration: Option<i64>,
+}
+
+/// A skill from char.skills. `cooldown` is the time in seconds until it can
+/// be used again; `ready` is the server's own flag, when it sends one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Skill {
+    pub name: String,
+    #[serde(default, alias = "time", alias = "remaining")]
+    pub cooldown: Option<f64>,
+    #[serde(default, alias = "available")]
+    pub ready: Option<bool>,
+}
+
+/// Item ids arrive as either strings or numbers.
+fn deserialize_item_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        other => Err(serde::de::Error::custom(format!("invalid item id: {}", other))),
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // TelnetMessage: extended to handle GMCP messages.
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 #[derive(Debug, Clone)]
 pub enum TelnetMessage {
-    MUDOutput(Vec<Span<'static>>),
-    ChatMessage(Vec<Span<'static>>),
-    Disconnect,
+    MUDOutput(StyledLine),
+    // A comm.channel message; `message` still carries its inline $-color markers.
+    ChatMessage { channel: String, player: String, message: String },
+    Connected(String), // "host:port" of a newly established connection
+    ConnectFailed { addr: String, error: String }, // `connect` could not reach "host:port"
+    Disconnect(DisconnectReason),
     CharLogin(String),
     CharVitals(i32, i32, i32), // CharVitals carries (hp, mana, movement)
     CharMaxStats(i32, i32, i32),    // CharMaxStats carries (maxhp, maxmana, maxmove)
-    RoomInfo(String, String),
-    CharStatus(i32, i64, i32),
+    RoomInfo(RoomInfo),
+    CharStatus(CharStatus),
     GroupInfo(GroupInfo),
+    ServerEcho(bool), // true when the server echoes input itself (typically a password prompt)
+    LocalEcho(String), // A command sent on the user's behalf (timers etc.) to be echoed in the output
+    Info(String),      // An informational line from the client itself (scripts, status notices)
+    Bell,              // The server sent a BEL (0x07); it is removed from the text
+    ItemList { location: String, items: Vec<Item> }, // char.items.list replaces a location's contents
+    ItemAdd { location: String, item: Item },
+    ItemRemove { location: String, id: String },
+    ItemUpdate { location: String, item: Item },
+    Affects(Vec<Affect>), // char.affects: every active affect, replacing the previous list
+    Skills(Vec<Skill>),      // char.skills / char.skills.list: every skill, replacing the previous list
+    SkillUpdate(Vec<Skill>), // char.skills.update: only the skills that changed
+    Pong,                 // core.ping: the server answered our Core.Ping
+}
+
+/// Why a connection ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// `TelnetClient::disconnect` closed it.
+    Client,
+    /// The server closed the connection.
+    ServerClosed,
+    /// The connection timed out.
+    TimedOut,
+    /// Reading from the socket failed; the error text.
+    Error(String),
+}
+
+impl DisconnectReason {
+    fn from_io(e: &std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::TimedOut {
+            return DisconnectReason::TimedOut;
+        }
+        DisconnectReason::Error(io_error_text(e))
+    }
+
+    /// Whether reconnecting could help: everything except a close we asked for.
+    pub fn should_reconnect(&self) -> bool {
+        *self != DisconnectReason::Client
+    }
+}
+
+/// "Connection reset by peer (os error 104)" reads better without the code.
+fn io_error_text(e: &std::io::Error) -> String {
+    let text = e.to_string();
+    match text.rfind(" (os error ") {
+        Some(i) => text[..i].to_string(),
+        None => text,
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisconnectReason::Client => write!(f, "closed by client"),
+            DisconnectReason::ServerClosed => write!(f, "closed by server"),
+            DisconnectReason::TimedOut => write!(f, "connection timed out"),
+            DisconnectReason::Error(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Connection options.
+////////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientOptions {
+    /// Minimum delay between queued commands, in milliseconds. 0 disables the queue.
+    pub throttle_ms: u64,
+    /// Disable Nagle's algorithm so small command writes go out immediately.
+    pub nodelay: bool,
+    /// GMCP commands sent by `fetch_all` once connected, in order.
+    pub gmcp_requests: Vec<String>,
+    /// Reconnect automatically when the connection drops (used by the TUI).
+    pub reconnect: bool,
+    /// Seconds to wait before each reconnect attempt.
+    pub reconnect_delay_secs: u64,
+    /// Reconnect attempts before giving up; 0 keeps trying forever.
+    pub max_reconnect_attempts: u32,
+    /// Seconds between GMCP Core.Ping latency checks (used by the TUI); 0 disables them.
+    pub ping_interval_secs: u64,
+    /// Character set of the server's text: "utf-8", "latin-1" or "cp437".
+    pub encoding: Encoding,
+    /// Bytes read from the socket at a time, between 1 KiB and 1 MiB.
+    pub read_buffer_size: usize,
+    /// Log bytes/s and events/s at debug level this often (seconds); 0 disables it.
+    pub stats_interval_secs: u64,
+    /// Seconds to wait for the server to take up GMCP before saying that the
+    /// gauges will stay empty; 0 turns the notice off.
+    pub gmcp_timeout_secs: u64,
+    /// What ends each command sent to the server.
+    pub line_ending: LineEnding,
+}
+
+/// The end of a command line: "crlf" (`\r\n`, the telnet convention), "lf"
+/// (`\n`) or "cr" (`\r`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    CrLf,
+    Lf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf => "\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The bytes `send_command` writes for `cmd`: trimmed, then this ending.
+    ///
+    /// ```
+    /// use mud_tui::telnet_client::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::CrLf.terminate(" look "), b"look\r\n");
+    /// assert_eq!(LineEnding::Lf.terminate("look"), b"look\n");
+    /// assert_eq!(LineEnding::Cr.terminate("look"), b"look\r");
+    /// assert_eq!(LineEnding::default(), LineEnding::CrLf);
+    /// ```
+    pub fn terminate(self, cmd: &str) -> Vec<u8> {
+        format!("{}{}", cmd.trim(), self.as_str()).into_bytes()
+    }
+}
+
+/// Allowed range of `ClientOptions::read_buffer_size`.
+const READ_BUFFER_RANGE: std::ops::RangeInclusive<usize> = 1024..=1024 * 1024;
+
+impl ClientOptions {
+    /// `read_buffer_size` clamped to `READ_BUFFER_RANGE`, with a warning when
+    /// it had to be.
+    fn read_buffer_size(&self) -> usize {
+        let size = self.read_buffer_size.clamp(*READ_BUFFER_RANGE.start(), *READ_BUFFER_RANGE.end());
+        if size != self.read_buffer_size {
+            warn!("read_buffer_size {} is outside 1024..=1048576; using {}", self.read_buffer_size, size);
+        }
+        size
+    }
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            throttle_ms: 0,
+            nodelay: true,
+            gmcp_requests: [
+                "config compact",
+                "config prompt",
+                "config xterm yes",
+                "request char",
+                "request room",
+                "request area",
+                "request quest",
+                "request group",
+            ]
+            .map(String::from)
+            .to_vec(),
+            reconnect: false,
+            reconnect_delay_secs: 5,
+            max_reconnect_attempts: 5,
+            ping_interval_secs: 0,
+            encoding: Encoding::Utf8,
+            read_buffer_size: 8192,
+            stats_interval_secs: 0,
+            gmcp_timeout_secs: 5,
+            line_ending: LineEnding::CrLf,
+        }
+    }
+}
+
+/// Messages a broadcast subscriber may fall behind by before it starts missing them.
+pub const SUBSCRIBER_CAPACITY: usize = 256;
+
+/// Delivers each message to the main channel and to every broadcast subscriber.
+///
+/// The main channel stays bounded on purpose: when the receiver falls behind,
+/// the read loop waits instead of buffering without limit, and the server's
+/// own TCP flow control takes over. Nothing is dropped or reordered. A full
+/// channel is logged once per stall so a stuck receiver can be diagnosed; a
+/// closed one means the receiver is gone, and the read loop stops.
+#[derive(Clone)]
+struct MessageSink {
+    primary: Sender<TelnetMessage>,
+    subscribers: broadcast::Sender<TelnetMessage>,
+    /// Set while a send is waiting on a full main channel.
+    stalled: Arc<AtomicBool>,
+}
+
+impl MessageSink {
+    async fn send(&self, msg: TelnetMessage) -> Result<(), SendError<TelnetMessage>> {
+        if self.subscribers.receiver_count() > 0 {
+            // Only fails when nobody is subscribed.
+            let _ = self.subscribers.send(msg.clone());
+        }
+        let msg = match self.primary.try_send(msg) {
+            Ok(()) => {
+                if self.stalled.swap(false, Ordering::Relaxed) {
+                    info!("Message channel drained; receiver caught up");
+                }
+                return Ok(());
+            }
+            Err(TrySendError::Full(msg)) => {
+                if !self.stalled.swap(true, Ordering::Relaxed) {
+                    warn!(
+                        "Message channel full ({} messages); waiting for the receiver to catch up",
+                        self.primary.max_capacity()
+                    );
+                }
+                msg
+            }
+            Err(TrySendError::Closed(msg)) => {
+                warn!("Message channel closed; message dropped");
+                return Err(SendError(msg));
+            }
+        };
+        self.primary.send(msg).await
+    }
+
+    /// Whether the receiving end of the main channel has been dropped.
+    fn is_closed(&self) -> bool {
+        self.primary.is_closed()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -159,50 +602,158 @@ pub enum TelnetMessage {
 pub struct TelnetClient {
     parser: Arc<Mutex<Parser>>,
     write_half: Arc<Mutex<Option<OwnedWriteHalf>>>,
-    sender: Sender<TelnetMessage>,
+    sender: MessageSink,
+    options: ClientOptions,
+    // Outbound commands waiting for the throttle task to release them.
+    queue: Arc<Mutex<VecDeque<String>>>,
+    queue_notify: Arc<Notify>,
+    queue_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    // The read loop of the current connection and the token that stops it.
+    read_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    shutdown: Arc<Mutex<CancellationToken>>,
+    // When a command was last written (or the connection opened), for idle tracking.
+    last_command: Arc<std::sync::Mutex<Instant>>,
+    naws: Arc<Naws>,
 }
 
 impl TelnetClient {
     pub fn new(sender: Sender<TelnetMessage>) -> Self {
+        Self::with_options(sender, ClientOptions::default())
+    }
+
+    pub fn with_options(sender: Sender<TelnetMessage>, options: ClientOptions) -> Self {
         Self {
-            parser: Arc::new(Mutex::new(Parser::new())),
+            parser: Arc::new(Mutex::new(Parser::with_support(telnet_support()))),
             write_half: Arc::new(Mutex::new(None)),
-            sender,
+            sender: MessageSink {
+                primary: sender,
+                subscribers: broadcast::channel(SUBSCRIBER_CAPACITY).0,
+                stalled: Arc::new(AtomicBool::new(false)),
+            },
+            options,
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            queue_notify: Arc::new(Notify::new()),
+            queue_task: Arc::new(Mutex::new(None)),
+            read_task: Arc::new(Mutex::new(None)),
+            shutdown: Arc::new(Mutex::new(CancellationToken::new())),
+            last_command: Arc::new(std::sync::Mutex::new(Instant::now())),
+            naws: Arc::new(Naws::default()),
         }
     }
 
+    /// Returns a receiver that gets a copy of every message from now on, in
+    /// addition to the channel the client was created with (for a logger,
+    /// scripts, ...). Any number of subscribers may exist.
+    ///
+    /// The main channel applies backpressure: the client waits while it is
+    /// full. Subscribers do not: one that falls more than
+    /// `SUBSCRIBER_CAPACITY` messages behind loses the oldest ones and its
+    /// next `recv` returns `RecvError::Lagged` with the number missed.
+    pub fn subscribe(&self) -> broadcast::Receiver<TelnetMessage> {
+        self.sender.subscribers.subscribe()
+    }
+
     /// Connect to the server and start the read loop.
     /// The gmcp_store is passed in so that incoming GMCP data can be saved.
+    /// A server that cannot be reached, or fails the first writes, is also
+    /// reported as `ConnectFailed`.
     pub async fn connect(&self, host: &str, port: &str, gmcp_store: Arc<Mutex<GMCPStore>>) -> Result<(), String> {
         let addr_str = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr_str)
-            .await
-            .map_err(|e| format!("Connection failed: {}", e))?;
+        let stream = match TcpStream::connect(&addr_str).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let error = io_error_text(&e);
+                let _ = self.sender.send(TelnetMessage::ConnectFailed { addr: addr_str, error: error.clone() }).await;
+                return Err(format!("Connection failed: {}", error));
+            }
+        };
+        self.start(addr_str, stream, gmcp_store).await
+    }
+
+    /// Sets up a freshly opened connection: negotiation, the first GMCP
+    /// requests and the read loop. If that fails the socket is shut down and
+    /// the failure reported as `ConnectFailed`, like an unreachable server.
+    async fn start(&self, addr_str: String, stream: TcpStream, gmcp_store: Arc<Mutex<GMCPStore>>) -> Result<(), String> {
         info!("Connected to {}", addr_str);
+        self.mark_activity();
+        // Negotiation state belongs to the previous connection.
+        *self.parser.lock().await = Parser::with_support(telnet_support());
+
+        match stream.set_nodelay(self.options.nodelay) {
+            Ok(()) => info!("TCP_NODELAY set to {}", self.options.nodelay),
+            Err(e) => error!("Failed to set TCP_NODELAY: {}", e),
+        }
 
         let (read_half, write_half) = stream.into_split();
         {
             let mut w = self.write_half.lock().await;
             *w = Some(write_half);
         }
-        // Send GMCP negotiation (IAC WILL TELOPT_GMCP)
-        self.enable_gmcp().await?;
-
-        // Send additional GMCP requests.
-        self.fetch_all().await?;
+        // Send GMCP negotiation (IAC WILL TELOPT_GMCP), then the additional GMCP requests.
+        let greeting = async {
+            self.enable_gmcp().await?;
+            self.fetch_all().await
+        };
+        if let Err(error) = greeting.await {
+            if let Some(mut write_half) = self.write_half.lock().await.take() {
+                let _ = write_half.shutdown().await;
+            }
+            let _ = self.sender.send(TelnetMessage::ConnectFailed { addr: addr_str, error: error.clone() }).await;
+            return Err(format!("Connection failed: {}", error));
+        }
 
         let parser_clone = Arc::clone(&self.parser);
         let tx_clone = self.sender.clone();
         let write_half_clone = Arc::clone(&self.write_half);
         let gmcp_store_clone = gmcp_store.clone();
+        self.naws.set_enabled(false);
+        let ctx = ReadContext {
+            naws: self.naws.clone(),
+            encoding: self.options.encoding,
+            buffer_size: self.options.read_buffer_size(),
+            stats_interval: Duration::from_secs(self.options.stats_interval_secs),
+            gmcp_timeout: Duration::from_secs(self.options.gmcp_timeout_secs),
+        };
+        let cancel = CancellationToken::new();
+        *self.shutdown.lock().await = cancel.clone();
 
-        tokio::spawn(async move {
-            run_read_loop(read_half, parser_clone, write_half_clone, tx_clone, gmcp_store_clone).await;
+        let handle = tokio::spawn(async move {
+            run_read_loop(read_half, parser_clone, write_half_clone, tx_clone, gmcp_store_clone, ctx, cancel).await;
         });
+        *self.read_task.lock().await = Some(handle);
+        let _ = self.sender.send(TelnetMessage::Connected(addr_str)).await;
+
+        if self.options.throttle_ms > 0 {
+            let interval = Duration::from_millis(self.options.throttle_ms);
+            let client = self.clone();
+            let handle = tokio::spawn(run_command_queue(client, interval));
+            if let Some(old) = self.queue_task.lock().await.replace(handle) {
+                old.abort();
+            }
+        }
 
         Ok(())
     }
 
+    /// Closes the connection: stops the read loop (which shuts the socket down),
+    /// stops the throttle task and discards any queued commands.
+    pub async fn disconnect(&self) {
+        self.shutdown.lock().await.cancel();
+        if let Some(task) = self.read_task.lock().await.take() {
+            let _ = task.await;
+        }
+        if let Some(task) = self.queue_task.lock().await.take() {
+            task.abort();
+            let _ = task.await;
+        }
+        self.queue.lock().await.clear();
+    }
+
+    /// Shows an informational line in the UI.
+    pub async fn info(&self, text: impl Into<String>) {
+        let _ = self.sender.send(TelnetMessage::Info(text.into())).await;
+    }
+
     /// Sends IAC WILL TELOPT_GMCP to enable GMCP.
     pub async fn enable_gmcp(&self) -> Result<(), String> {
         let gmcp_enable = [IAC, WILL, TELOPT_GMCP];
@@ -232,23 +783,87 @@ impl TelnetClient {
         }
     }
 
-    /// Sends several GMCP commands to fetch server data.
+    /// Sends the configured GMCP commands (`gmcp_requests`) to fetch server
+    /// data. Blank entries are skipped.
     pub async fn fetch_all(&self) -> Result<(), String> {
-        self.send_gmcp_subneg("config compact").await?;
-        self.send_gmcp_subneg("config prompt").await?;
-        self.send_gmcp_subneg("config xterm yes").await?;
-        self.send_gmcp_subneg("request char").await?;
-        self.send_gmcp_subneg("request room").await?;
-        self.send_gmcp_subneg("request area").await?;
-        self.send_gmcp_subneg("request quest").await?;
-        self.send_gmcp_subneg("request group").await?;
+        for request in &self.options.gmcp_requests {
+            let request = request.trim();
+            if request.is_empty() {
+                warn!("Skipping empty GMCP request in connection.gmcp_requests");
+                continue;
+            }
+            self.send_gmcp_subneg(request).await?;
+            info!("Sent GMCP request: {}", request);
+        }
         Ok(())
     }
 
     /// Sends a normal text command to the server.
+    /// When throttling is enabled the command is queued and released by the throttle task.
     pub async fn send_command(&self, cmd: &str) -> Result<(), String> {
-        let cmd = format!("{}\r\n", cmd.trim());
-        // debug("send_command(): sending {:?}", cmd.escape_default());
+        if self.options.throttle_ms == 0 {
+            return self.write_command(cmd).await;
+        }
+        self.queue.lock().await.push_back(cmd.to_string());
+        self.queue_notify.notify_one();
+        Ok(())
+    }
+
+    /// Sends a command and reports it back to the UI as a `LocalEcho` so it shows in the output.
+    pub async fn send_command_echoed(&self, cmd: &str) -> Result<(), String> {
+        self.send_command(cmd).await?;
+        let _ = self.sender.send(TelnetMessage::LocalEcho(cmd.to_string())).await;
+        Ok(())
+    }
+
+    /// Sends a command immediately, bypassing the throttle queue.
+    /// Used for commands the user typed by hand so they never wait behind automation.
+    pub async fn send_command_now(&self, cmd: &str) -> Result<(), String> {
+        self.write_command(cmd).await
+    }
+
+    /// Sends `text` exactly as given, without a line ending and bypassing the
+    /// throttle queue, for MUD prompts that read single keystrokes.
+    pub async fn send_raw(&self, text: &str) -> Result<(), String> {
+        self.write_bytes(text.as_bytes()).await
+    }
+
+    /// Sets the window size (columns, rows) reported to the server. It is sent
+    /// now if the server asked for NAWS, otherwise once it does.
+    pub async fn set_window_size(&self, width: u16, height: u16) -> Result<(), String> {
+        if let Ok(mut size) = self.naws.size.lock() {
+            *size = Some((width, height));
+        }
+        if !self.naws.enabled() {
+            return Ok(());
+        }
+        let Some(packet) = self.naws.packet() else { return Ok(()) };
+        let mut w = self.write_half.lock().await;
+        match w.as_mut() {
+            Some(write_half) => write_half.write_all(&packet).await.map_err(|e| format!("Failed to send NAWS: {}", e)),
+            None => Err("No write half available".into()),
+        }
+    }
+
+    /// How long since the last command was sent to the server (or the
+    /// connection opened). MUDs that drop idle players go by this.
+    pub fn idle_for(&self) -> Duration {
+        self.last_command.lock().map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    fn mark_activity(&self) {
+        if let Ok(mut last) = self.last_command.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    /// Writes a command line to the socket, ended with `line_ending`.
+    async fn write_command(&self, cmd: &str) -> Result<(), String> {
+        self.write_bytes(&self.options.line_ending.terminate(cmd)).await
+    }
+
+    async fn write_bytes(&self, bytes: &[u8]) -> Result<(), String> {
+        // debug("send_command(): sending {:?}", bytes.escape_ascii());
         let mut w = self.write_half.lock().await;
         let some_wh = match w.as_mut() {
             Some(wh) => wh,
@@ -257,10 +872,11 @@ impl TelnetClient {
                 return Err("Not connected".to_string());
             }
         };
-        let result = timeout(Duration::from_secs(5), some_wh.write_all(cmd.as_bytes())).await;
+        let result = timeout(Duration::from_secs(5), some_wh.write_all(bytes)).await;
         match result {
             Ok(Ok(())) => {
-                // debug("send_command(): success writing {} bytes", cmd.len());
+                // debug("send_command(): success writing {} bytes", bytes.len());
+                self.mark_activity();
                 Ok(())
             }
             Ok(Err(e)) => {
@@ -275,22 +891,137 @@ impl TelnetClient {
     }
 }
 
+/// Drains the outbound command queue, releasing at most one command per `interval`.
+async fn run_command_queue(client: TelnetClient, interval: Duration) {
+    loop {
+        let next = client.queue.lock().await.pop_front();
+        match next {
+            Some(cmd) => {
+                if let Err(e) = client.write_command(&cmd).await {
+                    error!("Failed to send queued command: {}", e);
+                }
+                tokio::time::sleep(interval).await;
+            }
+            None => client.queue_notify.notified().await,
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Read loop and GMCP handling.
 ////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Per-connection settings and state the read loop passes on to `handle_event`.
+struct ReadContext {
+    naws: Arc<Naws>,
+    encoding: Encoding,
+    buffer_size: usize,
+    /// Zero when throughput stats are off.
+    stats_interval: Duration,
+    /// Zero when the missing-GMCP notice is off.
+    gmcp_timeout: Duration,
+}
+
+/// How far the server has answered our IAC WILL GMCP on this connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GmcpState {
+    /// Neither an answer nor any GMCP data yet.
+    Pending,
+    /// IAC DO GMCP or a GMCP subnegotiation arrived.
+    Enabled,
+    /// IAC DONT GMCP, or nothing within `gmcp_timeout`; the user was told.
+    Unavailable,
+}
+
+/// What `raw` says about GMCP: `Enabled` for IAC DO GMCP or GMCP data,
+/// `Unavailable` for IAC DONT GMCP, `None` if it says nothing. The parser
+/// does not pass these on because GMCP is negotiated by hand.
+fn gmcp_negotiation(raw: &[u8]) -> Option<GmcpState> {
+    raw.windows(3).rev().find_map(|w| match w {
+        [IAC, DO | SB, TELOPT_GMCP] => Some(GmcpState::Enabled),
+        [IAC, DONT, TELOPT_GMCP] => Some(GmcpState::Unavailable),
+        _ => None,
+    })
+}
+
+/// Shown when the server never takes up GMCP.
+const NO_GMCP: &str = "Server did not enable GMCP; gauges unavailable";
+
+/// Traffic counted since the last throughput log line.
+struct ReadStats {
+    since: Instant,
+    bytes: usize,
+    events: usize,
+}
+
+impl ReadStats {
+    fn new() -> Self {
+        Self { since: Instant::now(), bytes: 0, events: 0 }
+    }
+
+    /// Logs and resets the counters once `interval` has passed, if debug
+    /// logging is on.
+    fn record(&mut self, bytes: usize, events: usize, interval: Duration) {
+        if interval.is_zero() || !log_enabled!(Level::Debug) {
+            return;
+        }
+        self.bytes += bytes;
+        self.events += events;
+        let elapsed = self.since.elapsed();
+        if elapsed >= interval {
+            let secs = elapsed.as_secs_f64();
+            debug!(
+                "Read throughput: {:.0} bytes/s, {:.1} events/s over {:.0}s",
+                self.bytes as f64 / secs,
+                self.events as f64 / secs,
+                secs
+            );
+            *self = Self::new();
+        }
+    }
+}
+
 async fn run_read_loop(
     mut r: OwnedReadHalf,
     parser_arc: Arc<Mutex<Parser>>,
     write_half_arc: Arc<Mutex<Option<OwnedWriteHalf>>>,
-    tx: Sender<TelnetMessage>,
+    tx: MessageSink,
     gmcp_store: Arc<Mutex<GMCPStore>>,
+    ctx: ReadContext,
+    cancel: CancellationToken,
 ) {
-    let mut buf = [0u8; 8192];
+    let mut buf = vec![0u8; ctx.buffer_size];
+    let mut stats = ReadStats::new();
+    // MSDP variables live only as long as the connection.
+    let mut msdp_store = MsdpStore::default();
+    // Text is decoded per read; a character split between reads waits here.
+    let mut carry = Utf8Carry::default();
+    let mut gmcp = GmcpState::Pending;
+    let gmcp_deadline = Instant::now() + ctx.gmcp_timeout;
     loop {
-        match r.read(&mut buf).await {
+        let read = tokio::select! {
+            read = r.read(&mut buf) => read,
+            _ = tokio::time::sleep_until(gmcp_deadline), if gmcp == GmcpState::Pending && !ctx.gmcp_timeout.is_zero() => {
+                gmcp = GmcpState::Unavailable;
+                info!("No GMCP answer within {:?}", ctx.gmcp_timeout);
+                // MSDP drives the gauges just as well.
+                if msdp_store.is_empty() {
+                    let _ = tx.send(TelnetMessage::Info(NO_GMCP.to_string())).await;
+                }
+                continue;
+            }
+            _ = cancel.cancelled() => {
+                if let Some(mut w) = write_half_arc.lock().await.take() {
+                    let _ = w.shutdown().await;
+                }
+                info!("Connection closed by client");
+                let _ = tx.send(TelnetMessage::Disconnect(DisconnectReason::Client)).await;
+                break;
+            }
+        };
+        match read {
             Ok(0) => {
-                // debug("Server closed connection");
-                let _ = tx.send(TelnetMessage::Disconnect).await;
+                info!("Connection closed by server");
+                let _ = tx.send(TelnetMessage::Disconnect(DisconnectReason::ServerClosed)).await;
                 break;
             }
             Ok(n) => {
@@ -304,33 +1035,59 @@ async fn run_read_loop(
                 };
                 // debug("Parsed events from parser: {:?}", events);
 
-                let fallback_events = extract_gmcp_subnegotiations(&raw_bytes);
-                if !fallback_events.is_empty() {
-                    // debug("Fallback extracted {} GMCP subnegotiation event(s)", fallback_events.len());
-                    events.extend(fallback_events);
+                match gmcp_negotiation(&raw_bytes) {
+                    Some(GmcpState::Unavailable) if gmcp == GmcpState::Pending => {
+                        gmcp = GmcpState::Unavailable;
+                        info!("Server refused GMCP");
+                        if !ctx.gmcp_timeout.is_zero() {
+                            let _ = tx.send(TelnetMessage::Info(NO_GMCP.to_string())).await;
+                        }
+                    }
+                    Some(GmcpState::Enabled) if gmcp != GmcpState::Enabled => {
+                        if gmcp == GmcpState::Unavailable {
+                            info!("GMCP enabled after all");
+                        }
+                        gmcp = GmcpState::Enabled;
+                    }
+                    _ => {}
+                }
+
+                // The parser only passes on subnegotiations for options it has
+                // enabled locally, so GMCP and MSDP are pulled out by hand.
+                for option in [TELOPT_GMCP, TELOPT_MSDP] {
+                    events.extend(extract_subnegotiations(&raw_bytes, option));
                 }
 
+                stats.record(n, events.len(), ctx.stats_interval);
                 for ev in events {
-                    handle_event(ev, &tx, &write_half_arc, gmcp_store.clone()).await;
+                    handle_event(ev, &tx, &write_half_arc, gmcp_store.clone(), &mut msdp_store, &mut carry, &ctx).await;
+                }
+                // Nobody is left to read what the server sends.
+                if tx.is_closed() {
+                    info!("Message channel closed; stopping the read loop");
+                    if let Some(mut w) = write_half_arc.lock().await.take() {
+                        let _ = w.shutdown().await;
+                    }
+                    break;
                 }
             }
             Err(e) => {
                 error!("Telnet read error: {}", e);
-                let _ = tx.send(TelnetMessage::Disconnect).await;
+                let _ = tx.send(TelnetMessage::Disconnect(DisconnectReason::from_io(&e))).await;
                 break;
             }
         }
     }
 }
 
-/// Manually extracts GMCP subnegotiation sequences.
-fn extract_gmcp_subnegotiations(raw: &[u8]) -> Vec<TelnetEvents> {
+/// Manually extracts subnegotiation sequences for `option`.
+fn extract_subnegotiations(raw: &[u8], option: u8) -> Vec<TelnetEvents> {
     let mut events = Vec::new();
     let mut i = 0;
     while i < raw.len() {
         if raw[i] == IAC {
             if i + 1 < raw.len() && raw[i + 1] == SB {
-                if i + 2 < raw.len() && raw[i + 2] == TELOPT_GMCP {
+                if i + 2 < raw.len() && raw[i + 2] == option {
                     let start = i + 3;
                     let mut end = start;
                     while end + 1 < raw.len() {
@@ -341,9 +1098,9 @@ fn extract_gmcp_subnegotiations(raw: &[u8]) -> Vec<TelnetEvents> {
                     }
                     if end + 1 < raw.len() {
                         let buffer = raw[start..end].to_vec();
-                        // debug("Manually extracted GMCP subnegotiation buffer: {:?}", buffer);
+                        // debug("Manually extracted subnegotiation buffer: {:?}", buffer);
                         events.push(TelnetEvents::Subnegotiation(TelnetSubnegotiation {
-                            option: TELOPT_GMCP,
+                            option,
                             buffer: buffer.into(),
                         }));
                         i = end + 2;
@@ -357,31 +1114,99 @@ fn extract_gmcp_subnegotiations(raw: &[u8]) -> Vec<TelnetEvents> {
     events
 }
 
-/// Parses a GMCP message into a package and JSON value.
-fn parse_gmcp(data: &str) -> Option<(String, Value)> {
+/// A GMCP message split into its package name and JSON payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GmcpMessage {
+    pub package: String,
+    pub data: Value,
+}
+
+/// Parses a GMCP message into a package and JSON payload.
+///
+/// Three forms are accepted:
+/// - the standard `Package.Name <json>`, e.g. `char.vitals {"hp": 10}`;
+/// - a bare package name with no payload, e.g. `Char.Items.List`, returned
+///   with a `Value::Null` payload;
+/// - a JSON object with a single key naming the package, e.g.
+///   `{"char.vitals": {"hp": 10}}`, which some servers send.
+///
+/// The object form is only recognized when the key looks like a package name
+/// (dotted, no spaces), so a genuine one-field object such as `{"hp": 10}` is
+/// not mistaken for a package and is rejected. Returns `None` for empty or
+/// malformed input.
+///
+/// ```
+/// use mud_tui::telnet_client::parse_gmcp;
+/// use serde_json::json;
+///
+/// let msg = parse_gmcp(r#"char.vitals {"hp": 10}"#).unwrap();
+/// assert_eq!((msg.package.as_str(), msg.data), ("char.vitals", json!({"hp": 10})));
+///
+/// let msg = parse_gmcp(r#"{"room.info": {"num": 1}}"#).unwrap();
+/// assert_eq!((msg.package.as_str(), msg.data), ("room.info", json!({"num": 1})));
+///
+/// assert_eq!(parse_gmcp("Foo.Bar").unwrap().data, json!(null));
+/// let msg = parse_gmcp("External.Discord.Status ").unwrap();
+/// assert_eq!((msg.package.as_str(), msg.data), ("External.Discord.Status", json!(null)));
+///
+/// assert_eq!(parse_gmcp(""), None);
+/// assert_eq!(parse_gmcp(r#"{"hp": 10}"#), None);
+/// assert_eq!(parse_gmcp(r#"char.vitals {"hp": "#), None);
+/// ```
+pub fn parse_gmcp(data: &str) -> Option<GmcpMessage> {
     let trimmed = data.trim();
-    if let Ok(val) = serde_json::from_str::<Value>(trimmed) {
-        if let Value::Object(map) = &val {
-            if map.len() == 1 {
-                let (package, value) = map.iter().next().unwrap();
-                return Some((package.clone(), value.clone()));
+    if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(trimmed) {
+        if map.len() == 1 {
+            let (package, value) = map.into_iter().next()?;
+            if is_package_name(&package) {
+                return Some(GmcpMessage { package, data: value });
             }
         }
+        return None;
     }
-    let mut parts = trimmed.splitn(2, char::is_whitespace);
-    if let Some(package) = parts.next() {
-        if let Some(json_part) = parts.next() {
-            if let Ok(value) = serde_json::from_str::<Value>(json_part.trim()) {
-                return Some((package.to_string(), value));
-            }
-        }
-    }
-    None
+    let Some((package, json_part)) = trimmed.split_once(char::is_whitespace) else {
+        return is_package_name(trimmed).then(|| GmcpMessage {
+            package: trimmed.to_string(),
+            data: Value::Null,
+        });
+    };
+    let data = serde_json::from_str::<Value>(json_part.trim()).ok()?;
+    Some(GmcpMessage { package: package.to_string(), data })
+}
+
+/// A GMCP package name: dot-separated words such as `Char.Vitals`.
+fn is_package_name(name: &str) -> bool {
+    name.contains('.')
+        && name.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
 }
 
 /// Tries to parse known GMCP modules and returns a corresponding TelnetMessage.
-fn parse_known_gmcp_modules(gmcp_str: &str) -> Option<TelnetMessage> {
-    if let Some((package, value)) = parse_gmcp(gmcp_str) {
+/// Package names are case-insensitive, as GMCP specifies.
+///
+/// ```
+/// use mud_tui::telnet_client::parse_known_gmcp_modules;
+/// use mud_tui::TelnetMessage;
+///
+/// for gmcp in [r#"Char.Vitals {"hp": 90, "mana": 40, "moves": 70}"#, r#"char.vitals {"hp": 90, "mana": 40, "moves": 70}"#] {
+///     assert!(matches!(parse_known_gmcp_modules(gmcp), Some(TelnetMessage::CharVitals(90, 40, 70))));
+/// }
+/// assert!(matches!(
+///     parse_known_gmcp_modules(r#"CHAR.MAXSTATS {"maxhp": 100, "maxmana": 50, "maxmoves": 80}"#),
+///     Some(TelnetMessage::CharMaxStats(100, 50, 80))
+/// ));
+/// match parse_known_gmcp_modules(r#"Char.Skills.Update {"bash": 4.5, "kick": true}"#) {
+///     Some(TelnetMessage::SkillUpdate(skills)) => {
+///         assert_eq!(skills[0].cooldown, Some(4.5));
+///         assert_eq!(skills[1].ready, Some(true));
+///     }
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub fn parse_known_gmcp_modules(gmcp_str: &str) -> Option<TelnetMessage> {
+    if let Some(GmcpMessage { package, data: value }) = parse_gmcp(gmcp_str) {
+        let package = package.to_lowercase();
         match package.as_str() {
             "char.login" => {
                 if let Ok(obj) = serde_json::from_value::<CharLogin>(value) {
@@ -400,18 +1225,13 @@ fn parse_known_gmcp_modules(gmcp_str: &str) -> Option<TelnetMessage> {
             }
             "room.info" => {
                 if let Ok(obj) = serde_json::from_value::<RoomInfo>(value) {
-                    return Some(TelnetMessage::RoomInfo(obj.name, obj.zone));
-                }
-            }
-            "comm.channel" => {
-                if let Ok(cc) = serde_json::from_value::<CommChannel>(value) {
-                    let parsed_msg = parse_gmcp_message(&cc.msg);
-                    return Some(TelnetMessage::ChatMessage(parsed_msg));
+                    return Some(TelnetMessage::RoomInfo(obj));
                 }
             }
+            "comm.channel" => return chat_message(value),
             "char.status" => {
                 if let Ok(obj) = serde_json::from_value::<CharStatus>(value) {
-                    return Some(TelnetMessage::CharStatus(obj.level, obj.tnl, obj.enemypct));
+                    return Some(TelnetMessage::CharStatus(obj));
                 }
             }
             "group" => {
@@ -419,118 +1239,269 @@ fn parse_known_gmcp_modules(gmcp_str: &str) -> Option<TelnetMessage> {
                     return Some(TelnetMessage::GroupInfo(obj));
                 }
             }
+            "char.affects" => return affects_message(value),
+            "char.skills" | "char.skills.list" => return skills(value).map(TelnetMessage::Skills),
+            "char.skills.update" => return skills(value).map(TelnetMessage::SkillUpdate),
+            "core.ping" => return Some(TelnetMessage::Pong),
+            _ if package.starts_with("char.items.") => return item_message(&package, value),
             _ => {}
         }
     }
     None
 }
 
-/// Converts a GMCP inline marker (like "$x196" or "$G") into a Color using the ANSI color map.
-fn convert_color_marker(marker: &str) -> Color {
-    if marker.starts_with("$x") {
-        let num_str = &marker[2..];
-        if let Ok(num) = num_str.parse::<u8>() {
-            let key = format!("38;5;{}", num);
-            if let Some(&color) = COLOR_MAP.get(key.as_str()) {
-                return color;
-            }
-        }
-        Color::White
-    } else if marker == "$G" {
-        Color::Rgb(0, 255, 0)
-    } else if marker == "$M" {
-        Color::Rgb(255, 0, 255)
-    } else if marker == "$R" {
-        Color::Rgb(255, 0, 0)
-    } else if marker == "$Y" {
-        Color::Rgb(255, 255, 0)
-    } else if marker == "$B" {
-        Color::Rgb(0, 0, 255)
-    } else if marker == "$C" {
-        Color::Rgb(0, 255, 255)
-    } else if marker == "$w" || marker == "$W" {
-        Color::Rgb(255, 255, 255)
-    } else {
-        Color::White
+/// Stores the variables from an MSDP subnegotiation and translates the ones the
+/// UI understands into the same messages GMCP produces.
+fn apply_msdp(store: &mut MsdpStore, buffer: &[u8]) -> Vec<TelnetMessage> {
+    let names: Vec<String> = parse_msdp(buffer)
+        .into_iter()
+        .map(|(name, value)| {
+            store.update(&name, value);
+            name
+        })
+        .collect();
+    let changed = |vars: &[&str]| names.iter().any(|n| vars.contains(&n.as_str()));
+    let num = |name: &str| store.number(name).unwrap_or(0);
+
+    let mut messages = Vec::new();
+    if changed(&["HEALTH", "MANA", "MOVEMENT"]) && store.number("HEALTH").is_some() {
+        messages.push(TelnetMessage::CharVitals(num("HEALTH"), num("MANA"), num("MOVEMENT")));
+    }
+    if changed(&["HEALTH_MAX", "MANA_MAX", "MOVEMENT_MAX"]) && store.number("HEALTH_MAX").is_some() {
+        messages.push(TelnetMessage::CharMaxStats(num("HEALTH_MAX"), num("MANA_MAX"), num("MOVEMENT_MAX")));
+    }
+    if changed(&["ROOM_VNUM", "ROOM_NAME", "AREA_NAME", "ROOM_EXITS"]) {
+        if let Some(name) = store.text("ROOM_NAME") {
+            let exits = match store.get("ROOM_EXITS") {
+                Some(MsdpValue::Table(table)) => table
+                    .iter()
+                    .map(|(dir, dest)| {
+                        let id = match dest {
+                            MsdpValue::Text(id) => id.trim().parse().ok(),
+                            _ => None,
+                        };
+                        (dir.clone(), id.unwrap_or(UNKNOWN_ROOM))
+                    })
+                    .collect(),
+                _ => HashMap::new(),
+            };
+            messages.push(TelnetMessage::RoomInfo(RoomInfo {
+                num: store.number("ROOM_VNUM").unwrap_or(UNKNOWN_ROOM),
+                name: name.to_string(),
+                zone: store.text("AREA_NAME").unwrap_or_default().to_string(),
+                exits,
+            }));
+        }
     }
+    messages
+}
+
+/// Builds a chat message from a comm.channel payload.
+/// This is the only place chat is decoded; both GMCP paths go through it.
+fn chat_message(value: Value) -> Option<TelnetMessage> {
+    let cc = serde_json::from_value::<CommChannel>(value).ok()?;
+    Some(TelnetMessage::ChatMessage {
+        channel: cc.chan,
+        player: cc.player,
+        message: cc.msg,
+    })
+}
+
+/// Converts char.items.list/add/remove/update (any letter case) into a message.
+fn item_message(package: &str, value: Value) -> Option<TelnetMessage> {
+    match package.strip_prefix("char.items.")? {
+        "list" => {
+            let list = serde_json::from_value::<ItemList>(value).ok()?;
+            Some(TelnetMessage::ItemList { location: list.location, items: list.items })
+        }
+        "add" => {
+            let change = serde_json::from_value::<ItemChange>(value).ok()?;
+            Some(TelnetMessage::ItemAdd { location: change.location, item: change.item })
+        }
+        "remove" => {
+            let change = serde_json::from_value::<ItemChange>(value).ok()?;
+            Some(TelnetMessage::ItemRemove { location: change.location, id: change.item.id })
+        }
+        "update" => {
+            let change = serde_json::from_value::<ItemChange>(value).ok()?;
+            Some(TelnetMessage::ItemUpdate { location: change.location, item: change.item })
+        }
+        _ => None,
+    }
+}
+
+/// char.affects arrives either as a list of `{name, duration}` objects or as an
+/// object mapping each affect name to its duration.
+fn affects_message(value: Value) -> Option<TelnetMessage> {
+    let affects = match value {
+        Value::Array(_) => serde_json::from_value::<Vec<Affect>>(value).ok()?,
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(name, duration)| Affect { name, duration: duration.as_i64() })
+            .collect(),
+        _ => return None,
+    };
+    Some(TelnetMessage::Affects(affects))
+}
+
+/// char.skills payloads: a list of skill objects, a single skill object, or an
+/// object mapping each skill name to its cooldown (a number) or readiness (a
+/// boolean).
+fn skills(value: Value) -> Option<Vec<Skill>> {
+    match value {
+        Value::Array(_) => serde_json::from_value::<Vec<Skill>>(value).ok(),
+        Value::Object(ref map) if map.contains_key("name") => Some(vec![serde_json::from_value::<Skill>(value).ok()?]),
+        Value::Object(map) => Some(
+            map.into_iter()
+                .map(|(name, state)| match state {
+                    Value::Bool(ready) => Skill { name, cooldown: None, ready: Some(ready) },
+                    state => Skill { name, cooldown: state.as_f64(), ready: None },
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Recognizes a comm.channel GMCP message that reached us as plain text (its
+/// IAC SB framing lost). Only a line that *is* such a message counts: it must
+/// start with the package name and carry a valid payload, so ordinary text that
+/// merely mentions "comm.channel" is left alone.
+fn leaked_chat_message(line: &str) -> Option<TelnetMessage> {
+    let GmcpMessage { package, data: value } = parse_gmcp(line)?;
+    if !package.eq_ignore_ascii_case("comm.channel") {
+        return None;
+    }
+    chat_message(value)
 }
 
 /// Parses inline color markers inside a GMCP message.  
 /// This version supports both "$xNNN" markers (using xterm 256‑color lookup)  
-/// as well as single‑character markers like "$G" for green.
-pub fn parse_gmcp_message(msg: &str) -> Vec<Span<'static>> {
-    let mut spans = Vec::new();
+/// as well as single‑character markers like "$G" for green (see
+/// `ansi_color::MARKER_COLORS`; `$n` resets to the default). ANSI color
+/// sequences (`ESC[31m`) are honored too, and either kind overrides the other.
+/// Text before the first marker has no color of its own, so the front-end
+/// draws it in whatever base color it chooses. The result is always a single
+/// line: embedded line breaks become spaces (see `parse_gmcp_lines` to keep
+/// them).
+///
+/// ```
+/// use mud_tui::telnet_client::parse_gmcp_message;
+/// use mud_tui::Rgb;
+///
+/// let line = parse_gmcp_message("plain $Rred \x1b[32mgreen\x1b[0m plain");
+/// let parts: Vec<_> = line.segments.iter().map(|s| (s.text.as_str(), s.style.fg)).collect();
+/// assert_eq!(parts, [
+///     ("plain ", None),
+///     ("red ", Some(Rgb(255, 0, 0))),
+///     ("green", Some(Rgb(0, 128, 0))),
+///     (" plain", None),
+/// ]);
+///
+/// let line = parse_gmcp_message("$gdim$Ddark$nplain$qliteral");
+/// let parts: Vec<_> = line.segments.iter().map(|s| (s.text.as_str(), s.style.fg)).collect();
+/// assert_eq!(parts, [
+///     ("dim", Some(Rgb(0, 128, 0))),
+///     ("dark", Some(Rgb(128, 128, 128))),
+///     ("plain$qliteral", None),
+/// ]);
+/// ```
+pub fn parse_gmcp_message(msg: &str) -> StyledLine {
+    parse_markers(msg, false).pop().unwrap_or_default()
+}
+
+/// Like `parse_gmcp_message`, but a line break starts a new line. Colors
+/// carry over the breaks.
+///
+/// ```
+/// use mud_tui::telnet_client::parse_gmcp_lines;
+/// use mud_tui::Rgb;
+///
+/// let lines = parse_gmcp_lines("$Rred\r\nstill red $nplain");
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines[0].text(), "red");
+/// assert_eq!(lines[1].segments[0].text, "still red ");
+/// assert_eq!(lines[1].segments[0].style.fg, Some(Rgb(255, 0, 0)));
+/// assert_eq!(lines[1].segments[1].style.fg, None);
+/// ```
+pub fn parse_gmcp_lines(msg: &str) -> Vec<StyledLine> {
+    parse_markers(msg, true)
+}
+
+/// The parser behind `parse_gmcp_message` and `parse_gmcp_lines`: one line
+/// per line break with `breaks`, else a single line.
+fn parse_markers(msg: &str, breaks: bool) -> Vec<StyledLine> {
+    let mut lines = Vec::new();
+    let mut line = StyledLine::default();
     let mut current_text = String::new();
-    let mut current_color = Color::White;
+    let mut current_color: Option<Rgb> = None;
+    let mut current_bg: Option<Rgb> = None;
     let mut chars = msg.chars().peekable();
 
     while let Some(ch) = chars.next() {
-        if ch == '$' {
-            // Look ahead to see if we have a valid color marker.
-            if let Some(&next_ch) = chars.peek() {
-                if next_ch == 'x' {
-                    // Marker like "$xNNN": consume 'x' and all following digits.
-                    chars.next(); // consume 'x'
-                    let mut num_str = String::new();
-                    while let Some(&digit) = chars.peek() {
-                        if digit.is_ascii_digit() {
-                            num_str.push(digit);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    let marker = format!("$x{}", num_str);
-                    let new_color = convert_color_marker(&marker);
-                    if !current_text.is_empty() {
-                        spans.push(Span::styled(
-                            current_text.clone(),
-                            Style::default().fg(current_color),
-                        ));
-                        current_text.clear();
-                    }
-                    current_color = new_color;
-                    continue;
-                } else {
-                    // Check for single-character markers.
-                    let marker = format!("${}", next_ch);
-                let new_color = match next_ch {
-                    'G' => Some(Color::Rgb(0, 255, 0)),
-                    'M' => Some(Color::Rgb(255, 0, 255)),
-                    'R' => Some(Color::Rgb(255, 0, 0)),
-                    'Y' => Some(Color::Rgb(255, 255, 0)),
-                    'B' => Some(Color::Rgb(0, 0, 255)),
-                    'C' => Some(Color::Rgb(0, 255, 255)),
-                    'w' | 'W' => Some(Color::Rgb(255, 255, 255)),
-                    _ => None,
-                };
-                if let Some(col) = new_color {
-                    if !current_text.is_empty() {
-                            spans.push(Span::styled(
-                                current_text.clone(),
-                                Style::default().fg(current_color),
-                            ));
-                        current_text.clear();
-                    }
-                        chars.next(); // consume the marker letter.
-                    current_color = col;
-                    continue;
-                } else {
-                        // Unrecognized marker: output the '$' literally.
-                    current_text.push(ch);
+        if ch == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            let mut params = String::new();
+            let mut end = None;
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    end = Some(c);
+                    break;
+                }
+                params.push(c);
+            }
+            // Only SGR (`m`) sequences matter; other control sequences are dropped.
+            if end == Some('m') {
+                let style = TextStyle { fg: current_color, bg: current_bg, ..TextStyle::default() };
+                line.push(std::mem::take(&mut current_text), style);
+                apply_sgr(&params, &mut current_color, &mut current_bg);
+            }
+            continue;
+        }
+        if ch != '$' {
+            match ch {
+                '\r' => {}
+                '\n' if breaks => {
+                    let style = TextStyle { fg: current_color, bg: current_bg, ..TextStyle::default() };
+                    line.push(std::mem::take(&mut current_text), style);
+                    lines.push(std::mem::take(&mut line));
+                }
+                '\n' => current_text.push(' '),
+                _ => current_text.push(ch),
+            }
+            continue;
+        }
+        // Look ahead to see if we have a valid color marker.
+        let marker = match chars.peek() {
+            Some('x') => {
+                // Marker like "$xNNN": consume 'x' and all following digits.
+                chars.next();
+                let mut num_str = String::new();
+                while let Some(&digit) = chars.peek() {
+                    if !digit.is_ascii_digit() {
+                        break;
                     }
+                    num_str.push(digit);
+                    chars.next();
                 }
-            } else {
+                format!("$x{}", num_str)
+            }
+            Some(&next_ch) if marker_color(next_ch).is_some() => {
+                chars.next(); // consume the marker letter.
+                format!("${}", next_ch)
+            }
+            _ => {
+                // Unrecognized marker: output the '$' literally.
                 current_text.push(ch);
+                continue;
             }
-        } else {
-            current_text.push(ch);
-        }
-    }
-    if !current_text.is_empty() {
-        spans.push(Span::styled(current_text, Style::default().fg(current_color)));
+        };
+        line.push(std::mem::take(&mut current_text), TextStyle { fg: current_color, bg: current_bg, ..TextStyle::default() });
+        current_color = convert_color_marker(&marker);
     }
-    spans
+    line.push(current_text, TextStyle { fg: current_color, bg: current_bg, ..TextStyle::default() });
+    lines.push(line);
+    lines
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -538,33 +1509,30 @@ pub fn parse_gmcp_message(msg: &str) -> Vec<Span<'static>> {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 async fn handle_event(
     event: TelnetEvents,
-    tx: &Sender<TelnetMessage>,
+    tx: &MessageSink,
     write_half_arc: &Arc<Mutex<Option<OwnedWriteHalf>>>,
     gmcp_store: Arc<Mutex<GMCPStore>>,
+    msdp_store: &mut MsdpStore,
+    carry: &mut Utf8Carry,
+    ctx: &ReadContext,
 ) {
     match event {
         TelnetEvents::DataReceive(data) => {
             // debug("DataReceive event: {} bytes", data.len());
-            let data_vec = data.to_vec();
-            let lines = parse_ansi_codes(data_vec);
+            let data_vec = match ctx.encoding {
+                Encoding::Utf8 => carry.feed(&data),
+                _ => data.to_vec(),
+            };
+            let (lines, bell) = parse_ansi_codes_with_bell(&data_vec, ctx.encoding);
+            if bell {
+                let _ = tx.send(TelnetMessage::Bell).await;
+            }
             for line in lines {
-                let full_text: String = line.iter().map(|span| span.content.clone()).collect();
+                let full_text = line.text();
                 // debug("Received line: {}", full_text);
-                if full_text.to_lowercase().contains("comm.channel") {
-                    // debug("GMCP candidate detected in normal text: {}", full_text);
-                    if let Some(json_start) = full_text.find('{') {
-                        let maybe_json = &full_text[json_start..];
-                        if let Ok(cc) = serde_json::from_str::<CommChannel>(maybe_json) {
-                            let parsed_msg = parse_gmcp_message(&cc.msg);
-                            let mut chat_spans = vec![Span::styled(
-                                format!("[{}] {}: ", cc.chan, cc.player),
-                                Style::default().fg(Color::Green),
-                            )];
-                            chat_spans.extend(parsed_msg);
-                            let _ = tx.send(TelnetMessage::ChatMessage(chat_spans)).await;
-                            continue;
-                        }
-                    }
+                if let Some(chat) = leaked_chat_message(&full_text) {
+                    let _ = tx.send(chat).await;
+                    continue;
                 }
                 let _ = tx.send(TelnetMessage::MUDOutput(line)).await;
             }
@@ -574,10 +1542,10 @@ async fn handle_event(
             if subneg.option == TELOPT_GMCP {
                 let gmcp_str = String::from_utf8_lossy(&subneg.buffer).to_string();
                 // debug("Received GMCP subnegotiation: {}", gmcp_str);
-                if let Some((package, value)) = parse_gmcp(&gmcp_str) {
+                if let Some(GmcpMessage { package, data }) = parse_gmcp(&gmcp_str) {
                     {
                         let mut store = gmcp_store.lock().await;
-                        store.update(&package, value.clone());
+                        store.update(&package, data);
                     }
                     // debug("Updated GMCP store with package: {}", package);
                     if let Some(msg) = parse_known_gmcp_modules(&gmcp_str) {
@@ -587,6 +1555,10 @@ async fn handle_event(
                 } else {
                     // debug("Unable to parse GMCP message: {}", gmcp_str);
                 }
+            } else if subneg.option == TELOPT_MSDP {
+                for msg in apply_msdp(msdp_store, &subneg.buffer) {
+                    let _ = tx.send(msg).await;
+                }
             } else {
                 // debug("Received non-GMCP subnegotiation: option={}, buffer={:?}", subneg.option, subneg.buffer);
             }
@@ -600,6 +1572,38 @@ async fn handle_event(
                 }
             }
         }
+        TelnetEvents::Negotiation(neg) if neg.option == TELOPT_ECHO => {
+            match neg.command {
+                WILL => { let _ = tx.send(TelnetMessage::ServerEcho(true)).await; }
+                WONT => { let _ = tx.send(TelnetMessage::ServerEcho(false)).await; }
+                _ => {}
+            }
+        }
+        TelnetEvents::Negotiation(neg) if neg.option == TELOPT_NAWS => {
+            let naws = &ctx.naws;
+            naws.set_enabled(neg.command == DO);
+            if neg.command == DO {
+                if let Some(packet) = naws.packet() {
+                    if let Some(ref mut owned_wh) = *write_half_arc.lock().await {
+                        if let Err(e) = owned_wh.write_all(&packet).await {
+                            error!("NAWS write error: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+        TelnetEvents::Negotiation(neg) if neg.option == TELOPT_MSDP && neg.command == WILL => {
+            info!("Server supports MSDP; requesting reports");
+            let mut packet = vec![IAC, SB, TELOPT_MSDP];
+            packet.extend(report_request(MSDP_REPORTED));
+            packet.extend_from_slice(&[IAC, SE]);
+            let mut wh = write_half_arc.lock().await;
+            if let Some(ref mut owned_wh) = *wh {
+                if let Err(e) = owned_wh.write_all(&packet).await {
+                    error!("MSDP report request write error: {}", e);
+                }
+            }
+        }
         TelnetEvents::IAC(iac) => {
             // debug("Received IAC command: {:?}", iac);
         }
@@ -608,3 +1612,54 @@ async fn handle_event(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn text_mentioning_comm_channel_is_not_chat() {
+        assert!(leaked_chat_message(r#"You see comm.channel {"x":1} on the wall"#).is_none());
+    }
+
+    #[test]
+    fn comm_channel_with_invalid_json_is_not_chat() {
+        assert!(leaked_chat_message(r#"comm.channel {"chan": "gossip", "msg": "#).is_none());
+        assert!(leaked_chat_message(r#"comm.channel {"x":1}"#).is_none());
+    }
+
+    #[test]
+    fn leaked_comm_channel_payload_is_chat() {
+        let line = r#"comm.channel {"chan":"gossip","player":"Bob","msg":"$GHello all"}"#;
+        match leaked_chat_message(line) {
+            Some(TelnetMessage::ChatMessage { channel, player, message }) => {
+                assert_eq!((channel.as_str(), player.as_str(), message.as_str()), ("gossip", "Bob", "$GHello all"));
+            }
+            other => panic!("expected a chat message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_greeting_is_reported_and_drops_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let _server = listener.accept().await.unwrap();
+        // Every write from here on fails.
+        stream.shutdown().await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let client = TelnetClient::new(tx);
+        let result = client.start("test:23".to_string(), stream, Arc::new(Mutex::new(GMCPStore::new()))).await;
+        assert!(result.is_err());
+        assert!(client.write_half.lock().await.is_none());
+        match rx.try_recv() {
+            Ok(TelnetMessage::ConnectFailed { addr, error }) => {
+                assert_eq!(addr, "test:23");
+                assert!(error.contains("Failed to enable GMCP"), "{}", error);
+            }
+            other => panic!("expected ConnectFailed, got {:?}", other),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+}
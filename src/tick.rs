@@ -0,0 +1,186 @@
+// src/tick.rs - Countdown to the MUD's next game tick, fixed or learned from output
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The `[tick]` config section. The tracker is on when `pattern` or
+/// `interval_secs` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickConfig {
+    /// A regex matching the line the MUD prints on each tick. Every match
+    /// restarts the countdown, and the gaps between matches set the interval.
+    pub pattern: Option<String>,
+    /// Seconds between ticks: the interval used until one has been learned
+    /// from `pattern`, or the only one without a pattern (counted from
+    /// connecting). 0 = none.
+    pub interval_secs: f64,
+    /// Status bar text; `{secs}` is replaced by the seconds left.
+    pub format: String,
+}
+
+impl Default for TickConfig {
+    fn default() -> Self {
+        Self { pattern: None, interval_secs: 0.0, format: "Tick {secs}s".to_string() }
+    }
+}
+
+/// Gaps between tick lines kept for learning the interval.
+const LEARN_GAPS: usize = 5;
+
+/// Matches closer together than this are the same tick printed twice.
+const MIN_GAP: Duration = Duration::from_secs(1);
+
+pub struct TickTracker {
+    pattern: Option<Regex>,
+    /// The configured interval, if any.
+    fixed: Option<Duration>,
+    format: String,
+    last_tick: Option<Instant>,
+    /// Recent gaps between ticks, each already divided by the number of
+    /// ticks it spans.
+    gaps: VecDeque<Duration>,
+}
+
+impl TickTracker {
+    /// `None` when the config turns the tracker off (or its pattern is
+    /// invalid and there is no interval, which is logged).
+    pub fn from_config(cfg: &TickConfig) -> Option<Self> {
+        let pattern = cfg.pattern.as_deref().and_then(|pattern| match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                warn!("Invalid tick pattern '{}': {}", pattern, e);
+                None
+            }
+        });
+        let fixed = (cfg.interval_secs > 0.0).then(|| Duration::from_secs_f64(cfg.interval_secs));
+        if pattern.is_none() && fixed.is_none() {
+            return None;
+        }
+        Some(Self { pattern, fixed, format: cfg.format.clone(), last_tick: None, gaps: VecDeque::new() })
+    }
+
+    /// Starts counting for a new connection. Without a pattern, ticks are
+    /// assumed to start now; with one, the countdown waits for the first
+    /// tick line.
+    pub fn start(&mut self, now: Instant) {
+        self.last_tick = if self.pattern.is_none() { Some(now) } else { None };
+    }
+
+    /// Restarts the countdown if `line` marks a tick.
+    pub fn on_line(&mut self, line: &str, now: Instant) {
+        if self.pattern.as_ref().is_some_and(|re| re.is_match(line)) {
+            self.tick(now);
+        }
+    }
+
+    /// Records a tick at `now` and learns from the gap since the last one.
+    /// A gap spanning several intervals (ticks whose line was missed, e.g.
+    /// while fighting) counts as that many ticks.
+    fn tick(&mut self, now: Instant) {
+        if let Some(last) = self.last_tick {
+            let gap = now.duration_since(last);
+            if gap < MIN_GAP {
+                return;
+            }
+            let ticks = match self.interval() {
+                Some(interval) => (gap.as_secs_f64() / interval.as_secs_f64()).round().max(1.0),
+                None => 1.0,
+            };
+            if self.gaps.len() == LEARN_GAPS {
+                self.gaps.pop_front();
+            }
+            self.gaps.push_back(gap.div_f64(ticks));
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// The median of the learned gaps, else the configured interval.
+    pub fn interval(&self) -> Option<Duration> {
+        if self.gaps.is_empty() {
+            return self.fixed;
+        }
+        let mut gaps: Vec<Duration> = self.gaps.iter().copied().collect();
+        gaps.sort();
+        Some(gaps[gaps.len() / 2])
+    }
+
+    /// Time until the next expected tick, if it can be told yet.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        let interval = self.interval().filter(|i| !i.is_zero())?;
+        let since = now.duration_since(self.last_tick?);
+        let into = Duration::from_secs_f64(since.as_secs_f64() % interval.as_secs_f64());
+        Some(interval - into)
+    }
+
+    /// The status bar text for the countdown, if there is one.
+    pub fn status(&self, now: Instant) -> Option<String> {
+        let left = self.remaining(now)?;
+        Some(self.format.replace("{secs}", &left.as_secs_f64().ceil().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(pattern: Option<&str>, interval_secs: f64) -> TickTracker {
+        let cfg = TickConfig { pattern: pattern.map(str::to_string), interval_secs, ..TickConfig::default() };
+        TickTracker::from_config(&cfg).unwrap()
+    }
+
+    fn secs(n: f64) -> Duration {
+        Duration::from_secs_f64(n)
+    }
+
+    #[test]
+    fn configured_interval_is_used_until_a_gap_is_learned() {
+        let t0 = Instant::now();
+        let mut tick = tracker(Some("^The day has begun"), 60.0);
+        tick.start(t0);
+        assert_eq!(tick.remaining(t0 + secs(5.0)), None);
+
+        tick.on_line("The day has begun.", t0 + secs(10.0));
+        assert_eq!(tick.interval(), Some(secs(60.0)));
+        assert_eq!(tick.remaining(t0 + secs(25.0)), Some(secs(45.0)));
+        assert_eq!(tick.status(t0 + secs(25.0)).as_deref(), Some("Tick 45s"));
+    }
+
+    #[test]
+    fn without_a_pattern_ticks_count_from_connecting() {
+        let t0 = Instant::now();
+        let mut tick = tracker(None, 30.0);
+        tick.start(t0);
+        assert_eq!(tick.remaining(t0 + secs(40.0)), Some(secs(20.0)));
+    }
+
+    #[test]
+    fn learned_interval_is_the_median_gap() {
+        let t0 = Instant::now();
+        let mut tick = tracker(Some("tick"), 60.0);
+        for at in [0.0, 30.0, 60.0, 90.0] {
+            tick.on_line("tick", t0 + secs(at));
+        }
+        assert_eq!(tick.interval(), Some(secs(30.0)));
+
+        // One slow tick does not move the median.
+        tick.on_line("tick", t0 + secs(135.0));
+        assert_eq!(tick.interval(), Some(secs(30.0)));
+    }
+
+    #[test]
+    fn missed_and_repeated_tick_lines_are_not_outliers() {
+        let t0 = Instant::now();
+        let mut tick = tracker(Some("tick"), 0.0);
+        tick.on_line("tick", t0);
+        tick.on_line("tick", t0 + secs(30.0));
+        // The same tick printed twice.
+        tick.on_line("tick", t0 + secs(30.5));
+        // Two ticks whose lines were lost in between.
+        tick.on_line("tick", t0 + secs(120.0));
+        assert_eq!(tick.interval(), Some(secs(30.0)));
+        assert_eq!(tick.remaining(t0 + secs(130.0)), Some(secs(20.0)));
+    }
+}
@@ -0,0 +1,111 @@
+// src/timers.rs - Named timers that send a command on an interval
+use crate::telnet_client::TelnetClient;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+/// A timer defined in the `[[timers]]` config section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerConfig {
+    pub name: String,
+    pub seconds: u64,
+    pub command: String,
+}
+
+struct Timer {
+    seconds: u64,
+    command: String,
+    paused: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The set of running timers, keyed by name.
+#[derive(Default)]
+pub struct Timers {
+    timers: BTreeMap<String, Timer>,
+}
+
+impl Timers {
+    /// Starts (or replaces) a timer that sends `command` every `seconds`.
+    /// When `echo` is set, each firing is echoed to the output pane.
+    pub fn add(&mut self, name: &str, seconds: u64, command: &str, client: &TelnetClient, echo: bool) {
+        self.remove(name);
+        let paused = Arc::new(AtomicBool::new(false));
+        let handle = tokio::spawn(run_timer(
+            client.clone(),
+            Duration::from_secs(seconds.max(1)),
+            command.to_string(),
+            paused.clone(),
+            echo,
+        ));
+        info!("Started timer '{}' every {}s", name, seconds);
+        self.timers.insert(
+            name.to_string(),
+            Timer { seconds, command: command.to_string(), paused, handle },
+        );
+    }
+
+    /// Stops and forgets a timer. Returns false if no such timer exists.
+    pub fn remove(&mut self, name: &str) -> bool {
+        match self.timers.remove(name) {
+            Some(timer) => {
+                timer.handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pauses or resumes a timer. Returns false if no such timer exists.
+    pub fn set_paused(&mut self, name: &str, paused: bool) -> bool {
+        match self.timers.get(name) {
+            Some(timer) => {
+                timer.paused.store(paused, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// One human-readable line per timer.
+    pub fn describe(&self) -> Vec<String> {
+        self.timers
+            .iter()
+            .map(|(name, t)| {
+                let state = if t.paused.load(Ordering::Relaxed) { " (paused)" } else { "" };
+                format!("{}: every {}s -> {}{}", name, t.seconds, t.command, state)
+            })
+            .collect()
+    }
+
+    /// Stops every timer, e.g. when the connection drops.
+    pub fn stop_all(&mut self) {
+        for (_, timer) in std::mem::take(&mut self.timers) {
+            timer.handle.abort();
+        }
+    }
+}
+
+async fn run_timer(client: TelnetClient, period: Duration, command: String, paused: Arc<AtomicBool>, echo: bool) {
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker.tick().await; // The first tick completes immediately; wait a full period first.
+    loop {
+        ticker.tick().await;
+        if paused.load(Ordering::Relaxed) {
+            continue;
+        }
+        let result = if echo {
+            client.send_command_echoed(&command).await
+        } else {
+            client.send_command(&command).await
+        };
+        if let Err(e) = result {
+            error!("Timer command failed: {}", e);
+        }
+    }
+}
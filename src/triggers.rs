@@ -0,0 +1,255 @@
+// src/triggers.rs - Regex triggers that react to lines of MUD output
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A trigger defined in the `[[triggers]]` config section.
+///
+/// Exactly one of `send` or `script`/`function` should be set. `send` may
+/// reference capture groups as `$1` or `${name}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    pub pattern: String,
+    #[serde(default)]
+    pub send: Option<String>,
+    #[serde(default)]
+    pub script: Option<String>,
+    #[serde(default)]
+    pub function: Option<String>,
+    /// Names the trigger for `/trigger`; the pattern when unset.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Triggers in a group are switched on and off together.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Higher priorities run first; equal ones in definition order.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl TriggerConfig {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.pattern)
+    }
+}
+
+/// What a trigger does when it matches.
+#[derive(Debug, Clone)]
+pub enum TriggerAction {
+    /// Send a command to the MUD (after capture expansion).
+    Send(String),
+    /// Call a function defined in a loaded script.
+    Script { script: String, function: String },
+}
+
+pub struct Trigger {
+    name: String,
+    group: Option<String>,
+    priority: i32,
+    enabled: bool,
+    pattern: Regex,
+    action: TriggerAction,
+}
+
+impl Trigger {
+    /// `None` (logged) when `pattern` is not a valid regex.
+    fn new(name: &str, pattern: &str, action: TriggerAction) -> Option<Self> {
+        match Regex::new(pattern) {
+            Ok(pattern) => Some(Self {
+                name: name.to_string(),
+                group: None,
+                priority: 0,
+                enabled: true,
+                pattern,
+                action,
+            }),
+            Err(e) => {
+                warn!("Invalid trigger pattern '{}': {}", pattern, e);
+                None
+            }
+        }
+    }
+}
+
+/// A matched trigger, with `Send` commands already expanded.
+pub struct TriggerHit {
+    pub action: TriggerAction,
+    pub captures: Vec<String>,
+}
+
+/// Triggers by name, kept in the order they run.
+#[derive(Default)]
+pub struct Triggers {
+    list: Vec<Trigger>,
+}
+
+impl Triggers {
+    /// Builds triggers from config, skipping (and logging) invalid entries.
+    pub fn from_config(configs: &[TriggerConfig]) -> Self {
+        let mut triggers = Self::default();
+        for cfg in configs {
+            let action = match (&cfg.send, &cfg.script, &cfg.function) {
+                (Some(send), None, None) => TriggerAction::Send(send.clone()),
+                (None, Some(script), Some(function)) => TriggerAction::Script {
+                    script: script.clone(),
+                    function: function.clone(),
+                },
+                _ => {
+                    warn!("Trigger '{}' needs either `send` or `script` + `function`", cfg.pattern);
+                    continue;
+                }
+            };
+            let Some(mut trigger) = Trigger::new(cfg.name(), &cfg.pattern, action) else {
+                continue;
+            };
+            trigger.group = cfg.group.clone();
+            trigger.priority = cfg.priority;
+            trigger.enabled = cfg.enabled;
+            triggers.insert(trigger);
+        }
+        triggers
+    }
+
+    /// Adds an enabled trigger named after its pattern, with priority 0.
+    /// Returns false (and logs) if the pattern is not a valid regex.
+    pub fn add(&mut self, pattern: &str, action: TriggerAction) -> bool {
+        match Trigger::new(pattern, pattern, action) {
+            Some(trigger) => {
+                self.insert(trigger);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds `trigger` after every trigger of the same or higher priority,
+    /// replacing one with the same name.
+    fn insert(&mut self, trigger: Trigger) {
+        self.list.retain(|t| t.name != trigger.name);
+        let at = self.list.partition_point(|t| t.priority >= trigger.priority);
+        self.list.insert(at, trigger);
+    }
+
+    /// Enables or disables the trigger called `target`, or else every trigger
+    /// in the group called that. Returns how many triggers it applied to.
+    pub fn set_enabled(&mut self, target: &str, enabled: bool) -> usize {
+        let by_name = self.list.iter().any(|t| t.name == target);
+        let mut count = 0;
+        for trigger in &mut self.list {
+            let hit = if by_name {
+                trigger.name == target
+            } else {
+                trigger.group.as_deref().is_some_and(|g| g.eq_ignore_ascii_case(target))
+            };
+            if hit {
+                trigger.enabled = enabled;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Whether the trigger called `name` is enabled, if there is one.
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.list.iter().find(|t| t.name == name).map(|t| t.enabled)
+    }
+
+    /// One line per trigger, in the order they run.
+    pub fn describe(&self) -> Vec<String> {
+        self.list
+            .iter()
+            .map(|t| {
+                let group = t.group.as_deref().map(|g| format!(" [{}]", g)).unwrap_or_default();
+                let state = if t.enabled { "" } else { " (disabled)" };
+                format!("{:>4} {}{}{}: {}", t.priority, t.name, group, state, t.pattern)
+            })
+            .collect()
+    }
+
+    /// Returns every enabled trigger matching `text`, highest priority first.
+    pub fn matching(&self, text: &str) -> Vec<TriggerHit> {
+        let mut hits = Vec::new();
+        for trigger in self.list.iter().filter(|t| t.enabled) {
+            let Some(caps) = trigger.pattern.captures(text) else {
+                continue;
+            };
+            let captures = caps
+                .iter()
+                .map(|m| m.map(|m| m.as_str().to_string()).unwrap_or_default())
+                .collect();
+            let action = match &trigger.action {
+                TriggerAction::Send(template) => {
+                    let mut expanded = String::new();
+                    caps.expand(template, &mut expanded);
+                    TriggerAction::Send(expanded)
+                }
+                other => other.clone(),
+            };
+            hits.push(TriggerHit { action, captures });
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, priority: i32, enabled: bool) -> TriggerConfig {
+        TriggerConfig {
+            pattern: "^You are hungry".to_string(),
+            send: Some(name.to_string()),
+            script: None,
+            function: None,
+            name: Some(name.to_string()),
+            group: None,
+            priority,
+            enabled,
+        }
+    }
+
+    fn sent(triggers: &Triggers, text: &str) -> Vec<String> {
+        triggers
+            .matching(text)
+            .into_iter()
+            .map(|hit| match hit.action {
+                TriggerAction::Send(cmd) => cmd,
+                TriggerAction::Script { function, .. } => function,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn higher_priorities_run_first_and_equal_ones_in_order() {
+        let triggers = Triggers::from_config(&[
+            config("low", -1, true),
+            config("first", 0, true),
+            config("high", 5, true),
+            config("second", 0, true),
+        ]);
+        assert_eq!(sent(&triggers, "You are hungry."), ["high", "first", "second", "low"]);
+    }
+
+    #[test]
+    fn added_trigger_goes_after_those_of_equal_priority() {
+        let mut triggers = Triggers::from_config(&[config("configured", 0, true), config("low", -1, true)]);
+        assert!(triggers.add("^You are hungry", TriggerAction::Send("added".to_string())));
+        assert_eq!(sent(&triggers, "You are hungry."), ["configured", "added", "low"]);
+    }
+
+    #[test]
+    fn disabled_triggers_are_skipped() {
+        let mut triggers = Triggers::from_config(&[config("on", 0, true), config("off", 0, false)]);
+        assert_eq!(sent(&triggers, "You are hungry."), ["on"]);
+        assert_eq!(triggers.set_enabled("on", false), 1);
+        assert!(sent(&triggers, "You are hungry.").is_empty());
+        assert_eq!(triggers.set_enabled("off", true), 1);
+        assert_eq!(sent(&triggers, "You are hungry."), ["off"]);
+    }
+}
@@ -0,0 +1,630 @@
+// src/tui.rs - Terminal setup and the main event loop
+use crate::app::{
+    handle_message, send_and_echo, send_hidden, send_macro, submit_command, AppState, SessionRequest, DEFAULT_HOST,
+    DEFAULT_PORT,
+};
+use crate::config::{Config, NewlineKey, CONFIG_FILE};
+use crate::gmcp_store::GMCPStore;
+use crate::inspector::GmcpInspector;
+use crate::scripting::{self, ScriptEngine};
+use crate::status_socket::{changes_status, StatusPublisher, StatusSocket};
+use crate::telnet_client::{TelnetClient, TelnetMessage};
+use crate::triggers::TriggerAction;
+use crate::ui::{timed_view, ui_draw, SessionTab};
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event as CEvent,
+    EventStream, KeyCode, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use log::{error, info};
+use ratatui::backend::CrosstermBackend;
+use std::error::Error;
+use std::io;
+use futures_core::Stream;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+/// How often the UI checks whether time alone changed what it shows, e.g. a
+/// countdown, and redraws if so.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Shortest time between redraws caused by server messages (about 30 per
+/// second). Keys and other terminal events redraw at once.
+const FRAME: Duration = Duration::from_millis(33);
+
+/// One connection and everything shown for it. Each session has its own
+/// channel and receive task, so messages always reach their own state.
+struct Session {
+    /// The host, shown in the tab bar.
+    name: String,
+    state: Arc<Mutex<AppState>>,
+    client: TelnetClient,
+    /// Set when output or chat arrives; cleared while the session is shown.
+    activity: Arc<AtomicBool>,
+    shutdown: CancellationToken,
+    receive_task: JoinHandle<()>,
+}
+
+impl Session {
+    /// Sets up a session for `host`:`port`. The connection is made in the
+    /// background once the session is running, so progress and failures show
+    /// in its output. Every handled message marks the UI `dirty` and wakes it
+    /// with `redraw`.
+    /// Snapshots of its state go to `status`, if given.
+    async fn open(
+        host: &str,
+        port: &str,
+        status: Option<StatusPublisher>,
+        redraw: &Arc<Notify>,
+        dirty: &Arc<AtomicBool>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let config = Config::load(Path::new(CONFIG_FILE));
+
+        // Room for a burst of output (a long room description, a who list)
+        // before the read loop has to wait for the UI.
+        let (tx, mut rx) = mpsc::channel(1024);
+        let telnet_client = TelnetClient::with_options(tx.clone(), config.connection.clone());
+
+        // Create the GMCP store.
+        let gmcp_store = Arc::new(Mutex::new(GMCPStore::new()));
+
+        // Load scripts before the UI starts so their triggers are in place.
+        let scripts = if config.scripting.enabled {
+            let engine = Arc::new(ScriptEngine::new(Path::new(&config.scripting.dir), gmcp_store.clone(), tx.clone()));
+            let loader = engine.clone();
+            let output = tokio::task::spawn_blocking(move || loader.load_all()).await?;
+            Some((engine, output))
+        } else {
+            None
+        };
+
+        let app_state = Arc::new(Mutex::new(AppState::new(config, gmcp_store.clone())));
+        {
+            let mut st = app_state.lock().await;
+            if let Some((engine, output)) = scripts {
+                for t in output.triggers {
+                    let action = TriggerAction::Script { script: t.script, function: t.function };
+                    st.triggers.add(&t.pattern, action);
+                }
+                scripting::apply_actions(&telnet_client, &tx, output.actions).await;
+                st.scripts = Some(engine);
+            }
+            st.last_server = Some((host.to_string(), port.to_string()));
+            st.status_socket = status;
+            st.add_system_output(format!("Connecting to {}:{}...", host, port));
+        }
+
+        // Spawn a task to handle incoming TelnetMessages and update UI state.
+        // On shutdown it stops waiting and handles whatever was already queued.
+        // Each handled message marks the UI dirty; the UI loop coalesces those
+        // into at most one redraw per FRAME.
+        let shutdown = CancellationToken::new();
+        let activity = Arc::new(AtomicBool::new(false));
+        let ui_state = Arc::clone(&app_state);
+        let rx_client = telnet_client.clone();
+        let receive_redraw = Arc::clone(redraw);
+        let receive_dirty = Arc::clone(dirty);
+        let receive_activity = Arc::clone(&activity);
+        let receive_shutdown = shutdown.clone();
+        let receive_task = tokio::spawn(async move {
+            loop {
+                let msg = tokio::select! {
+                    msg = rx.recv() => msg,
+                    _ = receive_shutdown.cancelled() => break,
+                };
+                let Some(msg) = msg else { break };
+                if matches!(msg, TelnetMessage::MUDOutput(_) | TelnetMessage::ChatMessage { .. }) {
+                    receive_activity.store(true, Ordering::Release);
+                }
+                let status_changed = changes_status(&msg);
+                let mut st = ui_state.lock().await;
+                handle_message(&mut st, &rx_client, msg);
+                if let Some(status) = st.status_socket.as_ref().filter(|_| status_changed) {
+                    status.publish(&st);
+                }
+                drop(st);
+                receive_dirty.store(true, Ordering::Release);
+                receive_redraw.notify_one();
+            }
+            rx.close();
+            while let Some(msg) = rx.recv().await {
+                handle_message(&mut *ui_state.lock().await, &rx_client, msg);
+            }
+        });
+
+        let client = telnet_client.clone();
+        let addr = (host.to_string(), port.to_string());
+        tokio::spawn(async move {
+            // A failure shows in the session; the client stays up for /reconnect.
+            if let Err(e) = client.connect(&addr.0, &addr.1, gmcp_store).await {
+                error!("Failed to connect to {}:{}: {}", addr.0, addr.1, e);
+            }
+        });
+
+        Ok(Self { name: host.to_string(), state: app_state, client: telnet_client, activity, shutdown, receive_task })
+    }
+
+    /// Keeps changed settings, closes the connection and stops every
+    /// background task of the session.
+    async fn close(self) {
+        {
+            let mut st = self.state.lock().await;
+            if let Err(e) = st.save_config(true) {
+                error!("Failed to save config: {}", e);
+            }
+            st.timers.stop_all();
+        }
+        self.client.disconnect().await;
+        self.shutdown.cancel();
+        let _ = self.receive_task.await;
+    }
+}
+
+/// Options for `run`, usually from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Stream JSON snapshots of every session's state to readers of a Unix
+    /// socket at this path.
+    pub status_socket: Option<PathBuf>,
+}
+
+/// Runs the client: opens the first session, whose connection is made in the
+/// background (a server that cannot be reached is reported in the UI), then
+/// drives the terminal UI until the user quits.
+pub async fn run(options: RunOptions) -> Result<(), Box<dyn Error>> {
+    let status_socket = match &options.status_socket {
+        Some(path) => Some(
+            StatusSocket::bind(path).map_err(|e| format!("Cannot open status socket {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+    let status = status_socket.as_ref().map(StatusSocket::publisher);
+    let redraw = Arc::new(Notify::new());
+    let dirty = Arc::new(AtomicBool::new(false));
+    let mut sessions = vec![Session::open(DEFAULT_HOST, DEFAULT_PORT, status.clone(), &redraw, &dirty).await?];
+    let mut active = 0;
+
+    // Set up the TUI. The guard restores the terminal however main exits.
+    let mut mouse_captured = sessions[active].state.lock().await.mouse_capture;
+    let terminal_guard = TerminalGuard::enter(mouse_captured)?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = ratatui::Terminal::new(backend)?;
+
+    // Terminal events are awaited directly; no reader thread or polling delay.
+    let mut events = EventStream::new();
+    // Wakes the loop without new input or output, for countdowns, the ping and idle checks.
+    let mut tick = tokio::time::interval(TICK);
+    tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    // Main UI loop.
+    let mut draw_now = true;
+    let mut ticked = false;
+    let mut last_draw = Instant::now();
+    // The active session's time-dependent content as last drawn.
+    let mut drawn_view = None;
+    loop {
+        // Carry out a /session command or session key from the last pass.
+        let request = sessions[active].state.lock().await.session_request.take();
+        if let Some(request) = request {
+            active = handle_session_request(&mut sessions, active, request, &status, &redraw, &dirty).await;
+            draw_now = true;
+        }
+        // Sessions in the background stay connected too.
+        for session in &sessions {
+            let mut st = session.state.lock().await;
+            draw_now |= st.check_idle(&session.client);
+            st.check_ping(&session.client);
+        }
+        let app_state = Arc::clone(&sessions[active].state);
+        let telnet_client = sessions[active].client.clone();
+        {
+            let mut st = app_state.lock().await;
+            draw_now |= st.check_scroll_return();
+            if st.mouse_capture != mouse_captured {
+                mouse_captured = st.mouse_capture;
+                if mouse_captured {
+                    execute!(io::stdout(), EnableMouseCapture)?;
+                } else {
+                    execute!(io::stdout(), DisableMouseCapture)?;
+                }
+            }
+            let view = (draw_now || ticked).then(|| timed_view(&mut st));
+            draw_now |= ticked && view != drawn_view;
+            if draw_now {
+                drawn_view = view;
+                dirty.store(false, Ordering::Release);
+                sessions[active].activity.store(false, Ordering::Release);
+                let tabs = session_tabs(&sessions, active);
+                terminal.draw(|f| ui_draw(f, &mut st, &tabs))?;
+                st.report_window_size(&telnet_client);
+                last_draw = Instant::now();
+            }
+        }
+        draw_now = false;
+        ticked = false;
+        tokio::select! {
+            evt = next_event(&mut events) => {
+                draw_now = true;
+                if let Some(Ok(e)) = evt {
+                    let mut st = app_state.lock().await;
+                    let ctrl_c = matches!(&e, CEvent::Key(k)
+                        if k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL));
+                    if matches!(e, CEvent::Key(_)) && !ctrl_c {
+                        // Any other key cancels a pending Ctrl+C quit.
+                        st.quit_pending = None;
+                    }
+                    match e {
+                        CEvent::Key(_) if ctrl_c && st.request_quit() => {
+                            info!("Ctrl+C pressed, exiting...");
+                            break;
+                        }
+                        CEvent::Key(_) if ctrl_c => {}
+                        // The GMCP inspector takes the keyboard while it is open.
+                        CEvent::Key(k) if st.inspector.is_some() => match k.code {
+                            KeyCode::Esc => st.inspector = None,
+                            KeyCode::F(5) => {
+                                let data = st.gmcp_store.lock().await.snapshot();
+                                st.inspector = Some(GmcpInspector::new(data));
+                            }
+                            code => {
+                                if let Some(inspector) = st.inspector.as_mut() {
+                                    match code {
+                                        KeyCode::Up => inspector.up(1),
+                                        KeyCode::Down => inspector.down(1),
+                                        KeyCode::PageUp => inspector.up(10),
+                                        KeyCode::PageDown => inspector.down(10),
+                                        KeyCode::Left => inspector.set_expanded(false),
+                                        KeyCode::Right => inspector.set_expanded(true),
+                                        KeyCode::Enter => inspector.toggle(),
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        },
+                        // Esc closes the recent-tells list rather than quitting.
+                        CEvent::Key(k) if st.show_tells && k.code == KeyCode::Esc => st.show_tells = false,
+                        // Macro keys win over the built-in bindings below.
+                        CEvent::Key(k) if st.macros.lookup(&k).is_some() => {
+                            let commands = st.macros.lookup(&k).map(<[String]>::to_vec).unwrap_or_default();
+                            send_macro(&mut st, &telnet_client, commands);
+                        }
+                        CEvent::Key(k) => match k.code {
+                            KeyCode::Char('y') if k.modifiers.contains(KeyModifiers::CONTROL) => {
+                                let count = st.config.ui.copy_lines;
+                                st.copy_last_lines(count, true);
+                            }
+                            KeyCode::Char(c @ '1'..='9') if k.modifiers.contains(KeyModifiers::ALT) => {
+                                st.session_request = Some(SessionRequest::Switch(c as usize - '1' as usize));
+                            }
+                            KeyCode::Left if k.modifiers.contains(KeyModifiers::ALT) => {
+                                st.session_request = Some(SessionRequest::Previous);
+                            }
+                            KeyCode::Right if k.modifiers.contains(KeyModifiers::ALT) => {
+                                st.session_request = Some(SessionRequest::Next);
+                            }
+                            KeyCode::Char(c) => {
+                                let mapped = if st.movement_mode {
+                                    st.config.movement.keys.get(&c).cloned()
+                                } else {
+                                    None
+                                };
+                                match mapped {
+                                    Some(cmd) => send_and_echo(&mut st, &telnet_client, cmd),
+                                    None => st.insert_char(c),
+                                }
+                            }
+                            KeyCode::Backspace => { st.backspace(); }
+                            KeyCode::Delete => { st.delete(); }
+                            KeyCode::Left => { st.cursor_left(); }
+                            KeyCode::Right => { st.cursor_right(); }
+                            KeyCode::Home if k.modifiers.contains(KeyModifiers::CONTROL) => { st.scroll_focused_to_oldest(); }
+                            KeyCode::End if k.modifiers.contains(KeyModifiers::CONTROL) => { st.scroll_focused_to_newest(); }
+                            KeyCode::Home => { st.cursor = 0; }
+                            KeyCode::End => { st.cursor = st.input.len(); }
+                            KeyCode::Enter if !st.password_mode && is_newline_key(st.config.input.newline_key, k.modifiers) => {
+                                st.insert_char('\n');
+                            }
+                            KeyCode::Enter => {
+                                let input = st.take_input();
+                                if st.password_mode {
+                                    // Never keep or echo what was typed at a password prompt.
+                                    send_hidden(&mut st, &telnet_client, input);
+                                } else if !input.contains('\n') {
+                                    submit_command(&mut st, &telnet_client, input);
+                                } else {
+                                    // Multi-line input (composed or pasted): each line is its own
+                                    // command, in order. Blank lines inside the block are sent
+                                    // as empty lines (paragraph breaks in MUD mail and boards).
+                                    for line in input.trim_matches('\n').split('\n') {
+                                        if line.trim().is_empty() {
+                                            send_and_echo(&mut st, &telnet_client, String::new());
+                                        } else {
+                                            submit_command(&mut st, &telnet_client, line.to_string());
+                                        }
+                                    }
+                                }
+                            }
+                            KeyCode::Up => { st.history_up(); }
+                            KeyCode::Down => { st.history_down(); }
+                            KeyCode::Tab => { st.autocomplete(); }
+                            KeyCode::BackTab => { st.toggle_focus(); }
+                            KeyCode::Esc => { info!("ESC pressed, exiting..."); break; }
+                            KeyCode::F(1) => { st.scroll_up_chat(1); }
+                            KeyCode::F(2) => { st.scroll_down_chat(1); }
+                            KeyCode::F(3) => {
+                                st.movement_mode = !st.movement_mode;
+                                info!("Movement mode {}", if st.movement_mode { "on" } else { "off" });
+                            }
+                            KeyCode::F(4) => {
+                                st.gauge_label = st.gauge_label.next();
+                                let name = st.gauge_label.name();
+                                st.add_system_output(format!("Gauge labels: {}", name));
+                            }
+                            KeyCode::F(5) => {
+                                let data = st.gmcp_store.lock().await.snapshot();
+                                st.inspector = Some(GmcpInspector::new(data));
+                            }
+                            KeyCode::F(7) => { st.toggle_tells(); }
+                            KeyCode::PageUp => { st.scroll_up_focused(1); }
+                            KeyCode::PageDown => { st.scroll_down_focused(1); }
+                            _ => {}
+                        },
+                        CEvent::Paste(text) => {
+                            // Pasted text lands in the input line for review; nothing is
+                            // sent until Enter. Line breaks are kept (shown as ⏎).
+                            let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                            if st.password_mode {
+                                st.insert_str(text.split('\n').next().unwrap_or(""));
+                            } else {
+                                st.insert_str(&text);
+                            }
+                        }
+                        CEvent::Mouse(me) => {
+                            // Wheel up reveals older text and wheel down newer; wheeling
+                            // over a pane also gives it the keyboard focus.
+                            let lines = st.config.ui.wheel_lines;
+                            match (me.kind, st.pane_at(me.column, me.row)) {
+                                (event::MouseEventKind::ScrollUp, Some(pane)) => {
+                                    st.focus = pane;
+                                    st.scroll_up_focused(lines);
+                                }
+                                (event::MouseEventKind::ScrollDown, Some(pane)) => {
+                                    st.focus = pane;
+                                    st.scroll_down_focused(lines);
+                                }
+                                _ => {}
+                            }
+                        }
+                        CEvent::Resize(width, height) => {
+                            // The next draw (right after this event) lays out for the new
+                            // size and then tells the server, if it asked for NAWS.
+                            st.term_size = (width, height);
+                        }
+                        _ => {}
+                    }
+                } else { break; }
+            }
+            _ = redraw.notified(), if !dirty.load(Ordering::Acquire) => {}
+            _ = tokio::time::sleep_until(last_draw + FRAME), if dirty.load(Ordering::Acquire) => draw_now = true,
+            _ = tick.tick() => ticked = true,
+        }
+    }
+
+    drop(events);
+    drop(terminal_guard);
+
+    for session in sessions {
+        session.close().await;
+    }
+    if let Some(socket) = status_socket {
+        socket.close().await;
+    }
+    info!("Application exited gracefully");
+    Ok(())
+}
+
+/// Carries out `request`, made in the session at `active`, and returns the
+/// index of the session to show next.
+async fn handle_session_request(
+    sessions: &mut Vec<Session>,
+    active: usize,
+    request: SessionRequest,
+    status: &Option<StatusPublisher>,
+    redraw: &Arc<Notify>,
+    dirty: &Arc<AtomicBool>,
+) -> usize {
+    let count = sessions.len();
+    let mut notice = Vec::new();
+    let next = match request {
+        SessionRequest::Open(host, port) => match Session::open(&host, &port, status.clone(), redraw, dirty).await {
+            Ok(session) => {
+                sessions.push(session);
+                count
+            }
+            Err(e) => {
+                notice.push(format!("Could not open a session for {}:{}: {}", host, port, e));
+                active
+            }
+        },
+        SessionRequest::Switch(index) if index < count => index,
+        SessionRequest::Switch(index) => {
+            notice.push(format!("No session {} (there are {})", index + 1, count));
+            active
+        }
+        SessionRequest::Next => (active + 1) % count,
+        SessionRequest::Previous => (active + count - 1) % count,
+        SessionRequest::Close if count == 1 => {
+            notice.push("This is the only session; Esc quits".to_string());
+            active
+        }
+        SessionRequest::Close => {
+            let session = sessions.remove(active);
+            notice.push(format!("Closed session {} ({})", active + 1, session.name));
+            session.close().await;
+            active.min(count - 2)
+        }
+        SessionRequest::List => {
+            for (i, session) in sessions.iter().enumerate() {
+                let st = session.state.lock().await;
+                let server = st.last_server.as_ref().map_or(session.name.clone(), |(host, port)| format!("{}:{}", host, port));
+                let state = if st.connected { "connected" } else { "not connected" };
+                let shown = if i == active { " (shown)" } else { "" };
+                notice.push(format!("  {} {} — {}{}", i + 1, server, state, shown));
+            }
+            active
+        }
+    };
+    let mut st = sessions[next].state.lock().await;
+    for line in notice {
+        st.add_system_output(line);
+    }
+    next
+}
+
+/// The tab bar's entries, in session order.
+fn session_tabs(sessions: &[Session], active: usize) -> Vec<SessionTab> {
+    sessions
+        .iter()
+        .enumerate()
+        .map(|(i, session)| SessionTab {
+            name: session.name.clone(),
+            active: i == active,
+            activity: session.activity.load(Ordering::Acquire),
+        })
+        .collect()
+}
+
+/// The next terminal event, or `None` once the stream has ended.
+async fn next_event(events: &mut EventStream) -> Option<io::Result<CEvent>> {
+    std::future::poll_fn(|cx| Pin::new(&mut *events).poll_next(cx)).await
+}
+
+/// Whether Enter with `modifiers` is the configured newline key.
+fn is_newline_key(key: NewlineKey, modifiers: KeyModifiers) -> bool {
+    match key {
+        NewlineKey::ShiftEnter => modifiers.contains(KeyModifiers::SHIFT),
+        NewlineKey::CtrlEnter => modifiers.contains(KeyModifiers::CONTROL),
+        NewlineKey::AltEnter => modifiers.contains(KeyModifiers::ALT),
+        NewlineKey::Off => false,
+    }
+}
+
+/// Set while the terminal's keyboard enhancement flags are pushed.
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Raw mode, alternate screen, mouse capture and bracketed paste, undone on drop
+/// (including while unwinding from a panic). Mouse capture is switched off on
+/// exit whether or not it is on at the time.
+struct TerminalGuard {
+    _panic_hook: PanicHookGuard,
+}
+
+impl TerminalGuard {
+    /// Sets up the terminal and installs a panic hook that restores it before the
+    /// panic message is printed. The hook also covers panics in spawned tasks,
+    /// which never unwind through `main`.
+    fn enter(mouse_capture: bool) -> io::Result<Self> {
+        let guard = Self { _panic_hook: PanicHookGuard::install(restore_terminal) };
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+        if mouse_capture {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        // Lets terminals that can report Shift+Enter apart from Enter do so.
+        if crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+            KEYBOARD_ENHANCED.store(true, Ordering::SeqCst);
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+type PanicHook = dyn Fn(&std::panic::PanicHookInfo<'_>) + Sync + Send + 'static;
+
+/// A panic hook that runs `restore` before the hook it replaced, which is put
+/// back on drop.
+struct PanicHookGuard {
+    previous: Arc<Box<PanicHook>>,
+}
+
+impl PanicHookGuard {
+    fn install(restore: fn()) -> Self {
+        let previous: Arc<Box<PanicHook>> = Arc::new(std::panic::take_hook());
+        let chained = previous.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            error!("Panic: {}", info);
+            chained(info);
+        }));
+        Self { previous }
+    }
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        // The hook cannot be swapped while unwinding; the process is exiting then anyway.
+        if std::thread::panicking() {
+            return;
+        }
+        let previous = self.previous.clone();
+        std::panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+/// Puts the terminal back into its normal state. Safe to call more than once.
+fn restore_terminal() {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        crossterm::cursor::Show
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    static RESTORED: AtomicUsize = AtomicUsize::new(0);
+    static PREVIOUS_RAN: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_restore() {
+        RESTORED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn panic_hook_restores_then_is_removed_on_drop() {
+        std::panic::set_hook(Box::new(|_| {
+            PREVIOUS_RAN.fetch_add(1, Ordering::SeqCst);
+        }));
+        let guard = PanicHookGuard::install(count_restore);
+        assert!(std::panic::catch_unwind(|| panic!("inside the guard")).is_err());
+        assert_eq!(RESTORED.load(Ordering::SeqCst), 1);
+        assert_eq!(PREVIOUS_RAN.load(Ordering::SeqCst), 1);
+
+        drop(guard);
+        assert!(std::panic::catch_unwind(|| panic!("after the guard")).is_err());
+        assert_eq!(RESTORED.load(Ordering::SeqCst), 1);
+        assert_eq!(PREVIOUS_RAN.load(Ordering::SeqCst), 2);
+        let _ = std::panic::take_hook();
+    }
+}
@@ -0,0 +1,890 @@
+// src/ui.rs - Drawing the TUI
+use crate::ansi_color::strip_color_markers;
+use crate::app::{AppState, Pane, BELL_FLASH, TELL_FLASH};
+use crate::config::{ChatConfig, GroupPanel};
+use crate::gauges::{
+    render_enemy_gauge, render_group_summary, render_hp_gauge, render_mana_gauge, render_mv_gauge, render_tnl_gauge,
+};
+use crate::skills::SkillState;
+use crate::styled::{Rgb, StyledLine};
+use crate::telnet_client;
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Rect};
+use ratatui::prelude::Backend;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Clear, Padding, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
+};
+use std::collections::HashMap;
+use std::io;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A session's entry in the tab bar.
+pub(crate) struct SessionTab {
+    pub(crate) name: String,
+    pub(crate) active: bool,
+    /// New output since the session was last shown.
+    pub(crate) activity: bool,
+}
+
+/// Renders the gauges on one horizontal line.
+/// The personal gauges (HP, MN, MV) are built from char.vitals and char.maxstats.
+/// If group info is available and there is at least one enemy, an enemy gauge is appended.
+/// With more than one session open, a tab bar takes the top row.
+pub(crate) fn ui_draw<B: Backend>(f: &mut ratatui::Frame<B>, st: &mut AppState, tabs: &[SessionTab]) {
+    let mut outer = f.size();
+    if tabs.len() > 1 && outer.height > MIN_HEIGHT {
+        let tab_rect = Rect { height: 1, ..outer };
+        f.render_widget(Clear, tab_rect);
+        f.render_widget(Paragraph::new(tab_line(tabs)), tab_rect);
+        outer.y += 1;
+        outer.height -= 1;
+    }
+    if too_small(outer) {
+        let message = format!(
+            "Terminal too small ({}×{}, need ≥ {}×{})",
+            outer.width, outer.height, MIN_WIDTH, MIN_HEIGHT
+        );
+        f.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), outer);
+        st.pane_areas = [(Pane::Main, Rect::default()), (Pane::Chat, Rect::default())];
+        return;
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(0)
+        .constraints([Constraint::Ratio(3, 4), Constraint::Ratio(1, 4)].as_ref())
+        .split(outer);
+
+    // Gauges go on as few lines as fit; the gauge area grows to hold them.
+    let gauge_lines = gauge_lines(st, chunks[0].width.saturating_sub(2));
+
+    // The left pane is divided into output, status, gauge, and input areas.
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(1), // Status bar
+            Constraint::Length(gauge_lines.len().max(1) as u16 + 2), // Gauge area
+            Constraint::Length(3), // Input area
+        ])
+        .split(chunks[0]);
+    let main_rect = left_chunks[0];
+    let status_rect = left_chunks[1];
+    let gauge_rect = left_chunks[2];
+    let input_rect = left_chunks[3];
+    // The right pane holds the map (once there is a room to center it on),
+    // any active affects, the skills panel and the group above the chat.
+    let show_map = st.config.mapper.enabled && st.mapper.current().is_some();
+    let affect_lines = affect_lines(st);
+    let affects_height = if affect_lines.is_empty() { 0 } else { affect_lines.len().min(MAX_AFFECT_LINES) as u16 + 2 };
+    let skill_lines = if st.show_skills { skill_lines(st) } else { Vec::new() };
+    let skills_height = if skill_lines.is_empty() { 0 } else { skill_lines.len().min(MAX_SKILL_LINES) as u16 + 2 };
+    let group_lines = group_lines(st, chunks[1].width.saturating_sub(2) as usize);
+    let group_height = match st.group_panel {
+        _ if group_lines.is_empty() => 0,
+        GroupPanel::Compact => 1,
+        _ => group_lines.len().min(MAX_GROUP_LINES) as u16 + 2,
+    };
+    let right_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(if show_map { 11 } else { 0 }),
+            Constraint::Length(affects_height),
+            Constraint::Length(skills_height),
+            Constraint::Length(group_height),
+            Constraint::Min(3),
+        ])
+        .split(chunks[1]);
+    let map_rect = right_chunks[0];
+    let affects_rect = right_chunks[1];
+    let skills_rect = right_chunks[2];
+    let group_rect = right_chunks[3];
+    let chat_rect = right_chunks[4];
+    st.pane_areas = [(Pane::Main, main_rect), (Pane::Chat, chat_rect)];
+
+    f.render_widget(Clear, main_rect);
+    f.render_widget(Clear, status_rect);
+    f.render_widget(Clear, gauge_rect);
+    f.render_widget(Clear, input_rect);
+    f.render_widget(Clear, chat_rect);
+
+    if show_map {
+        f.render_widget(Clear, map_rect);
+        let map_lines: Vec<Line> = st
+            .mapper
+            .render(map_rect.width.saturating_sub(2), map_rect.height.saturating_sub(2))
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        let map_par = Paragraph::new(map_lines)
+            .block(Block::default().borders(Borders::ALL).title(" Map "))
+            .style(Style::default().fg(Color::Gray));
+        f.render_widget(map_par, map_rect);
+    }
+
+    if affects_height > 0 {
+        f.render_widget(Clear, affects_rect);
+        let affects_par = Paragraph::new(affect_lines)
+            .block(Block::default().borders(Borders::ALL).title(" Affects "));
+        f.render_widget(affects_par, affects_rect);
+    }
+
+    if skills_height > 0 {
+        f.render_widget(Clear, skills_rect);
+        let skills_par = Paragraph::new(skill_lines)
+            .block(Block::default().borders(Borders::ALL).title(" Skills "));
+        f.render_widget(skills_par, skills_rect);
+    }
+
+    if group_height > 0 {
+        f.render_widget(Clear, group_rect);
+        let group_par = Paragraph::new(group_lines);
+        let group_par = match (st.group_panel, &st.group_info) {
+            (GroupPanel::Full, Some(group)) => {
+                group_par.block(Block::default().borders(Borders::ALL).title(format!(" Group: {} ", group.groupname)))
+            }
+            // The compact line lines up with the bordered panes' contents.
+            _ => group_par.block(Block::default().padding(Padding::horizontal(1))),
+        };
+        f.render_widget(group_par, group_rect);
+    }
+
+    let total_main = st.mud_output.len();
+    let main_width = main_rect.width.saturating_sub(2) as usize;
+    let lines_main: Vec<Line> = st
+        .mud_output
+        .iter()
+        .map(|lv| Line::from(fill_background(lv.spans.clone(), main_width)))
+        .collect();
+    let visible_height_main = main_rect.height.saturating_sub(2);
+    st.main_view_height = visible_height_main;
+    st.main_view_width = main_width as u16;
+    st.scroll_offset = st.scroll_offset.min(max_scroll(lines_main.len(), visible_height_main));
+    let scroll_top_main = scroll_top(lines_main.len(), visible_height_main, st.scroll_offset);
+    let main_title = if st.scroll_offset > 0 && st.config.ui.scroll_lock_title {
+        Span::styled(" MUD Output [SCROLL LOCK] ", Style::default().fg(Color::Black).bg(Color::Yellow))
+    } else {
+        Span::raw(" MUD Output ")
+    };
+    let mud_par = Paragraph::new(lines_main)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(if st.bell_flash.is_some_and(|t| t.elapsed() < BELL_FLASH) {
+                    Style::default().fg(Color::LightRed)
+                } else {
+                    pane_border(st.focus == Pane::Main)
+                })
+                .title(main_title),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((scroll_top_main, 0));
+    f.render_widget(mud_par, main_rect);
+    render_scrollbar(f, main_rect, total_main, visible_height_main, st.scroll_offset);
+
+    let total_chat = st.chat_output.len();
+    let lines_chat: Vec<Line> = st
+        .chat_output
+        .iter()
+        .map(|lv| Line::from(lv.clone()))
+        .collect();
+    let visible_height_chat = chat_rect.height.saturating_sub(2);
+    st.chat_view_height = visible_height_chat;
+    st.chat_scroll_offset = st.chat_scroll_offset.min(max_scroll(lines_chat.len(), visible_height_chat));
+    let scroll_top_chat = scroll_top(lines_chat.len(), visible_height_chat, st.chat_scroll_offset);
+    let chat_title = if st.unread_chat > 0 {
+        Span::styled(
+            format!(" Chat ({} new) ", st.unread_chat),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        )
+    } else {
+        Span::raw(" Chat ")
+    };
+    let chat_par = Paragraph::new(lines_chat)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(pane_border(st.focus == Pane::Chat))
+                .title(chat_title),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((scroll_top_chat, 0));
+    f.render_widget(chat_par, chat_rect);
+    render_scrollbar(f, chat_rect, total_chat, visible_height_chat, st.chat_scroll_offset);
+
+    f.render_widget(Paragraph::new(status_line(st, status_rect.width)), status_rect);
+
+    let gauge_par = Paragraph::new(gauge_lines)
+        .block(Block::default().borders(Borders::ALL).title(" Gauges "));
+    f.render_widget(gauge_par, gauge_rect);
+
+    let input_title = if st.movement_mode {
+        Span::styled(" Input [MOVE] ", Style::default().fg(Color::LightGreen))
+    } else {
+        Span::raw(" Input ")
+    };
+    let shown_input = displayed_input(&st.input, st.password_mode);
+    let cursor_col = display_width(&displayed_input(&st.input[..st.cursor], st.password_mode));
+    let input_width = input_rect.width.saturating_sub(2) as usize;
+    let (visible_input, visible_cursor) = input_window(&shown_input, cursor_col, input_width, &mut st.input_scroll);
+    let inp_par = Paragraph::new(visible_input)
+        .block(Block::default().borders(Borders::ALL).title(input_title))
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(inp_par, input_rect);
+
+    let cursor_x = input_rect.x + (visible_cursor as u16).min(input_rect.width.saturating_sub(2)) + 1;
+    let cursor_y = input_rect.y + 1;
+    if cursor_x < f.size().width && cursor_y < f.size().height {
+        f.set_cursor(cursor_x, cursor_y);
+    }
+
+    if st.show_tells {
+        // Newest at the bottom, sized to the list.
+        let height = (st.tells.len().max(1) as u16 + 2).min(main_rect.height);
+        let area = Rect { y: main_rect.y + main_rect.height - height, height, ..main_rect };
+        let skip = st.tells.len().saturating_sub(height.saturating_sub(2) as usize);
+        let lines: Vec<Line> = if st.tells.is_empty() {
+            vec![Line::from("No tells yet")]
+        } else {
+            st.tells.iter().skip(skip).map(|spans| Line::from(spans.clone())).collect()
+        };
+        f.render_widget(Clear, area);
+        let tells_par = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::LightYellow))
+                .title(" Recent tells — F7/Esc close "),
+        );
+        f.render_widget(tells_par, area);
+    }
+
+    if let Some(inspector) = st.inspector.as_mut() {
+        let area = outer.inner(&Margin { vertical: 1, horizontal: 2 });
+        let lines = inspector.lines(area.height.saturating_sub(2) as usize);
+        f.render_widget(Clear, area);
+        let inspector_par = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::LightYellow))
+                .title(" GMCP Inspector — ↑/↓ move, ←/→/Enter fold, F5 refresh, Esc close "),
+        );
+        f.render_widget(inspector_par, area);
+    }
+}
+
+/// The tab bar: ` 1 host ` per session, the shown one highlighted and ones
+/// with new output marked with `*`.
+fn tab_line(tabs: &[SessionTab]) -> Line<'static> {
+    let spans = tabs
+        .iter()
+        .enumerate()
+        .map(|(i, tab)| {
+            let marker = if tab.activity && !tab.active { "*" } else { "" };
+            let style = if tab.active {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else if tab.activity {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            Span::styled(format!(" {} {}{} ", i + 1, tab.name, marker), style)
+        })
+        .collect::<Vec<_>>();
+    Line::from(spans)
+}
+
+/// Smallest terminal the layout fits: the output pane, status bar, one gauge
+/// line and the input box (12 rows), with room beside the chat column.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
+/// Whether `area` is too small for `ui_draw`'s layout.
+fn too_small(area: Rect) -> bool {
+    area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+/// Renders the gauges and packs them into lines at most `width` columns wide,
+/// never splitting a gauge.
+fn gauge_lines(st: &AppState, width: u16) -> Vec<Line<'static>> {
+    let label = st.gauge_label;
+    let glyphs = st.config.ui.gauge_glyphs;
+    let colors = &st.config.gauge_colors;
+    let mut gauges: Vec<Vec<Span<'static>>> = Vec::new();
+    if let (Some(vitals), Some(maxstats)) = (&st.gmcp_vitals, &st.gmcp_maxstats) {
+        gauges.push(render_hp_gauge(vitals.hp, maxstats.maxhp, label, glyphs, &colors.hp.label_colors()));
+        gauges.push(render_mana_gauge(vitals.mana, maxstats.maxmana, label, glyphs, &colors.mana.label_colors()));
+        gauges.push(render_mv_gauge(vitals.movement, maxstats.maxmove, label, glyphs, &colors.moves.label_colors()));
+    }
+    if let Some(progress) = &st.tnl_progress {
+        let per_level = st.config.ui.xp_per_level;
+        gauges.extend(render_tnl_gauge(progress, per_level, label, glyphs, &colors.xp.label_colors()));
+    }
+    // The enemy's hp come from group info when available, otherwise from
+    // char.status's percentage; the gauge is labelled with its name.
+    let status_enemy = st.char_status.as_ref().filter(|s| !s.enemy.is_empty());
+    let enemy_colors = colors.enemy.label_colors();
+    if let Some(enemy) = st.group_info.as_ref().and_then(|g| g.enemies.first()) {
+        let name = status_enemy.map_or(enemy.name.as_str(), |s| s.enemy.as_str());
+        gauges.push(render_enemy_gauge(name, enemy.info.hp, enemy.info.mhp, label, glyphs, &enemy_colors));
+    } else if let Some(status) = status_enemy {
+        gauges.push(render_enemy_gauge(&status.enemy, status.enemypct, 100, label, glyphs, &enemy_colors));
+    }
+
+    let mut lines: Vec<Vec<Span<'static>>> = Vec::new();
+    let mut used = 0;
+    for gauge in gauges {
+        let gauge_width: usize = gauge.iter().map(|s| display_width(&s.content)).sum();
+        match lines.last_mut() {
+            Some(line) if used + 2 + gauge_width <= width as usize => {
+                line.push(Span::raw("  "));
+                line.extend(gauge);
+                used += 2 + gauge_width;
+            }
+            _ => {
+                lines.push(gauge);
+                used = gauge_width;
+            }
+        }
+    }
+    lines.into_iter().map(Line::from).collect()
+}
+
+/// Widens a trailing empty span that carries a background color (added for
+/// `ui.fill_background`) into spaces reaching the pane's right edge. Lines
+/// that wrap are left alone, as where their last row ends isn't known here.
+fn fill_background(mut spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    if let Some(last) = spans.last() {
+        if last.content.is_empty() && last.style.bg.is_some() {
+            let used: usize = spans.iter().map(|s| display_width(&s.content)).sum();
+            if used <= width {
+                let style = last.style;
+                spans.pop();
+                spans.push(Span::styled(" ".repeat(width - used), style));
+            }
+        }
+    }
+    spans
+}
+
+/// Most affects listed before the panel stops growing.
+const MAX_AFFECT_LINES: usize = 8;
+
+/// One line per active affect with its countdown (m:ss), or "--" when the
+/// server gave no duration.
+fn affect_lines(st: &mut AppState) -> Vec<Line<'static>> {
+    st.affects
+        .remaining()
+        .into_iter()
+        .map(|(name, left)| {
+            let time = match left {
+                Some(left) => format!("{}:{:02}", left.as_secs() / 60, left.as_secs() % 60),
+                None => "--".to_string(),
+            };
+            Line::from(vec![
+                Span::styled(format!("{:>6} ", time), Style::default().fg(Color::LightCyan)),
+                Span::raw(name.to_string()),
+            ])
+        })
+        .collect()
+}
+
+/// Most skills listed before the panel stops growing.
+const MAX_SKILL_LINES: usize = 10;
+
+/// One line per skill: "ready", its cooldown (m:ss, or seconds with a tenth
+/// below ten seconds) or "--" when it is unavailable with no known end.
+fn skill_lines(st: &AppState) -> Vec<Line<'static>> {
+    st.skills
+        .states()
+        .into_iter()
+        .map(|(name, state)| {
+            let (time, color) = match state {
+                SkillState::Ready => ("ready".to_string(), Color::LightGreen),
+                SkillState::Cooldown(left) if left.as_secs() < 10 => (format!("{:.1}s", left.as_secs_f64()), Color::Yellow),
+                SkillState::Cooldown(left) => {
+                    (format!("{}:{:02}", left.as_secs() / 60, left.as_secs() % 60), Color::Yellow)
+                }
+                SkillState::Unavailable => ("--".to_string(), Color::DarkGray),
+            };
+            Line::from(vec![
+                Span::styled(format!("{:>6} ", time), Style::default().fg(color)),
+                Span::raw(name.to_string()),
+            ])
+        })
+        .collect()
+}
+
+/// What the UI shows that changes with time alone: the status bar (tick
+/// countdown, ping, tell flash), the affect and skill countdowns and the bell
+/// flash. The UI loop's tick redraws only when this changed.
+#[derive(PartialEq, Eq)]
+pub(crate) struct TimedView {
+    status: Line<'static>,
+    affects: Vec<Line<'static>>,
+    skills: Vec<Line<'static>>,
+    bell_flash: bool,
+}
+
+pub(crate) fn timed_view(st: &mut AppState) -> TimedView {
+    TimedView {
+        status: status_line(st, st.term_size.0),
+        affects: affect_lines(st),
+        skills: if st.show_skills { skill_lines(st) } else { Vec::new() },
+        bell_flash: st.bell_flash.is_some_and(|t| t.elapsed() < BELL_FLASH),
+    }
+}
+
+/// Most group members listed before the full panel stops growing.
+const MAX_GROUP_LINES: usize = 8;
+
+/// The group panel's lines for `ui.group_panel`: one per member (name, then
+/// HP, mana and moves percentages) when full, or the one-line summary when
+/// compact. Members not in the room are dimmed and in parentheses.
+fn group_lines(st: &AppState, width: usize) -> Vec<Line<'static>> {
+    let Some(group) = st.group_info.as_ref().filter(|g| !g.members.is_empty()) else {
+        return Vec::new();
+    };
+    let colors = &st.config.gauge_colors;
+    match st.group_panel {
+        GroupPanel::Off => Vec::new(),
+        GroupPanel::Compact => vec![Line::from(render_group_summary(&group.members, width, &colors.hp.label_colors()))],
+        GroupPanel::Full => group
+            .members
+            .iter()
+            .map(|member| {
+                let info = &member.info;
+                let here = info.here != 0;
+                let name = if here { member.name.clone() } else { format!("({})", member.name) };
+                let percent = |current: i32, max: i32| if max > 0 { current as i64 * 100 / max as i64 } else { 0 };
+                let mut spans = vec![Span::styled(
+                    format!("{:<12}", truncate(&name, 12)),
+                    Style::default().fg(if here { Color::White } else { Color::DarkGray }),
+                )];
+                for (current, max, label) in [
+                    (info.hp, info.mhp, colors.hp.label_colors()),
+                    (info.mn, info.mmn, colors.mana.label_colors()),
+                    (info.mv, info.mmv, colors.moves.label_colors()),
+                ] {
+                    let pct = percent(current, max);
+                    let mut style = Style::default().fg(label.color_for(pct));
+                    if !here {
+                        style = style.add_modifier(Modifier::DIM);
+                    }
+                    spans.push(Span::styled(format!(" {:>3}%", pct), style));
+                }
+                Line::from(spans)
+            })
+            .collect(),
+    }
+}
+
+/// `text` cut to `width` characters, ending in "…" when it was longer.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{}…", kept)
+}
+
+/// Border style marking the pane that keyboard scrolling acts on.
+fn pane_border(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(Color::LightYellow)
+    } else {
+        Style::default()
+    }
+}
+
+/// Draws a scrollbar over the right border of a bordered pane showing which
+/// part of `total` lines is on screen. Nothing is drawn while everything fits
+/// or while the pane follows the newest line (`offset` 0).
+fn render_scrollbar<B: Backend>(f: &mut ratatui::Frame<B>, area: Rect, total: usize, visible: u16, offset: u16) {
+    let max = max_scroll(total, visible);
+    if max == 0 || offset == 0 {
+        return;
+    }
+    // The widget places the thumb at position / content_length, so scale the
+    // first visible row so that the oldest page puts it at the top and the
+    // newest at the bottom.
+    let content = total.min(u16::MAX as usize) as u16;
+    let top = scroll_top(total, visible, offset) as usize;
+    let position = (top * content as usize / max as usize) as u16;
+    let mut state = ScrollbarState::default()
+        .content_length(content)
+        .viewport_content_length(visible)
+        .position(position);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .symbols(ratatui::symbols::scrollbar::VERTICAL)
+        .thumb_style(Style::default().fg(Color::Gray))
+        .track_style(Style::default().fg(Color::DarkGray));
+    f.render_stateful_widget(scrollbar, area.inner(&Margin { vertical: 1, horizontal: 0 }), &mut state);
+}
+
+/// Largest useful scroll offset: the one that puts the oldest line at the top of
+/// a pane `visible` rows tall. Zero when everything already fits.
+pub(crate) fn max_scroll(total: usize, visible: u16) -> u16 {
+    total.saturating_sub(visible as usize).min(u16::MAX as usize) as u16
+}
+
+/// First row to draw for a pane scrolled `offset` lines back from the newest line.
+pub(crate) fn scroll_top(total: usize, visible: u16, offset: u16) -> u16 {
+    max_scroll(total, visible).saturating_sub(offset)
+}
+
+/// The input line as drawn: masked at password prompts, with pasted line breaks shown as ⏎.
+pub(crate) fn displayed_input(input: &str, password_mode: bool) -> String {
+    if password_mode {
+        "*".repeat(input.chars().count())
+    } else {
+        input.replace('\n', "⏎")
+    }
+}
+
+/// Picks the part of the input line to show in a box `width` columns wide so
+/// the cursor stays visible, marking clipped text with `<` and `>`.
+///
+/// `shown` is the input as displayed and `cursor_col` the cursor's display
+/// column in it. `scroll` is the first column shown; it is kept between draws
+/// and only moves when the cursor would leave the window. Returns the text to
+/// draw and the cursor's column within it.
+pub(crate) fn input_window(shown: &str, cursor_col: usize, width: usize, scroll: &mut usize) -> (String, usize) {
+    let total = display_width(shown);
+    // Everything fits, including the cursor after the last character.
+    if total < width || width < 3 {
+        *scroll = 0;
+        return (shown.to_string(), cursor_col);
+    }
+    // One column is kept on each side for the clip markers.
+    let avail = width - 2;
+    if cursor_col < *scroll {
+        *scroll = cursor_col;
+    } else if cursor_col >= *scroll + avail {
+        *scroll = cursor_col + 1 - avail;
+    }
+    // Don't leave blank space after the end of the input.
+    *scroll = (*scroll).min(total + 1 - avail);
+
+    let end = *scroll + avail;
+    let mut text = String::from(if *scroll > 0 { '<' } else { ' ' });
+    let mut col = 0;
+    // Whether the last character with a width was drawn whole.
+    let mut base_shown = false;
+    for ch in shown.chars() {
+        let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if w == 0 {
+            // A combining mark goes with the character before it.
+            if base_shown {
+                text.push(ch);
+            }
+            continue;
+        }
+        base_shown = col >= *scroll && col + w <= end;
+        if base_shown {
+            text.push(ch);
+        } else if col < end && col + w > *scroll {
+            // A wide character cut by either edge.
+            text.push(' ');
+        }
+        col += w;
+    }
+    let drawn = display_width(&text) - 1;
+    text.push_str(&" ".repeat(avail.saturating_sub(drawn)));
+    text.push(if total > end { '>' } else { ' ' });
+    (text, cursor_col - *scroll + 1)
+}
+
+/// Number of terminal columns `text` occupies. Combining marks take none and
+/// full-width (e.g. CJK) characters take two.
+pub(crate) fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Rings the terminal bell.
+pub(crate) fn ring_bell() {
+    use std::io::Write;
+    let mut out = io::stdout();
+    let _ = out.write_all(b"\x07");
+    let _ = out.flush();
+}
+
+/// Builds the one-line status bar: connection state, current room and exits, and level/TNL.
+/// The room segment is shortened with an ellipsis when the bar is wider than `width`.
+pub(crate) fn status_line(st: &AppState, width: u16) -> Line<'static> {
+    let (conn_text, conn_color) = if st.connected {
+        ("● Connected", Color::Green)
+    } else {
+        ("○ Disconnected", Color::Red)
+    };
+    let mut level_text = match &st.char_status {
+        Some(status) => format!(" | Lvl {} TNL {}{}", status.level, status.tnl, combat_text(&status.state, &status.enemy)),
+        None => String::new(),
+    };
+    if st.connected && st.config.connection.ping_interval_secs > 0 {
+        match st.latency {
+            Some(latency) => level_text.push_str(&format!(" | ping {}ms", latency.as_millis())),
+            None => level_text.push_str(" | ping n/a"),
+        }
+    }
+    if let Some(tick) = st.tick.as_ref().filter(|_| st.connected) {
+        if let Some(text) = tick.status(std::time::Instant::now()) {
+            level_text.push_str(&format!(" | {}", text));
+        }
+    }
+    if st.away.is_some() {
+        level_text.push_str(" | AFK");
+    }
+    if st.bells > 0 {
+        level_text.push_str(&format!(" | Bells: {}", st.bells));
+    }
+    let mut room_text = match &st.current_room {
+        Some(room) => format!(" | {} ({})", room.name, room.zone),
+        None => String::new(),
+    };
+    let exits_text = match &st.current_room {
+        Some(room) if !room.exits.is_empty() => format!(" | Exits: {}", exit_list(&room.exits)),
+        Some(_) => " | Exits: none".to_string(),
+        None => String::new(),
+    };
+
+    let tells_text = match st.unread_tells {
+        0 => String::new(),
+        1 => " | ✉ 1 tell (F7)".to_string(),
+        n => format!(" | ✉ {} tells (F7)", n),
+    };
+    let mut tells_style = Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD);
+    if st.tell_flash.is_some_and(|t| t.elapsed() < TELL_FLASH) {
+        tells_style = tells_style.add_modifier(Modifier::REVERSED);
+    }
+
+    let fixed = conn_text.chars().count()
+        + exits_text.chars().count()
+        + level_text.chars().count()
+        + tells_text.chars().count();
+    let room_budget = (width as usize).saturating_sub(fixed);
+    if room_text.chars().count() > room_budget {
+        room_text = if room_budget > 1 {
+            let kept: String = room_text.chars().take(room_budget - 1).collect();
+            format!("{}…", kept)
+        } else {
+            String::new()
+        };
+    }
+
+    Line::from(vec![
+        Span::styled(conn_text, Style::default().fg(conn_color)),
+        Span::styled(room_text, Style::default().fg(Color::Magenta)),
+        Span::styled(exits_text, Style::default().fg(Color::LightGreen)),
+        Span::styled(level_text, Style::default().fg(Color::Cyan)),
+        Span::styled(tells_text, tells_style),
+    ])
+}
+
+/// " | Fighting: a goblin", " | Resting", or nothing when there is no state.
+fn combat_text(state: &str, enemy: &str) -> String {
+    let mut chars = state.chars();
+    let Some(first) = chars.next() else {
+        return String::new();
+    };
+    let state: String = first.to_uppercase().chain(chars).collect();
+    if enemy.is_empty() {
+        format!(" | {}", state)
+    } else {
+        format!(" | {}: {}", state, enemy)
+    }
+}
+
+/// Lists exit directions in compass order ("n, e, up"), abbreviating the
+/// compass points; anything else (e.g. "portal") follows alphabetically.
+pub(crate) fn exit_list(exits: &HashMap<String, i32>) -> String {
+    const ORDER: [(&str, &str, &str); 10] = [
+        ("n", "north", "n"),
+        ("ne", "northeast", "ne"),
+        ("e", "east", "e"),
+        ("se", "southeast", "se"),
+        ("s", "south", "s"),
+        ("sw", "southwest", "sw"),
+        ("w", "west", "w"),
+        ("nw", "northwest", "nw"),
+        ("u", "up", "up"),
+        ("d", "down", "down"),
+    ];
+    let rank = |dir: &str| {
+        let dir = dir.to_lowercase();
+        ORDER
+            .iter()
+            .position(|(short, long, _)| dir == *short || dir == *long)
+            .map_or((ORDER.len(), dir.clone()), |i| (i, ORDER[i].2.to_string()))
+    };
+    let mut dirs: Vec<(usize, String)> = exits.keys().map(|d| rank(d)).collect();
+    dirs.sort();
+    dirs.into_iter().map(|(_, name)| name).collect::<Vec<_>>().join(", ")
+}
+
+/// Builds the `[channel] player: message` chat lines using the configured
+/// colors. A message with line breaks (news posts and the like) becomes one
+/// line per break, the later ones indented under the first line's text;
+/// colors carry over the breaks.
+pub(crate) fn chat_lines(chat: &ChatConfig, channel: &str, player: &str, message: &str) -> Vec<Vec<Span<'static>>> {
+    let colors = chat.colors_for(channel);
+    let tag = format!("[{}] ", channel);
+    let name = format!("{}: ", player);
+    let indent = " ".repeat(tag.width() + name.width());
+    let mut lines = vec![vec![
+        Span::styled(tag, Style::default().fg(colors.tag)),
+        Span::styled(name, Style::default().fg(colors.player)),
+    ]];
+    for (i, line) in telnet_client::parse_gmcp_lines(message).iter().enumerate() {
+        if i > 0 {
+            lines.push(vec![Span::raw(indent.clone())]);
+        }
+        lines.last_mut().expect("starts with one line").extend(to_spans(line, colors.message));
+    }
+    lines
+}
+
+impl From<Rgb> for Color {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Color::Rgb(r, g, b)
+    }
+}
+
+/// The text of an output line without styling. Inline `$`-color markers that
+/// reached the pane unparsed (e.g. `$x196`) are left out too, so copying and
+/// logging see the text the player reads.
+pub(crate) fn plain_text(spans: &[Span]) -> String {
+    let raw: String = spans.iter().map(|s| s.content.as_ref()).collect();
+    strip_color_markers(&raw)
+}
+
+/// Converts a parsed line into spans, drawing uncolored text in `default_fg`.
+pub(crate) fn to_spans(line: &StyledLine, default_fg: Color) -> Vec<Span<'static>> {
+    line.segments
+        .iter()
+        .map(|segment| {
+            let style = &segment.style;
+            let mut out = Style::default().fg(style.fg.map_or(default_fg, Color::from));
+            if let Some(bg) = style.bg {
+                out = out.bg(bg.into());
+            }
+            if style.bold {
+                out = out.add_modifier(Modifier::BOLD);
+            }
+            if style.italic {
+                out = out.add_modifier(Modifier::ITALIC);
+            }
+            // Hyperlinks are underlined; the terminal cannot be handed the URL itself.
+            if style.underline || segment.link.is_some() {
+                out = out.add_modifier(Modifier::UNDERLINED);
+            }
+            Span::styled(segment.text.clone(), out)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[Vec<Span<'static>>]) -> Vec<String> {
+        lines.iter().map(|spans| spans.iter().map(|s| s.content.as_ref()).collect()).collect()
+    }
+
+    #[test]
+    fn chat_message_with_line_breaks_becomes_indented_lines() {
+        let lines = chat_lines(&ChatConfig::default(), "news", "Bob", "$RFirst line\nsecond line");
+        assert_eq!(texts(&lines), ["[news] Bob: First line", "            second line"]);
+        // The color set before the break carries over it.
+        let second = lines[1].last().unwrap();
+        assert_eq!(second.style.fg, Some(Color::Rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn plain_text_joins_spans_and_drops_only_color_markers() {
+        let spans = vec![
+            Span::styled("You see $x", Style::default().fg(Color::Red)),
+            Span::raw("196a red gem$0 worth $05"),
+            Span::raw(" and $x here.\n"),
+        ];
+        assert_eq!(plain_text(&spans), "You see a red gem worth $05 and $x here.\n");
+    }
+
+    #[test]
+    fn plain_text_keeps_text_without_markers() {
+        let spans = vec![Span::raw("$"), Span::raw("5 for a "), Span::raw("sword")];
+        assert_eq!(plain_text(&spans), "$5 for a sword");
+    }
+
+    #[test]
+    fn input_that_fits_is_shown_whole() {
+        let mut scroll = 4;
+        assert_eq!(input_window("look", 2, 10, &mut scroll), ("look".to_string(), 2));
+        assert_eq!(scroll, 0);
+    }
+
+    #[test]
+    fn long_input_scrolls_to_keep_the_cursor_in_view() {
+        let input = "abcdefghijklmnop";
+        let mut scroll = 0;
+        // Cursor at the start: the end is clipped.
+        assert_eq!(input_window(input, 0, 10, &mut scroll), (" abcdefgh>".to_string(), 1));
+        // Cursor after the end: the start is clipped and a column is left for the cursor.
+        assert_eq!(input_window(input, 16, 10, &mut scroll), ("<jklmnop  ".to_string(), 8));
+        assert_eq!(scroll, 9);
+        // Moving back into the middle scrolls just far enough.
+        assert_eq!(input_window(input, 5, 10, &mut scroll), ("<fghijklm>".to_string(), 1));
+        // Moving within the window leaves it where it is.
+        assert_eq!(input_window(input, 8, 10, &mut scroll), ("<fghijklm>".to_string(), 4));
+        assert_eq!(scroll, 5);
+    }
+
+    #[test]
+    fn wide_characters_cut_by_the_edge_become_blanks() {
+        // Six CJK characters, two columns each.
+        let input = "你好世界你好";
+        let mut scroll = 0;
+        let (text, cursor) = input_window(input, 12, 10, &mut scroll);
+        assert_eq!(scroll, 5);
+        assert_eq!(text, "< 界你好  ");
+        assert_eq!(display_width(&text), 10);
+        assert_eq!(cursor, 8);
+
+        let (text, cursor) = input_window(input, 0, 10, &mut scroll);
+        assert_eq!(text, " 你好世界>");
+        assert_eq!(cursor, 1);
+    }
+
+    #[test]
+    fn layout_needs_forty_by_twelve() {
+        assert!(too_small(Rect::new(0, 0, 39, 12)));
+        assert!(too_small(Rect::new(0, 0, 40, 11)));
+        assert!(!too_small(Rect::new(0, 0, 40, 12)));
+    }
+
+    #[test]
+    fn timed_view_changes_only_with_time_dependent_content() {
+        let mut st = crate::app::tests::state();
+        let before = timed_view(&mut st);
+        st.add_system_output("Not time-dependent");
+        assert!(timed_view(&mut st) == before);
+        st.bell_flash = Some(std::time::Instant::now());
+        assert!(timed_view(&mut st) != before);
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_character() {
+        let input = "cafe\u{301} 你好 abc";
+        assert_eq!(display_width(input), 13);
+        let after_mark = "cafe\u{301}".len();
+        assert_eq!(display_width(&input[..after_mark]), 4);
+
+        let mut scroll = 0;
+        assert_eq!(input_window(input, 4, 10, &mut scroll), (" cafe\u{301} 你 >".to_string(), 5));
+        assert_eq!(input_window(input, display_width(input), 10, &mut scroll), ("< 好 abc  ".to_string(), 8));
+        // Scrolled to just past the accented "e": its mark is not drawn on its own.
+        assert_eq!(input_window(input, 4, 10, &mut scroll), ("< 你好 ab>".to_string(), 1));
+        assert_eq!(scroll, 4);
+    }
+}